@@ -0,0 +1,325 @@
+use db_proto::clients::Client;
+use db_proto::cmd::{Get, Set};
+use db_proto::pkg::Frame;
+use db_proto::DEFAULT_PORT;
+
+use bytes::Bytes;
+use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tokio::time;
+
+#[derive(Parser, Debug)]
+#[command(name = "db-bench", version)]
+struct Cli {
+    #[arg(id = "hostname", long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// Connect through a unix domain socket instead of TCP
+    #[arg(long)]
+    socket: Option<PathBuf>,
+
+    /// Username to authenticate as
+    #[arg(long)]
+    user: Option<String>,
+
+    /// Password to authenticate with
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Number of concurrent clients
+    #[arg(short, long, default_value_t = 50)]
+    clients: usize,
+
+    /// Total number of requests per workload
+    #[arg(short = 'n', long, default_value_t = 100_000)]
+    requests: u64,
+
+    /// Number of distinct keys to spread requests over
+    #[arg(short = 'r', long, default_value_t = 10_000)]
+    keyspace: u64,
+
+    /// Value size in bytes
+    #[arg(short = 'd', long, default_value_t = 3)]
+    data_size: usize,
+
+    /// Number of requests each client sends before reading the replies
+    #[arg(short = 'P', long, default_value_t = 1)]
+    pipeline: usize,
+
+    /// Fraction of GETs in the mixed workload
+    #[arg(long, default_value_t = 0.8)]
+    read_ratio: f64,
+
+    /// Number of subscribers in the pub/sub workload
+    #[arg(long, default_value_t = 10)]
+    subscribers: usize,
+
+    /// Workloads to run, in order
+    #[arg(short, long = "workload", value_enum, default_values_t = [Workload::Set, Workload::Get, Workload::Mixed])]
+    workloads: Vec<Workload>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Workload {
+    /// SET random keys
+    Set,
+    /// GET random keys
+    Get,
+    /// GETs and SETs according to --read-ratio
+    Mixed,
+    /// PUBLISH to one channel read by --subscribers clients
+    Pubsub,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    ops: u64,
+    errors: u64,
+    elapsed: Duration,
+    latencies: Vec<Duration>,
+}
+
+#[tokio::main]
+async fn main() -> db_proto::Result<()> {
+    let cli = Arc::new(Cli::parse());
+
+    for &workload in &cli.workloads {
+        let report = match workload {
+            Workload::Pubsub => pubsub(&cli).await?,
+            _ => commands(&cli, workload).await?,
+        };
+
+        report.print(&cli, workload);
+    }
+
+    Ok(())
+}
+
+async fn connect(cli: &Cli) -> db_proto::Result<Client> {
+    let mut client = match &cli.socket {
+        Some(socket) => Client::connect_unix(socket).await?,
+        None => Client::connect(format!("{}:{}", cli.host, cli.port)).await?,
+    };
+
+    if let Some(password) = &cli.password {
+        client.auth(cli.user.as_deref(), password).await?;
+    }
+
+    Ok(client)
+}
+
+async fn connect_all(cli: &Cli, n: usize) -> db_proto::Result<Vec<Client>> {
+    let mut clients = Vec::with_capacity(n);
+
+    for _ in 0..n {
+        clients.push(connect(cli).await?);
+    }
+
+    Ok(clients)
+}
+
+/// Every client claims `pipeline` requests at a time from a shared counter
+/// until `requests` have been issued. Each request in a batch is recorded
+/// with the round trip time of the whole batch.
+async fn commands(cli: &Arc<Cli>, workload: Workload) -> db_proto::Result<Report> {
+    let issued = Arc::new(AtomicU64::new(0));
+    let value = Bytes::from(vec![b'x'; cli.data_size]);
+    let mut tasks = JoinSet::new();
+    let start = Instant::now();
+
+    for (i, mut client) in connect_all(cli, cli.clients).await?.into_iter().enumerate() {
+        let (cli, issued, value) = (cli.clone(), issued.clone(), value.clone());
+
+        tasks.spawn(async move {
+            let mut rng = Rng::new(i as u64);
+            let mut report = Report::default();
+            let pipeline = cli.pipeline.max(1) as u64;
+
+            loop {
+                let claimed = issued.fetch_add(pipeline, Ordering::Relaxed);
+
+                if claimed >= cli.requests {
+                    break;
+                }
+
+                let batch = pipeline.min(cli.requests - claimed) as usize;
+                let frames: Vec<Frame> = (0..batch)
+                    .map(|_| {
+                        let key = format!("key:{:012}", rng.next() % cli.keyspace.max(1));
+                        let read = match workload {
+                            Workload::Get => true,
+                            Workload::Mixed => rng.next_f64() < cli.read_ratio,
+                            _ => false,
+                        };
+
+                        if read {
+                            Get::new(key).into_frame()
+                        } else {
+                            Set::new(key, value.clone(), None).into_frame()
+                        }
+                    })
+                    .collect();
+
+                let sent = Instant::now();
+                let replies = client.pipeline(&frames).await?;
+                let latency = sent.elapsed();
+
+                report.ops += batch as u64;
+                report.errors += replies.iter().filter(|reply| matches!(reply, Frame::Error(_))).count() as u64;
+                report.latencies.extend(std::iter::repeat_n(latency, batch));
+            }
+
+            Ok::<_, db_proto::Error>(report)
+        });
+    }
+
+    Report::merge(tasks, start).await
+}
+
+/// Publishers send `requests` messages in total, each stamped with the time
+/// since the start of the run; subscribers measure delivery latency from it.
+async fn pubsub(cli: &Arc<Cli>) -> db_proto::Result<Report> {
+    const CHANNEL: &str = "db_bench";
+
+    let mut subscribers = Vec::with_capacity(cli.subscribers);
+
+    for client in connect_all(cli, cli.subscribers).await? {
+        subscribers.push(client.subscribe(vec![CHANNEL.to_string()]).await?);
+    }
+
+    let publishers = connect_all(cli, cli.clients).await?;
+    let published = Arc::new(AtomicU64::new(0));
+    let mut receivers = JoinSet::new();
+    let mut senders = JoinSet::new();
+    let epoch = Instant::now();
+
+    for mut subscriber in subscribers {
+        let expected = cli.requests;
+
+        receivers.spawn(async move {
+            let mut report = Report::default();
+
+            while report.ops < expected {
+                let message = match time::timeout(Duration::from_secs(1), subscriber.next_message()).await {
+                    Ok(message) => message?,
+                    Err(_) => break,
+                };
+
+                let Some(message) = message else {
+                    break;
+                };
+
+                let sent = message.content.split(|&b| b == b':').next().and_then(atoi::atoi::<u64>).map(Duration::from_nanos);
+
+                match sent {
+                    Some(sent) => report.latencies.push(epoch.elapsed().saturating_sub(sent)),
+                    None => report.errors += 1,
+                }
+
+                report.ops += 1;
+            }
+
+            Ok::<_, db_proto::Error>(report)
+        });
+    }
+
+    for mut client in publishers {
+        let (cli, published) = (cli.clone(), published.clone());
+
+        senders.spawn(async move {
+            let mut report = Report::default();
+
+            while published.fetch_add(1, Ordering::Relaxed) < cli.requests {
+                let mut message = format!("{}:", epoch.elapsed().as_nanos()).into_bytes();
+                message.resize(message.len().max(cli.data_size), b'x');
+
+                client.publish(CHANNEL, Bytes::from(message)).await?;
+                report.ops += 1;
+            }
+
+            Ok::<_, db_proto::Error>(report)
+        });
+    }
+
+    let sent = Report::merge(senders, epoch).await?;
+    let mut report = Report::merge(receivers, epoch).await?;
+    report.errors += sent.ops * cli.subscribers as u64 - report.ops;
+
+    Ok(report)
+}
+
+impl Report {
+    async fn merge(mut tasks: JoinSet<db_proto::Result<Report>>, start: Instant) -> db_proto::Result<Report> {
+        let mut total = Report::default();
+
+        while let Some(report) = tasks.join_next().await {
+            let report = report??;
+            total.ops += report.ops;
+            total.errors += report.errors;
+            total.latencies.extend(report.latencies);
+        }
+
+        total.elapsed = start.elapsed();
+        total.latencies.sort_unstable();
+
+        Ok(total)
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let rank = ((p / 100.0) * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+
+    fn print(&self, cli: &Cli, workload: Workload) {
+        let name = format!("{:?}", workload).to_uppercase();
+        let unit = if workload == Workload::Pubsub { "messages delivered" } else { "requests" };
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+
+        println!("====== {} ======", name);
+        println!("  {} {} completed in {:.2} seconds", self.ops, unit, self.elapsed.as_secs_f64());
+        println!("  {} parallel clients, {} byte payload, pipeline {}", cli.clients, cli.data_size, cli.pipeline.max(1));
+
+        if workload == Workload::Pubsub {
+            println!("  {} subscribers, {} messages lost", cli.subscribers, self.errors);
+        } else if self.errors > 0 {
+            println!("  {} errors", self.errors);
+        }
+
+        println!("  throughput: {:.2} {}/sec", self.ops as f64 / self.elapsed.as_secs_f64(), unit.split(' ').next().unwrap());
+        println!(
+            "  latency (msec): p50={:.3} p99={:.3} p999={:.3} max={:.3}",
+            ms(self.percentile(50.0)),
+            ms(self.percentile(99.0)),
+            ms(self.percentile(99.9)),
+            ms(self.latencies.last().copied().unwrap_or_default())
+        );
+        println!();
+    }
+}
+
+/// xorshift64, seeded per client so runs spread over the keyspace.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng { Rng(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1) }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn next_f64(&mut self) -> f64 { (self.next() >> 11) as f64 / (1u64 << 53) as f64 }
+}
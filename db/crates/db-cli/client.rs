@@ -1,4 +1,9 @@
-use db_proto::{clients::Client, DEFAULT_PORT};
+mod repl;
+mod transfer;
+
+use db_proto::{clients::Client, pkg::registry::Filter, pkg::tls::TlsConfig, DEFAULT_PORT};
+
+use transfer::Format;
 
 use bytes::Bytes;
 use clap::{Parser, Subcommand};
@@ -6,18 +11,64 @@ use std::num::ParseIntError;
 use std::path::PathBuf;
 use std::str;
 use std::time::Duration;
+use tokio_stream::StreamExt;
+
+/// Errors from the shell and transfer modules come from several crates, so
+/// they are boxed rather than forced into `db_proto::Error`.
+type Result<T, E = Box<dyn std::error::Error + Send + Sync>> = std::result::Result<T, E>;
 
 #[derive(Parser, Debug)]
 #[command(name = "db-client", version)]
 struct Cli {
+    /// Command to run; starts an interactive shell when omitted
     #[clap(subcommand)]
-    command: Command,
+    command: Option<Command>,
 
     #[arg(id = "hostname", long, default_value = "127.0.0.1")]
     host: String,
 
     #[arg(long, default_value_t = DEFAULT_PORT)]
     port: u16,
+
+    /// Connect through a unix domain socket instead of TCP
+    #[arg(long, conflicts_with = "tls")]
+    socket: Option<PathBuf>,
+
+    /// Username to authenticate as
+    #[arg(long)]
+    user: Option<String>,
+
+    /// Password to authenticate with
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Connect using TLS
+    #[arg(long)]
+    tls: bool,
+
+    /// CA certificate bundle used to verify the server
+    #[arg(long, requires = "tls")]
+    cacert: Option<PathBuf>,
+
+    /// Client certificate to authenticate with
+    #[arg(long, requires_all = ["tls", "key"])]
+    cert: Option<PathBuf>,
+
+    /// Private key for the client certificate
+    #[arg(long, requires = "cert")]
+    key: Option<PathBuf>,
+
+    /// Server name to verify against the certificate, defaults to the hostname
+    #[arg(long, requires = "tls")]
+    sni: Option<String>,
+
+    /// Logical database to SELECT after connecting
+    #[arg(short = 'n', long, default_value_t = 0)]
+    db: u64,
+
+    /// Pipeline raw RESP or inline commands read from stdin
+    #[arg(long)]
+    pipe: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -56,27 +107,278 @@ enum Command {
         /// Specific channel or channels
         channels: Vec<String>,
     },
+    /// Dump the database state to a file in the server's data directory.
     Dump {
-        /// Path to the output file (optional, defaults to "db-state.bin")
-        #[arg(long, default_value = "db-state.bin")]
-        output: PathBuf,
+        /// Name of the output file (optional, defaults to the server's snapshot file)
+        #[arg(long)]
+        output: Option<PathBuf>,
     },
-    /// Load the database state from a file.
+    /// Load the database state from a file in the server's data directory.
     Load {
-        /// Path to the input file (optional, defaults to "db-state.bin")
-        #[arg(long, default_value = "db-state.bin")]
-        input: PathBuf,
+        /// Name of the input file (optional, defaults to the server's snapshot file)
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Make the server a replica of another server, or `no one` to promote it.
+    Replicaof {
+        /// Hostname of the master, or `no`
+        host: String,
+
+        /// Port of the master, or `one`
+        port: String,
+    },
+    /// Show the replication role of the server.
+    Role,
+    /// Show information and statistics about the server.
+    Info {
+        /// Section to show
+        section: Option<String>,
+    },
+    /// Show the number of keys in the database.
+    Dbsize,
+    /// Move a key to another logical database.
+    Move {
+        /// Name of key to move
+        key: String,
+
+        /// Index of the target database
+        db: u64,
+    },
+    /// Swap the contents of two logical databases.
+    Swapdb {
+        /// Index of the first database
+        first: u64,
+
+        /// Index of the second database
+        second: u64,
+    },
+    /// Remove all keys from the selected database.
+    Flushdb {
+        /// Free the removed keys in the background
+        #[arg(long)]
+        r#async: bool,
+    },
+    /// Remove all keys from every database.
+    Flushall {
+        /// Free the removed keys in the background
+        #[arg(long)]
+        r#async: bool,
+    },
+    /// Show the server time.
+    Time,
+    /// Show the unix time of the last successful save.
+    Lastsave,
+    /// Inspect and manage client connections.
+    Client {
+        #[clap(subcommand)]
+        command: ClientCommand,
+    },
+    /// Stream every command processed by the server.
+    Monitor,
+    /// Read and change server configuration at runtime.
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Inspect the log of slow commands.
+    Slowlog {
+        #[clap(subcommand)]
+        command: SlowlogCommand,
+    },
+    /// Inspect per-command latency histograms.
+    Latency {
+        #[clap(subcommand)]
+        command: LatencyCommand,
+    },
+    /// Inspect and modify ACL users.
+    Acl {
+        #[clap(subcommand)]
+        command: AclCommand,
+    },
+    /// Inspect the cluster topology.
+    Cluster {
+        #[clap(subcommand)]
+        command: ClusterCommand,
+    },
+    /// Write all keys with their values and TTLs, iterating with SCAN.
+    Export {
+        /// Output file (optional, defaults to stdout)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+        format: Format,
+
+        /// Only export keys matching this glob-style pattern
+        #[arg(long = "match")]
+        pattern: Option<String>,
+
+        /// Number of keys to request per SCAN call
+        #[arg(long, default_value_t = 1000)]
+        count: u64,
+    },
+    /// Load keys written by `export`.
+    Import {
+        /// Input file (optional, defaults to stdin)
+        #[arg(long)]
+        input: Option<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ClientCommand {
+    /// List connected clients.
+    List,
+    /// Close client connections matching the given filters.
+    Kill {
+        /// Id of the client to kill
+        #[arg(long)]
+        id: Option<u64>,
+
+        /// Address of the client to kill
+        #[arg(long)]
+        addr: Option<String>,
+
+        /// Kill all clients authenticated as this user
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Suspend command processing for all clients.
+    Pause {
+        /// Pause duration in milliseconds
+        #[arg(value_parser = duration_from_ms_str)]
+        timeout: Duration,
+
+        /// Only pause write commands
+        #[arg(long)]
+        write: bool,
+    },
+    /// Resume command processing.
+    Unpause,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Show the parameters matching a glob-style pattern.
+    Get {
+        /// Pattern of parameter names, e.g. maxmemory*
+        pattern: String,
+    },
+    /// Change a parameter without restarting the server.
+    Set {
+        /// Parameter name
+        name: String,
+
+        /// New value
+        value: String,
+    },
+    /// Persist the running configuration to the server's config file.
+    Rewrite,
+}
+
+#[derive(Subcommand, Debug)]
+enum SlowlogCommand {
+    /// Show the most recent slow log entries.
+    Get {
+        /// Number of entries to show, -1 for all
+        #[arg(allow_negative_numbers = true)]
+        count: Option<i64>,
     },
+    /// Show the number of slow log entries.
+    Len,
+    /// Clear the slow log.
+    Reset,
+}
+
+#[derive(Subcommand, Debug)]
+enum LatencyCommand {
+    /// Show latency histograms, optionally only for the given commands.
+    Histogram {
+        /// Commands to show
+        commands: Vec<String>,
+    },
+    /// Reset all latency histograms.
+    Reset,
+}
+
+#[derive(Subcommand, Debug)]
+enum ClusterCommand {
+    /// Show the slot ranges served by each node.
+    Slots,
+    /// Show the nodes of the cluster.
+    Nodes,
+    /// Show the hash slot of a key.
+    Keyslot {
+        /// Key to hash
+        key: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AclCommand {
+    /// Create or modify a user with the given rules.
+    Setuser {
+        /// Name of user to modify
+        username: String,
+
+        /// ACL rules, e.g. `on >secret ~cache:* +@read`
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        rules: Vec<String>,
+    },
+    /// Show the rules of a user.
+    Getuser {
+        /// Name of user to show
+        username: String,
+    },
+    /// List all users and their rules.
+    List,
+    /// Show the user of the current connection.
+    Whoami,
 }
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> db_proto::Result<()> {
+async fn main() -> Result<()> {
     let cli = Cli::parse();
     let addr = format!("{}:{}", cli.host, cli.port);
 
-    let mut client = Client::connect(&addr).await?;
+    let mut client = if let Some(socket) = &cli.socket {
+        Client::connect_unix(socket).await?
+    } else if cli.tls {
+        let mut tls = TlsConfig::new();
+
+        if let Some(cacert) = &cli.cacert {
+            tls = tls.add_ca_file(cacert)?;
+        }
+        if let (Some(cert), Some(key)) = (&cli.cert, &cli.key) {
+            tls = tls.with_client_cert(&std::fs::read(cert)?, &std::fs::read(key)?)?;
+        }
+
+        Client::connect_tls(&addr, cli.sni.as_deref().unwrap_or(&cli.host), &tls).await?
+    } else {
+        Client::connect(&addr).await?
+    };
+
+    if let Some(password) = &cli.password {
+        client.auth(cli.user.as_deref(), password).await?;
+    }
+
+    if cli.db != 0 {
+        client.select(cli.db).await?;
+    }
+
+    if cli.pipe {
+        return transfer::pipe(&mut client).await;
+    }
 
-    match cli.command {
+    let Some(command) = cli.command else {
+        let host = cli.socket.as_ref().map(|path| path.display().to_string()).unwrap_or(addr);
+        return repl::run(client, host).await;
+    };
+
+    match command {
         Command::Ping { msg } => {
             let value = client.ping(msg).await?;
             if let Ok(string) = str::from_utf8(&value) {
@@ -119,13 +421,122 @@ async fn main() -> db_proto::Result<()> {
             }
         }
         Command::Dump { output } => {
-            client.dump(&output).await?;
-            println!("Database state dumped to {:?}", output);
+            client.dump(output.as_deref()).await?;
+            println!("OK");
         }
         Command::Load { input } => {
-            client.load(&input).await?;
-            println!("Database state loaded from {:?}", input);
+            client.load(input.as_deref()).await?;
+            println!("OK");
         }
+        Command::Replicaof { host, port } => {
+            if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+                client.replicaof(None).await?;
+            } else {
+                client.replicaof(Some((&host, port.parse()?))).await?;
+            }
+            println!("OK");
+        }
+        Command::Role => println!("{}", client.role().await?),
+        Command::Info { section } => print!("{}", client.info(section.as_deref()).await?.replace("\r\n", "\n")),
+        Command::Dbsize => println!("{}", client.dbsize().await?),
+        Command::Time => {
+            let time = client.time().await?;
+            println!("{}\n{}", time.as_secs(), time.subsec_micros());
+        }
+        Command::Lastsave => println!("{}", client.lastsave().await?),
+        Command::Client { command } => match command {
+            ClientCommand::List => print!("{}", client.client_list().await?),
+            ClientCommand::Kill { id, addr, user } => {
+                let filter = Filter { id, addr, user, skip: None };
+                println!("{}", client.client_kill(filter).await?);
+            }
+            ClientCommand::Pause { timeout, write } => {
+                client.client_pause(timeout, write).await?;
+                println!("OK");
+            }
+            ClientCommand::Unpause => {
+                client.client_unpause().await?;
+                println!("OK");
+            }
+        },
+        Command::Monitor => {
+            let lines = client.monitor().await?;
+            tokio::pin!(lines);
+
+            while let Some(line) = lines.next().await {
+                println!("{}", line?);
+            }
+        }
+        Command::Move { key, db } => println!("{}", client.move_key(&key, db).await? as u8),
+        Command::Swapdb { first, second } => {
+            client.swapdb(first, second).await?;
+            println!("OK");
+        }
+        Command::Flushdb { r#async } => {
+            client.flushdb(r#async).await?;
+            println!("OK");
+        }
+        Command::Flushall { r#async } => {
+            client.flushall(r#async).await?;
+            println!("OK");
+        }
+        Command::Config { command } => match command {
+            ConfigCommand::Get { pattern } => {
+                for (name, value) in client.config_get(&pattern).await? {
+                    println!("{} {}", name, value);
+                }
+            }
+            ConfigCommand::Set { name, value } => {
+                client.config_set(&[(name, value)]).await?;
+                println!("OK");
+            }
+            ConfigCommand::Rewrite => {
+                client.config_rewrite().await?;
+                println!("OK");
+            }
+        },
+        Command::Slowlog { command } => match command {
+            SlowlogCommand::Get { count } => println!("{}", client.slowlog_get(count).await?),
+            SlowlogCommand::Len => println!("{}", client.slowlog_len().await?),
+            SlowlogCommand::Reset => {
+                client.slowlog_reset().await?;
+                println!("OK");
+            }
+        },
+        Command::Latency { command } => match command {
+            LatencyCommand::Histogram { commands } => println!("{}", client.latency_histogram(&commands).await?),
+            LatencyCommand::Reset => {
+                client.latency_reset().await?;
+                println!("OK");
+            }
+        },
+        Command::Acl { command } => match command {
+            AclCommand::Setuser { username, rules } => {
+                client.acl_setuser(&username, &rules).await?;
+                println!("OK");
+            }
+            AclCommand::Getuser { username } => match client.acl_getuser(&username).await? {
+                Some(user) => println!("{}", user),
+                None => println!("(nil)"),
+            },
+            AclCommand::List => {
+                for user in client.acl_list().await? {
+                    println!("{}", user);
+                }
+            }
+            AclCommand::Whoami => println!("{}", client.acl_whoami().await?),
+        },
+        Command::Cluster { command } => match command {
+            ClusterCommand::Slots => {
+                for (start, end, node) in client.cluster_slots().await? {
+                    println!("{}-{} {}", start, end, node);
+                }
+            }
+            ClusterCommand::Nodes => print!("{}", client.cluster_nodes().await?),
+            ClusterCommand::Keyslot { key } => println!("{}", client.cluster_keyslot(&key).await?),
+        },
+        Command::Export { output, format, pattern, count } => transfer::export(&mut client, output.as_deref(), format, pattern.as_deref(), count).await?,
+        Command::Import { input, format } => transfer::import(&mut client, input.as_deref(), format).await?,
     }
 
     Ok(())
@@ -0,0 +1,365 @@
+use db_proto::{clients::Client, pkg::Frame};
+
+use bytes::Bytes;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use tokio::signal;
+
+const COMMANDS: &[(&str, &str)] = &[
+    ("ACL GETUSER", "username"),
+    ("ACL LIST", ""),
+    ("ACL SETUSER", "username [rule ...]"),
+    ("ACL WHOAMI", ""),
+    ("ASKING", ""),
+    ("AUTH", "[username] password"),
+    ("CLIENT GETNAME", ""),
+    ("CLIENT ID", ""),
+    ("CLIENT KILL", "[ID client-id] [ADDR ip:port] [USER username] [SKIPME yes|no]"),
+    ("CLIENT LIST", "[ID client-id ...]"),
+    ("CLIENT PAUSE", "timeout [WRITE|ALL]"),
+    ("CLIENT SETNAME", "connection-name"),
+    ("CLIENT UNPAUSE", ""),
+    ("CLUSTER INFO", ""),
+    ("CLUSTER KEYSLOT", "key"),
+    ("CLUSTER MYID", ""),
+    ("CLUSTER NODES", ""),
+    ("CLUSTER SLOTS", ""),
+    ("CONFIG GET", "pattern"),
+    ("CONFIG REWRITE", ""),
+    ("CONFIG SET", "parameter value [parameter value ...]"),
+    ("DBSIZE", ""),
+    ("DUMP", "[path]"),
+    ("FLUSHALL", "[ASYNC|SYNC]"),
+    ("FLUSHDB", "[ASYNC|SYNC]"),
+    ("GET", "key"),
+    ("INFO", "[section]"),
+    ("LASTSAVE", ""),
+    ("LATENCY HISTOGRAM", "[command ...]"),
+    ("LATENCY RESET", ""),
+    ("LOAD", "[path]"),
+    ("MONITOR", ""),
+    ("MOVE", "key db"),
+    ("PING", "[message]"),
+    ("PTTL", "key"),
+    ("PUBLISH", "channel message"),
+    ("REPLICAOF", "host port"),
+    ("ROLE", ""),
+    ("SCAN", "cursor [MATCH pattern] [COUNT count]"),
+    ("SELECT", "index"),
+    ("SET", "key value [EX seconds|PX milliseconds]"),
+    ("SLOWLOG GET", "[count]"),
+    ("SLOWLOG LEN", ""),
+    ("SLOWLOG RESET", ""),
+    ("SUBSCRIBE", "channel [channel ...]"),
+    ("SWAPDB", "index1 index2"),
+    ("TIME", ""),
+    ("TTL", "key"),
+];
+
+const RAW_OUTPUT: &[&str] = &["info", "client list", "cluster nodes", "cluster info"];
+
+struct ReplHelper;
+
+pub async fn run(mut client: Client, host: String) -> crate::Result<()> {
+    let history = history_file();
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper));
+
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let mut db = 0;
+
+    loop {
+        let prompt = if db == 0 { format!("{}> ", host) } else { format!("{}[{}]> ", host, db) };
+        let (returned, line) = tokio::task::spawn_blocking(move || {
+            let line = editor.readline(&prompt);
+            (editor, line)
+        })
+        .await?;
+        editor = returned;
+
+        let line = match line {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        let args = match split_args(&line) {
+            Ok(args) if args.is_empty() => continue,
+            Ok(args) => args,
+            Err(err) => {
+                println!("Invalid argument(s): {}", err);
+                continue;
+            }
+        };
+
+        if !is_sensitive(&args) {
+            editor.add_history_entry(line.as_str())?;
+        }
+
+        let name = String::from_utf8_lossy(&args[0]).to_lowercase();
+        let full_name = args.get(1).map(|sub| format!("{} {}", name, String::from_utf8_lossy(sub).to_lowercase())).unwrap_or_else(|| name.clone());
+
+        if name == "quit" || name == "exit" {
+            break;
+        }
+
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::from(arg.clone()))).collect());
+
+        let reply = match client.execute(frame).await {
+            Ok(reply) => reply,
+            Err(err) => {
+                println!("(error) {}", err);
+                continue;
+            }
+        };
+
+        if name == "select" && reply == "OK" {
+            db = args.get(1).and_then(|index| String::from_utf8_lossy(index).parse().ok()).unwrap_or(db);
+        }
+
+        if RAW_OUTPUT.contains(&name.as_str()) || RAW_OUTPUT.contains(&full_name.as_str()) {
+            println!("{}", reply);
+        } else {
+            println!("{}", format_reply(&reply, 0));
+        }
+
+        if name == "subscribe" || name == "monitor" {
+            println!("Reading messages... (press Ctrl-C to quit)");
+            stream_replies(&mut client).await?;
+            break;
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+
+    Ok(())
+}
+
+async fn stream_replies(client: &mut Client) -> crate::Result<()> {
+    loop {
+        tokio::select! {
+            frame = client.read_frame() => match frame? {
+                Some(Frame::Simple(line)) => println!("{}", line),
+                Some(frame) => println!("{}", format_reply(&frame, 0)),
+                None => return Ok(()),
+            },
+            _ = signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+pub fn format_reply(frame: &Frame, indent: usize) -> String {
+    match frame {
+        Frame::Simple(value) => value.clone(),
+        Frame::Error(msg) => format!("(error) {}", msg),
+        Frame::Integer(value) => format!("(integer) {}", value),
+        Frame::Bulk(value) => format!("\"{}\"", value.escape_ascii()),
+        Frame::Null => "(nil)".to_string(),
+        Frame::Array(items) if items.is_empty() => "(empty array)".to_string(),
+        Frame::Array(items) => {
+            let width = items.len().to_string().len();
+            let mut out = String::new();
+
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                }
+
+                let prefix = format!("{:>width$}) ", i + 1, width = width);
+                out.push_str(&prefix);
+                out.push_str(&format_reply(item, indent + prefix.len()));
+            }
+
+            out
+        }
+    }
+}
+
+/// Splits a line the way redis-cli does: whitespace separated, with
+/// `"double"` quotes supporting `\n`, `\t`, `\xHH` style escapes and
+/// `'single'` quotes taken literally.
+pub fn split_args(line: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            chars.next();
+        }
+
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+
+        let mut arg = Vec::new();
+        let push = |arg: &mut Vec<u8>, c: char| arg.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+
+        match first {
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next().ok_or("unbalanced quotes")? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or("unbalanced quotes")? {
+                            'n' => arg.push(b'\n'),
+                            'r' => arg.push(b'\r'),
+                            't' => arg.push(b'\t'),
+                            'x' => {
+                                let hex: String = chars.by_ref().take(2).collect();
+                                let byte = u8::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\x{}", hex))?;
+                                arg.push(byte);
+                            }
+                            c => push(&mut arg, c),
+                        },
+                        c => push(&mut arg, c),
+                    }
+                }
+            }
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next().ok_or("unbalanced quotes")? {
+                        '\'' => break,
+                        c => push(&mut arg, c),
+                    }
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    push(&mut arg, c);
+                    chars.next();
+                }
+            }
+        }
+
+        if chars.peek().map(|c| !c.is_whitespace()).unwrap_or(false) {
+            return Err("closing quote must be followed by a space".to_string());
+        }
+
+        args.push(arg);
+    }
+}
+
+fn history_file() -> Option<PathBuf> {
+    match std::env::var_os("DB_CLIENT_HISTFILE") {
+        Some(path) if path.is_empty() || path == "/dev/null" => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".db_client_history")),
+    }
+}
+
+/// Commands that carry credentials are kept out of the history file, as
+/// redis-cli does.
+fn is_sensitive(args: &[Vec<u8>]) -> bool {
+    let args: Vec<String> = args.iter().map(|arg| String::from_utf8_lossy(arg).to_lowercase()).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["auth", ..] | ["acl", "setuser", ..] => true,
+        ["hello", rest @ ..] | ["migrate", rest @ ..] => rest.iter().any(|arg| *arg == "auth" || *arg == "auth2"),
+        ["config", "set", rest @ ..] => rest.iter().step_by(2).any(|param| param.contains("pass") || param.starts_with("master")),
+        _ => false,
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let typed = &line[..pos];
+        let start = typed.len() - typed.trim_start().len();
+        let prefix = typed.trim_start().to_uppercase();
+        let lowercase = typed.trim_start().chars().any(|c| c.is_lowercase());
+
+        let candidates = COMMANDS
+            .iter()
+            .filter(|(name, _)| name.starts_with(&prefix) && prefix.split_whitespace().count() <= name.split_whitespace().count())
+            .map(|(name, _)| {
+                let name = if lowercase { name.to_lowercase() } else { name.to_string() };
+                Pair { display: name.clone(), replacement: name + " " }
+            })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() || !line.ends_with(' ') {
+            return None;
+        }
+
+        let words: Vec<String> = line.split_whitespace().map(str::to_uppercase).collect();
+
+        let (name, syntax) = COMMANDS
+            .iter()
+            .filter(|(name, _)| {
+                let parts: Vec<&str> = name.split_whitespace().collect();
+                parts.len() <= words.len() && parts.iter().zip(&words).all(|(part, word)| part == word)
+            })
+            .max_by_key(|(name, _)| name.len())?;
+
+        let typed = words.len() - name.split_whitespace().count();
+        let remaining: Vec<&str> = syntax_args(syntax).into_iter().skip(typed).collect();
+
+        if remaining.is_empty() {
+            None
+        } else {
+            Some(remaining.join(" "))
+        }
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> { Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint)) }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Splits a syntax string into arguments, keeping `[optional groups]` whole.
+fn syntax_args(syntax: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in syntax.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ' ' if depth == 0 => {
+                if i > start {
+                    args.push(&syntax[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if start < syntax.len() {
+        args.push(&syntax[start..]);
+    }
+
+    args
+}
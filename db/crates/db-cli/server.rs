@@ -1,8 +1,10 @@
 mod verbose;
 
-use clap::Parser;
-use db_server::DEFAULT_PORT;
-use std::path::PathBuf;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use db_proto::pkg::memory::{self, Policy};
+use db_server::{Config, MasterTlsFiles, TlsFiles};
+use std::path::{Path, PathBuf};
 use tokio::net::TcpListener;
 use tokio::signal;
 use verbose::{InfoLevel, Verbosity};
@@ -13,17 +15,107 @@ use tracing_subscriber::prelude::*;
 #[derive(Parser, Debug)]
 #[command(name = "db-server", version)]
 struct Cli {
-    #[arg(long, default_value_t = DEFAULT_PORT)]
-    port: u16,
+    #[arg(help = "redis.conf-style config file; flags given on the command line override it")]
+    config: Option<PathBuf>,
 
-    #[arg(id = "hostname", long, default_value = "127.0.0.1")]
-    host: String,
+    #[arg(long, help = "Port to listen on [default: 6379]")]
+    port: Option<u16>,
+
+    #[arg(id = "hostname", long, help = "Address to listen on [default: 127.0.0.1]")]
+    host: Option<String>,
 
     #[clap(flatten)]
     verbose: Verbosity<InfoLevel>,
 
-    #[arg(long, help = "Path to save/load database state")]
-    state: Option<PathBuf>,
+    #[arg(long, help = "Data directory that snapshots are read from and written to [default: .]")]
+    dir: Option<PathBuf>,
+
+    #[arg(long, value_parser = snapshot_path, help = "Snapshot file to load on start and save on shutdown; a bare file name is kept in --dir")]
+    state: Option<(Option<PathBuf>, String)>,
+
+    #[arg(long, help = "Require clients to AUTH with this password as the default user")]
+    requirepass: Option<String>,
+
+    #[arg(long, requires = "tls_key_file", help = "Serve TLS using this PEM certificate chain")]
+    tls_cert_file: Option<PathBuf>,
+
+    #[arg(long, requires = "tls_cert_file", help = "PEM private key for the TLS certificate")]
+    tls_key_file: Option<PathBuf>,
+
+    #[arg(long, requires = "tls_cert_file", help = "Require client certificates signed by this PEM CA bundle")]
+    tls_ca_cert_file: Option<PathBuf>,
+
+    #[arg(long, help = "Also accept connections on this unix domain socket")]
+    unixsocket: Option<PathBuf>,
+
+    #[arg(long, requires = "unixsocket", value_parser = octal_mode, help = "Permissions of the unix socket in octal, e.g. 770")]
+    unixsocketperm: Option<u32>,
+
+    #[arg(long, value_name = "HOST:PORT", value_parser = host_port, help = "Start as a read-only replica of this master")]
+    replicaof: Option<(String, u16)>,
+
+    #[arg(long, help = "Username to authenticate with against the master")]
+    masteruser: Option<String>,
+
+    #[arg(long, help = "Password to authenticate with against the master")]
+    masterauth: Option<String>,
+
+    #[arg(long, help = "Connect to the master over TLS, trusting this PEM CA bundle")]
+    tls_replication_ca_cert_file: Option<PathBuf>,
+
+    #[arg(long, requires_all = ["tls_replication_ca_cert_file", "tls_replication_key_file"], help = "PEM client certificate presented to the master")]
+    tls_replication_cert_file: Option<PathBuf>,
+
+    #[arg(long, requires = "tls_replication_cert_file", help = "PEM private key for the replication client certificate")]
+    tls_replication_key_file: Option<PathBuf>,
+
+    #[arg(long, requires = "tls_replication_ca_cert_file", help = "Name to verify the master's certificate against [default: the replicaof host]")]
+    tls_replication_server_name: Option<String>,
+
+    #[arg(long, help = "Run in cluster mode using this static topology file")]
+    cluster_config: Option<PathBuf>,
+
+    #[arg(long, requires = "cluster_config", help = "Id of this node in the cluster topology, defaults to the node listening on --port")]
+    cluster_node_id: Option<String>,
+
+    #[arg(long, value_parser = memory::parse_size, help = "Memory limit for keys, e.g. 100mb; 0 disables the limit [default: 0]")]
+    maxmemory: Option<usize>,
+
+    #[arg(long, help = "What to evict once maxmemory is reached: noeviction, allkeys-lru, allkeys-lfu, allkeys-random, volatile-lru, volatile-ttl [default: noeviction]")]
+    maxmemory_policy: Option<Policy>,
+
+    #[arg(long, help = "Number of keys sampled per eviction [default: 5]")]
+    maxmemory_samples: Option<usize>,
+
+    #[arg(long, value_name = "HOST:PORT", help = "Serve Prometheus metrics on http://HOST:PORT/metrics")]
+    metrics_addr: Option<String>,
+
+    #[arg(long, allow_negative_numbers = true, help = "Log commands slower than this many microseconds; negative disables the slow log [default: 10000]")]
+    slowlog_log_slower_than: Option<i64>,
+
+    #[arg(long, help = "Maximum number of slow log entries kept [default: 128]")]
+    slowlog_max_len: Option<usize>,
+
+    #[arg(long, help = "Maximum number of simultaneous client connections [default: 250]")]
+    maxclients: Option<usize>,
+
+    #[arg(long, value_name = "SECONDS", help = "Close client connections idle for this many seconds; 0 disables the timeout [default: 0]")]
+    timeout: Option<u64>,
+
+    #[arg(long, value_name = "RULES", help = "Snapshot after <seconds> <changes> pairs, e.g. \"900 1 300 10\"; empty disables")]
+    save: Option<String>,
+
+    #[arg(long, help = "Number of logical databases clients can SELECT [default: 16]")]
+    databases: Option<usize>,
+
+    #[arg(long, value_name = "SECONDS", help = "Interval of TCP keepalive probes on client connections; 0 disables them [default: 300]")]
+    tcp_keepalive: Option<u64>,
+
+    #[arg(long, value_name = "SECONDS", help = "How long to wait for in-flight commands on shutdown before closing connections [default: 10]")]
+    shutdown_timeout: Option<u64>,
+
+    #[arg(long, value_parser = memory::parse_size, help = "Largest bulk string a client may send, e.g. 512mb [default: 512mb]")]
+    proto_max_bulk_len: Option<usize>,
 }
 
 #[tokio::main]
@@ -40,8 +132,102 @@ pub async fn main() -> db_proto::Result<()> {
         .with(formatting_layer_config)
         .init();
 
-    let addr = format!("{}:{}", cli.host, cli.port);
+    let mut config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    override_with(&mut config.port, cli.port);
+    override_with(&mut config.bind, cli.host);
+    override_with(&mut config.dir, cli.dir.clone());
+    if let Some((dir, dbfilename)) = cli.state {
+        if let Some(dir) = dir {
+            if cli.dir.is_some() {
+                Cli::command().error(ErrorKind::ArgumentConflict, "--state can only be a file name when --dir is given").exit();
+            }
+
+            config.dir = dir;
+        }
+
+        config.dbfilename = Some(dbfilename);
+    }
+    override_with(&mut config.tls, cli.tls_cert_file.zip(cli.tls_key_file).map(|(cert_file, key_file)| {
+        Some(TlsFiles {
+            cert_file,
+            key_file,
+            ca_cert_file: cli.tls_ca_cert_file,
+        })
+    }));
+    override_with(&mut config.unixsocket, cli.unixsocket.map(Some));
+    override_with(&mut config.unixsocketperm, cli.unixsocketperm.map(Some));
+    override_with(&mut config.replicaof, cli.replicaof.map(Some));
+    override_with(&mut config.masteruser, cli.masteruser.map(Some));
+    override_with(&mut config.masterauth, cli.masterauth.map(Some));
+    override_with(&mut config.master_tls, cli.tls_replication_ca_cert_file.map(|ca_cert_file| {
+        Some(MasterTlsFiles {
+            ca_cert_file,
+            cert_file: cli.tls_replication_cert_file,
+            key_file: cli.tls_replication_key_file,
+            server_name: cli.tls_replication_server_name,
+        })
+    }));
+    override_with(&mut config.cluster_config, cli.cluster_config.map(Some));
+    override_with(&mut config.cluster_node_id, cli.cluster_node_id.map(Some));
+    override_with(&mut config.metrics_addr, cli.metrics_addr.map(Some));
+    override_with(&mut config.databases, cli.databases);
+
+    // Runtime settings go through the same setters as the config file and
+    // CONFIG SET, after the file's own so the command line wins.
+    let params = [
+        ("requirepass", cli.requirepass),
+        ("maxmemory", cli.maxmemory.map(|size| size.to_string())),
+        ("maxmemory-policy", cli.maxmemory_policy.map(|policy| policy.to_string())),
+        ("maxmemory-samples", cli.maxmemory_samples.map(|samples| samples.to_string())),
+        ("slowlog-log-slower-than", cli.slowlog_log_slower_than.map(|micros| micros.to_string())),
+        ("slowlog-max-len", cli.slowlog_max_len.map(|len| len.to_string())),
+        ("maxclients", cli.maxclients.map(|max| max.to_string())),
+        ("timeout", cli.timeout.map(|secs| secs.to_string())),
+        ("tcp-keepalive", cli.tcp_keepalive.map(|secs| secs.to_string())),
+        ("shutdown-timeout", cli.shutdown_timeout.map(|secs| secs.to_string())),
+        ("proto-max-bulk-len", cli.proto_max_bulk_len.map(|len| len.to_string())),
+        ("save", cli.save),
+    ];
+    config.params.extend(params.into_iter().filter_map(|(name, value)| Some((name.to_string(), value?))));
+
+    let addr = format!("{}:{}", config.bind, config.port);
     let listener = TcpListener::bind(&addr).await?;
 
-    Ok(db_server::run(listener, signal::ctrl_c(), cli.state).await)
+    db_server::run(listener, terminate(), config).await
+}
+
+async fn terminate() {
+    let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+
+    tokio::select! {
+        _ = signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
+
+fn override_with<T>(setting: &mut T, flag: Option<T>) {
+    if let Some(value) = flag {
+        *setting = value;
+    }
+}
+
+fn octal_mode(src: &str) -> Result<u32, std::num::ParseIntError> { u32::from_str_radix(src, 8) }
+
+fn host_port(src: &str) -> Result<(String, u16), String> {
+    let (host, port) = src.rsplit_once(':').ok_or("expected HOST:PORT")?;
+    Ok((host.to_string(), port.parse().map_err(|_| format!("invalid port `{}`", port))?))
+}
+
+/// Splits a snapshot path into the directory it lives in, if any, and its file
+/// name.
+fn snapshot_path(src: &str) -> Result<(Option<PathBuf>, String), String> {
+    let path = Path::new(src);
+    let name = path.file_name().and_then(|name| name.to_str()).ok_or("expected a path to a file")?;
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).map(Path::to_path_buf);
+
+    Ok((dir, name.to_string()))
 }
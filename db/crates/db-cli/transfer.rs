@@ -0,0 +1,254 @@
+use crate::repl::split_args;
+use db_proto::cmd::{Get, Set, Ttl};
+use db_proto::pkg::frame::Limits;
+use db_proto::{clients::Client, pkg::Frame};
+
+use bytes::{Bytes, BytesMut};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+const PIPE_BATCH: usize = 1000;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    /// One JSON object per line
+    Jsonl,
+    /// Comma separated values with a header row
+    Csv,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    key: String,
+    value: String,
+    /// Remaining time to live in milliseconds
+    ttl: Option<u64>,
+    encoding: Option<Encoding>,
+}
+
+/// Values that are not valid UTF-8 are written hex encoded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    Hex,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    replies: usize,
+    errors: usize,
+}
+
+/// Streams commands from stdin to the server, pipelining them in batches.
+/// Input may be raw RESP or inline commands, one per line.
+pub async fn pipe(client: &mut Client) -> crate::Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut buffer = BytesMut::with_capacity(64 * 1024);
+    let mut batch = Vec::with_capacity(PIPE_BATCH);
+    let mut summary = Summary::default();
+
+    loop {
+        let eof = stdin.read_buf(&mut buffer).await? == 0;
+
+        while let Some(frame) = next_command(&mut buffer, eof)? {
+            batch.push(frame);
+
+            if batch.len() == PIPE_BATCH {
+                summary.send(client, &batch).await?;
+                batch.clear();
+            }
+        }
+
+        if eof {
+            break;
+        }
+    }
+
+    if !buffer.is_empty() {
+        return Err("unexpected end of input; incomplete RESP command".into());
+    }
+
+    summary.send(client, &batch).await?;
+    println!("errors: {}, replies: {}", summary.errors, summary.replies);
+
+    Ok(())
+}
+
+fn next_command(buffer: &mut BytesMut, eof: bool) -> crate::Result<Option<Frame>> {
+    loop {
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+
+        if buffer[0] == b'*' {
+            return Ok(Frame::decode(buffer, &Limits::default())?);
+        }
+
+        let line = match buffer.iter().position(|&b| b == b'\n') {
+            Some(end) => buffer.split_to(end + 1),
+            None if eof => buffer.split(),
+            None => return Ok(None),
+        };
+
+        let args = split_args(String::from_utf8_lossy(&line).trim_end())?;
+
+        if !args.is_empty() {
+            return Ok(Some(Frame::Array(args.into_iter().map(|arg| Frame::Bulk(Bytes::from(arg))).collect())));
+        }
+    }
+}
+
+impl Summary {
+    async fn send(&mut self, client: &mut Client, frames: &[Frame]) -> crate::Result<()> {
+        if frames.is_empty() {
+            return Ok(());
+        }
+
+        for reply in client.pipeline(frames).await? {
+            self.replies += 1;
+
+            if let Frame::Error(msg) = reply {
+                self.errors += 1;
+                eprintln!("{}", msg);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes every key of the selected database along with its value and TTL,
+/// iterating with `SCAN` so the server is never blocked for long.
+pub async fn export(client: &mut Client, output: Option<&Path>, format: Format, pattern: Option<&str>, count: u64) -> crate::Result<()> {
+    let out: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let mut writer = Writer::new(BufWriter::new(out), format)?;
+    let mut cursor = 0;
+    let mut exported = 0;
+
+    loop {
+        let (next, keys) = client.scan(cursor, pattern, Some(count)).await?;
+        let frames: Vec<Frame> = keys.iter().flat_map(|key| [Get::new(key).into_frame(), Ttl::new(key, true).into_frame()]).collect();
+        let replies = client.pipeline(&frames).await?;
+
+        for (key, reply) in keys.into_iter().zip(replies.chunks(2)) {
+            let (value, ttl) = match reply {
+                [Frame::Bulk(value), Frame::Integer(ttl)] if *ttl != -2 => (value, u64::try_from(*ttl).ok()),
+                [Frame::Null, _] | [_, Frame::Integer(-2)] => continue,
+                [Frame::Error(msg), _] | [_, Frame::Error(msg)] => return Err(format!("exporting '{}': {}", key, msg).into()),
+                _ => return Err(format!("exporting '{}': unexpected reply", key).into()),
+            };
+
+            let (value, encoding) = match std::str::from_utf8(value) {
+                Ok(value) => (value.to_string(), None),
+                Err(_) => (hex_encode(value), Some(Encoding::Hex)),
+            };
+
+            writer.write(&Record { key, value, ttl, encoding })?;
+            exported += 1;
+        }
+
+        cursor = next;
+
+        if cursor == 0 {
+            break;
+        }
+    }
+
+    writer.flush()?;
+    eprintln!("exported {} keys", exported);
+
+    Ok(())
+}
+
+/// Loads records written by `export`, pipelining a `SET` per record.
+pub async fn import(client: &mut Client, input: Option<&Path>, format: Format) -> crate::Result<()> {
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let records: Box<dyn Iterator<Item = crate::Result<Record>>> = match format {
+        Format::Jsonl => Box::new(reader.lines().filter(|line| line.as_ref().map(|line| !line.trim().is_empty()).unwrap_or(true)).map(|line| Ok(serde_json::from_str(&line?)?))),
+        Format::Csv => Box::new(csv::Reader::from_reader(reader).into_deserialize().map(|record| Ok(record?))),
+    };
+
+    let mut batch = Vec::with_capacity(PIPE_BATCH);
+    let mut summary = Summary::default();
+
+    for record in records {
+        let record = record?;
+
+        let value = match record.encoding {
+            Some(Encoding::Hex) => hex_decode(&record.value).ok_or_else(|| format!("invalid hex value for key '{}'", record.key))?,
+            None => record.value.into_bytes(),
+        };
+
+        batch.push(Set::new(record.key, Bytes::from(value), record.ttl.map(Duration::from_millis)).into_frame());
+
+        if batch.len() == PIPE_BATCH {
+            summary.send(client, &batch).await?;
+            batch.clear();
+        }
+    }
+
+    summary.send(client, &batch).await?;
+    eprintln!("imported {} keys, {} errors", summary.replies - summary.errors, summary.errors);
+
+    Ok(())
+}
+
+enum Writer<W: Write> {
+    Jsonl(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> Writer<W> {
+    fn new(out: W, format: Format) -> crate::Result<Writer<W>> {
+        match format {
+            Format::Jsonl => Ok(Writer::Jsonl(out)),
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(out);
+                writer.write_record(["key", "value", "ttl", "encoding"])?;
+                Ok(Writer::Csv(Box::new(writer)))
+            }
+        }
+    }
+
+    fn write(&mut self, record: &Record) -> crate::Result<()> {
+        match self {
+            Writer::Jsonl(out) => {
+                serde_json::to_writer(&mut *out, record)?;
+                out.write_all(b"\n")?;
+            }
+            Writer::Csv(writer) => writer.serialize((&record.key, &record.value, record.ttl, record.encoding))?,
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Jsonl(out) => out.flush(),
+            Writer::Csv(writer) => writer.flush(),
+        }
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() }
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
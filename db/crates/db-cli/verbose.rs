@@ -42,7 +42,7 @@ impl<L: LogLevel> Verbosity<L> {
 
     pub fn log_level(&self) -> Option<Level> { level_enum(self.verbosity()) }
 
-    pub fn log_level_filter(&self) -> LevelFilter { return level_enum(self.verbosity()).map(LevelFilter::from_level).unwrap_or(LevelFilter::OFF); }
+    pub fn log_level_filter(&self) -> LevelFilter { level_enum(self.verbosity()).map(LevelFilter::from_level).unwrap_or(LevelFilter::OFF) }
 
     pub fn is_silent(&self) -> bool { self.log_level().is_none() }
 
@@ -91,19 +91,20 @@ pub trait LogLevel {
 pub struct ErrorLevel;
 
 impl LogLevel for ErrorLevel {
-    fn default() -> Option<Level> { return Some(Level::ERROR); }
+    fn default() -> Option<Level> { Some(Level::ERROR) }
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct WarnLevel;
 
 impl LogLevel for WarnLevel {
-    fn default() -> Option<Level> { return Some(Level::WARN); }
+    fn default() -> Option<Level> { Some(Level::WARN) }
 }
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct InfoLevel;
 
 impl LogLevel for InfoLevel {
-    fn default() -> Option<Level> { return Some(Level::INFO); }
+    fn default() -> Option<Level> { Some(Level::INFO) }
 }
@@ -1,4 +1,10 @@
+use crate::clients::Codec;
+use crate::pkg::tls::TlsConfig;
+
 use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
 use std::time::Duration;
 use tokio::net::ToSocketAddrs;
 use tokio::runtime::Runtime;
@@ -29,12 +35,34 @@ impl BlockingClient {
         Ok(BlockingClient { inner, rt })
     }
 
+    pub fn connect_unix(path: impl AsRef<Path>) -> crate::Result<BlockingClient> {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+
+        let inner = rt.block_on(crate::clients::Client::connect_unix(path))?;
+
+        Ok(BlockingClient { inner, rt })
+    }
+
+    pub fn connect_tls<T: ToSocketAddrs>(addr: T, domain: &str, tls: &TlsConfig) -> crate::Result<BlockingClient> {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+
+        let inner = rt.block_on(crate::clients::Client::connect_tls(addr, domain, tls))?;
+
+        Ok(BlockingClient { inner, rt })
+    }
+
     pub fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> { self.rt.block_on(self.inner.get(key)) }
 
     pub fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> { self.rt.block_on(self.inner.set(key, value)) }
 
     pub fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> { self.rt.block_on(self.inner.set_expires(key, value, expiration)) }
 
+    pub fn get_as<T: DeserializeOwned>(&mut self, key: &str, codec: impl Codec) -> crate::Result<Option<T>> { self.rt.block_on(self.inner.get_as(key, codec)) }
+
+    pub fn set_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, codec: impl Codec) -> crate::Result<()> { self.rt.block_on(self.inner.set_as(key, value, codec)) }
+
+    pub fn set_expires_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, expiration: Duration, codec: impl Codec) -> crate::Result<()> { self.rt.block_on(self.inner.set_expires_as(key, value, expiration, codec)) }
+
     pub fn publish(&mut self, channel: &str, message: Bytes) -> crate::Result<u64> { self.rt.block_on(self.inner.publish(channel, message)) }
 
     pub fn subscribe(self, channels: Vec<String>) -> crate::Result<BlockingSubscriber> {
@@ -0,0 +1,184 @@
+use crate::clients::{Client, Codec};
+use crate::cmd::Subscribe;
+use crate::pkg::tracking::INVALIDATE_CHANNEL;
+use crate::pkg::Frame;
+
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::ToSocketAddrs;
+use tokio::task::JoinHandle;
+use tracing::{debug, instrument, warn};
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// A `Client` that serves repeated `GET`s from a local cache. The server
+/// tracks every key read through it and sends invalidations to a second,
+/// subscribed connection; if that connection is lost the cache is cleared
+/// and every read goes to the server.
+pub struct CachingClient {
+    client: Client,
+    cache: Arc<Mutex<Cache>>,
+    listener: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct Cache {
+    entries: HashMap<String, Slot>,
+    capacity: usize,
+    connected: bool,
+}
+
+#[derive(Debug)]
+enum Slot {
+    /// A `GET` is in flight. An invalidation arriving before its reply
+    /// removes the slot, so the possibly stale reply isn't cached.
+    Pending,
+    Ready(Option<Bytes>),
+}
+
+impl CachingClient {
+    pub async fn connect<T: ToSocketAddrs + Clone>(addr: T) -> crate::Result<CachingClient> {
+        let client = Client::connect(addr.clone()).await?;
+        let listener = Client::connect(addr).await?;
+
+        CachingClient::new(client, listener).await
+    }
+
+    /// Builds the cache from two connected (and, if needed, authenticated)
+    /// clients: `client` runs the commands and `listener` receives the
+    /// invalidations.
+    pub async fn new(mut client: Client, mut listener: Client) -> crate::Result<CachingClient> {
+        let id = listener.client_id().await?;
+
+        match listener.execute(Subscribe::new(vec![INVALIDATE_CHANNEL.to_string()]).into_frame()).await? {
+            Frame::Array(_) => {}
+            frame => return Err(frame.to_error()),
+        }
+
+        client.client_tracking(true, Some(id)).await?;
+
+        let cache = Arc::new(Mutex::new(Cache {
+            entries: HashMap::new(),
+            capacity: DEFAULT_CAPACITY,
+            connected: true,
+        }));
+
+        let listener = tokio::spawn(listen(listener, cache.clone()));
+
+        Ok(CachingClient { client, cache, listener })
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) { self.cache.lock().unwrap().capacity = capacity; }
+
+    /// Number of values currently served locally.
+    pub fn cached(&self) -> usize { self.cache.lock().unwrap().entries.values().filter(|slot| matches!(slot, Slot::Ready(_))).count() }
+
+    /// The underlying client, for commands that aren't cached. Writes made
+    /// through it are still invalidated by the server.
+    pub fn client(&mut self) -> &mut Client { &mut self.client }
+
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        {
+            let mut cache = self.cache.lock().unwrap();
+
+            if let Some(Slot::Ready(value)) = cache.entries.get(key) {
+                return Ok(value.clone());
+            }
+
+            cache.insert(key, Slot::Pending);
+        }
+
+        let value = self.client.get(key).await;
+        let mut cache = self.cache.lock().unwrap();
+
+        if let Some(slot @ Slot::Pending) = cache.entries.get_mut(key) {
+            match &value {
+                Ok(value) => *slot = Slot::Ready(value.clone()),
+                Err(_) => {
+                    cache.entries.remove(key);
+                }
+            }
+        }
+
+        value
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.cache.lock().unwrap().entries.remove(key);
+        self.client.set(key, value).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        self.cache.lock().unwrap().entries.remove(key);
+        self.client.set_expires(key, value, expiration).await
+    }
+
+    pub async fn get_as<T: DeserializeOwned>(&mut self, key: &str, codec: impl Codec) -> crate::Result<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(codec.decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, codec: impl Codec) -> crate::Result<()> { self.set(key, codec.encode(value)?).await }
+}
+
+impl Drop for CachingClient {
+    fn drop(&mut self) { self.listener.abort(); }
+}
+
+impl Cache {
+    fn insert(&mut self, key: &str, slot: Slot) {
+        if !self.connected || self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(key) {
+            if let Some(victim) = self.entries.keys().next().cloned() {
+                self.entries.remove(&victim);
+            }
+        }
+
+        self.entries.insert(key.to_string(), slot);
+    }
+}
+
+async fn listen(mut listener: Client, cache: Arc<Mutex<Cache>>) {
+    loop {
+        let frame = match listener.read_frame().await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => {
+                debug!("invalidation connection closed");
+                break;
+            }
+            Err(err) => {
+                warn!(cause = ?err, "invalidation connection failed");
+                break;
+            }
+        };
+
+        let Frame::Array(parts) = frame else { continue };
+        let mut cache = cache.lock().unwrap();
+
+        match parts.as_slice() {
+            [kind, _, Frame::Array(keys)] if *kind == "message" => {
+                for key in keys {
+                    cache.entries.remove(&key.to_string());
+                }
+            }
+            [kind, _, Frame::Null] if *kind == "message" => cache.entries.clear(),
+            _ => {}
+        }
+    }
+
+    let mut cache = cache.lock().unwrap();
+    cache.connected = false;
+    cache.entries.clear();
+}
@@ -1,12 +1,18 @@
-use crate::cmd::{Dump, Get, Load, Ping, Publish, Set, Subscribe, Unsubscribe};
+use crate::cmd::{Acl, Asking, Auth, Client as ClientCmd, Cluster, Config, Dbsize, Dump, Flushall, Flushdb, Get, Info, Lastsave, Latency, Load, Monitor, Move, Ping, Publish, Replicaof, Role, Scan, Select, Set, Slowlog, Subscribe, Swapdb, Time, Ttl, Unsubscribe};
+use crate::clients::Codec;
+use crate::pkg::registry::Filter;
+use crate::pkg::tls::TlsConfig;
 use crate::pkg::{Connection, Frame};
 
 use async_stream::try_stream;
 use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 use std::time::Duration;
-use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::net::{TcpStream, ToSocketAddrs, UnixStream};
+use tokio_rustls::rustls::pki_types::ServerName;
 use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
@@ -28,11 +34,28 @@ pub struct Message {
 impl Client {
     pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
         let socket = TcpStream::connect(addr).await?;
+        socket.set_nodelay(true)?;
         let connection = Connection::new(socket);
 
         Ok(Client { connection })
     }
 
+    pub async fn connect_unix(path: impl AsRef<Path>) -> crate::Result<Client> {
+        let socket = UnixStream::connect(path).await?;
+        let connection = Connection::new(socket);
+
+        Ok(Client { connection })
+    }
+
+    pub async fn connect_tls<T: ToSocketAddrs>(addr: T, domain: &str, tls: &TlsConfig) -> crate::Result<Client> {
+        let socket = TcpStream::connect(addr).await?;
+        socket.set_nodelay(true)?;
+        let server_name = ServerName::try_from(domain.to_string())?;
+        let stream = tls.connector()?.connect(server_name, socket).await?;
+
+        Ok(Client { connection: Connection::new(stream) })
+    }
+
     #[instrument(skip(self))]
     pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
         let frame = Ping::new(msg).into_frame();
@@ -46,9 +69,61 @@ impl Client {
         }
     }
 
+    #[instrument(skip(self, password))]
+    pub async fn auth(&mut self, username: Option<&str>, password: &str) -> crate::Result<()> {
+        let frame = Auth::new(username.map(str::to_string), password).into_frame();
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     #[instrument(skip(self))]
-    pub async fn dump(&mut self, path: &Path) -> crate::Result<()> {
-        let frame = Dump::new(path.to_path_buf()).into_frame();
+    pub async fn acl_setuser(&mut self, username: &str, rules: &[String]) -> crate::Result<()> {
+        let frame = Acl::SetUser {
+            username: username.to_string(),
+            rules: rules.to_vec(),
+        }
+        .into_frame();
+
+        match self.execute(frame).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn acl_getuser(&mut self, username: &str) -> crate::Result<Option<Frame>> {
+        let frame = Acl::GetUser { username: username.to_string() }.into_frame();
+
+        match self.execute(frame).await? {
+            Frame::Null => Ok(None),
+            frame @ Frame::Array(_) => Ok(Some(frame)),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn acl_list(&mut self) -> crate::Result<Vec<String>> {
+        match self.execute(Acl::List.into_frame()).await? {
+            Frame::Array(users) => Ok(users.iter().map(ToString::to_string).collect()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn acl_whoami(&mut self) -> crate::Result<String> {
+        match self.execute(Acl::WhoAmI.into_frame()).await? {
+            frame @ (Frame::Simple(_) | Frame::Bulk(_)) => Ok(frame.to_string()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn dump(&mut self, path: Option<&Path>) -> crate::Result<()> {
+        let frame = Dump::new(path.map(Path::to_path_buf)).into_frame();
         debug!(request = ?frame);
 
         self.connection.write_frame(&frame).await?;
@@ -60,8 +135,8 @@ impl Client {
     }
 
     #[instrument(skip(self))]
-    pub async fn load(&mut self, path: &Path) -> crate::Result<()> {
-        let frame = Load::new(path.to_path_buf()).into_frame();
+    pub async fn load(&mut self, path: Option<&Path>) -> crate::Result<()> {
+        let frame = Load::new(path.map(Path::to_path_buf)).into_frame();
         debug!(request = ?frame);
 
         self.connection.write_frame(&frame).await?;
@@ -72,6 +147,370 @@ impl Client {
         }
     }
 
+    #[instrument(skip(self))]
+    pub async fn replicaof(&mut self, master: Option<(&str, u16)>) -> crate::Result<()> {
+        let frame = Replicaof::new(master.map(|(host, port)| (host.to_string(), port))).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn role(&mut self) -> crate::Result<Frame> {
+        let frame = Role::new().into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            frame @ Frame::Array(_) => Ok(frame),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn info(&mut self, section: Option<&str>) -> crate::Result<String> {
+        let frame = Info::new(section.map(str::to_string)).into_frame();
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Bulk(info) => Ok(String::from_utf8_lossy(&info).into_owned()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn asking(&mut self) -> crate::Result<()> {
+        match self.execute(Asking::new().into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn cluster_slots(&mut self) -> crate::Result<Vec<(u16, u16, String)>> {
+        let response = self.execute(Cluster::Slots.into_frame()).await?;
+
+        let ranges = match &response {
+            Frame::Array(ranges) => ranges,
+            _ => return Err(response.to_error()),
+        };
+
+        ranges
+            .iter()
+            .map(|range| match range {
+                Frame::Array(parts) => match parts.as_slice() {
+                    [Frame::Integer(start), Frame::Integer(end), Frame::Array(node), ..] => match node.as_slice() {
+                        [host, Frame::Integer(port), ..] => match (u16::try_from(*start), u16::try_from(*end)) {
+                            (Ok(start), Ok(end)) => Ok((start, end, format!("{}:{}", host, port))),
+                            _ => Err(range.to_error()),
+                        },
+                        _ => Err(range.to_error()),
+                    },
+                    _ => Err(range.to_error()),
+                },
+                _ => Err(range.to_error()),
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    pub async fn cluster_keyslot(&mut self, key: &str) -> crate::Result<u64> {
+        match self.execute(Cluster::Keyslot { key: key.to_string() }.into_frame()).await? {
+            Frame::Integer(slot) => Ok(slot as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn cluster_nodes(&mut self) -> crate::Result<String> {
+        match self.execute(Cluster::Nodes.into_frame()).await? {
+            Frame::Bulk(nodes) => Ok(String::from_utf8_lossy(&nodes).into_owned()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn select(&mut self, index: u64) -> crate::Result<()> {
+        match self.execute(Select::new(index).into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn move_key(&mut self, key: &str, db: u64) -> crate::Result<bool> {
+        match self.execute(Move::new(key, db).into_frame()).await? {
+            Frame::Integer(moved) => Ok(moved == 1),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn swapdb(&mut self, first: u64, second: u64) -> crate::Result<()> {
+        match self.execute(Swapdb::new(first, second).into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn flushdb(&mut self, lazy: bool) -> crate::Result<()> {
+        match self.execute(Flushdb::new(lazy).into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn flushall(&mut self, lazy: bool) -> crate::Result<()> {
+        match self.execute(Flushall::new(lazy).into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn scan(&mut self, cursor: u64, pattern: Option<&str>, count: Option<u64>) -> crate::Result<(u64, Vec<String>)> {
+        match self.execute(Scan::new(cursor, pattern.map(str::to_string), count).into_frame()).await? {
+            Frame::Array(parts) => match &parts[..] {
+                [cursor, Frame::Array(keys)] => {
+                    let cursor = cursor.to_string().parse().map_err(|_| crate::Error::Protocol("invalid SCAN cursor".into()))?;
+                    Ok((cursor, keys.iter().map(|key| key.to_string()).collect()))
+                }
+                _ => Err(crate::Error::Protocol("invalid SCAN response".into())),
+            },
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Returns the remaining time to live of `key`, `None` when it has no
+    /// expiration, or an error if the key does not exist.
+    #[instrument(skip(self))]
+    pub async fn pttl(&mut self, key: &str) -> crate::Result<Option<Duration>> {
+        match self.execute(Ttl::new(key, true).into_frame()).await? {
+            Frame::Integer(-2) => Err(format!("no such key '{}'", key).into()),
+            Frame::Integer(-1) => Ok(None),
+            Frame::Integer(ms) => Ok(Some(Duration::from_millis(ms as u64))),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn dbsize(&mut self) -> crate::Result<u64> {
+        match self.execute(Dbsize::new().into_frame()).await? {
+            Frame::Integer(size) => Ok(size as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn time(&mut self) -> crate::Result<Duration> {
+        match self.execute(Time::new().into_frame()).await? {
+            Frame::Array(parts) => match &parts[..] {
+                [Frame::Bulk(secs), Frame::Bulk(micros)] => {
+                    let secs = atoi::atoi::<u64>(secs).ok_or_else(|| crate::Error::Protocol("invalid TIME seconds".into()))?;
+                    let micros = atoi::atoi::<u64>(micros).ok_or_else(|| crate::Error::Protocol("invalid TIME microseconds".into()))?;
+                    Ok(Duration::from_secs(secs) + Duration::from_micros(micros))
+                }
+                _ => Err(crate::Error::Protocol("invalid TIME response".into())),
+            },
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn lastsave(&mut self) -> crate::Result<u64> {
+        match self.execute(Lastsave::new().into_frame()).await? {
+            Frame::Integer(time) => Ok(time as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn slowlog_get(&mut self, count: Option<i64>) -> crate::Result<Frame> {
+        match self.execute(Slowlog::Get { count }.into_frame()).await? {
+            frame @ Frame::Array(_) => Ok(frame),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn slowlog_len(&mut self) -> crate::Result<u64> {
+        match self.execute(Slowlog::Len.into_frame()).await? {
+            Frame::Integer(len) => Ok(len as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn slowlog_reset(&mut self) -> crate::Result<()> {
+        match self.execute(Slowlog::Reset.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn latency_histogram(&mut self, commands: &[String]) -> crate::Result<Frame> {
+        match self.execute(Latency::Histogram { commands: commands.to_vec() }.into_frame()).await? {
+            frame @ Frame::Array(_) => Ok(frame),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn latency_reset(&mut self) -> crate::Result<()> {
+        match self.execute(Latency::Reset.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_list(&mut self) -> crate::Result<String> {
+        match self.execute(ClientCmd::List { ids: vec![] }.into_frame()).await? {
+            Frame::Bulk(list) => Ok(String::from_utf8_lossy(&list).into_owned()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_kill(&mut self, filter: Filter) -> crate::Result<u64> {
+        let frame = ClientCmd::Kill { addr: None, filter, skipme: true }.into_frame();
+
+        match self.execute(frame).await? {
+            Frame::Integer(killed) => Ok(killed as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_setname(&mut self, name: &str) -> crate::Result<()> {
+        match self.execute(ClientCmd::SetName { name: name.to_string() }.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_getname(&mut self) -> crate::Result<Option<String>> {
+        match self.execute(ClientCmd::GetName.into_frame()).await? {
+            Frame::Bulk(name) => Ok(Some(String::from_utf8_lossy(&name).into_owned())),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_id(&mut self) -> crate::Result<u64> {
+        match self.execute(ClientCmd::Id.into_frame()).await? {
+            Frame::Integer(id) => Ok(id as u64),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_pause(&mut self, timeout: Duration, writes_only: bool) -> crate::Result<()> {
+        match self.execute(ClientCmd::Pause { timeout, writes_only }.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn client_unpause(&mut self) -> crate::Result<()> {
+        match self.execute(ClientCmd::Unpause.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// Sends invalidations for keys this connection reads to the client with
+    /// id `redirect`, which should be subscribed to `__redis__:invalidate`.
+    #[instrument(skip(self))]
+    pub async fn client_tracking(&mut self, on: bool, redirect: Option<u64>) -> crate::Result<()> {
+        match self.execute(ClientCmd::Tracking { on, redirect }.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn config_get(&mut self, pattern: &str) -> crate::Result<Vec<(String, String)>> {
+        match self.execute(Config::Get { pattern: pattern.to_string() }.into_frame()).await? {
+            Frame::Array(params) => Ok(params.chunks(2).map(|pair| (pair[0].to_string(), pair.get(1).map(ToString::to_string).unwrap_or_default())).collect()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn config_set(&mut self, params: &[(String, String)]) -> crate::Result<()> {
+        match self.execute(Config::Set { params: params.to_vec() }.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn config_rewrite(&mut self) -> crate::Result<()> {
+        match self.execute(Config::Rewrite.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn monitor(mut self) -> crate::Result<impl Stream<Item = crate::Result<String>>> {
+        match self.execute(Monitor::new().into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => {}
+            frame => return Err(frame.to_error()),
+        }
+
+        Ok(try_stream! {
+            while let Some(frame) = self.connection.read_frame().await? {
+                match frame {
+                    Frame::Simple(line) => yield line,
+                    frame => Err(frame.to_error())?,
+                }
+            }
+        })
+    }
+
+    pub async fn execute(&mut self, frame: Frame) -> crate::Result<Frame> {
+        debug!(request = ?frame);
+        self.connection.write_frame(&frame).await?;
+        self.read_response().await
+    }
+
+    /// Sends all `frames` before reading any reply. Replies are returned in
+    /// order, with server errors left as `Frame::Error` instead of failing.
+    pub async fn pipeline(&mut self, frames: &[Frame]) -> crate::Result<Vec<Frame>> {
+        self.connection.write_frames(frames).await?;
+
+        let mut replies = Vec::with_capacity(frames.len());
+
+        for _ in frames {
+            match self.connection.read_frame().await? {
+                Some(frame) => replies.push(frame),
+                None => return Err(Error::new(ErrorKind::ConnectionReset, "connection reset by server").into()),
+            }
+        }
+
+        Ok(replies)
+    }
+
+    /// Reads the next frame pushed by the server without sending anything,
+    /// e.g. messages after a raw `SUBSCRIBE` or `MONITOR`.
+    pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> { self.connection.read_frame().await }
+
     #[instrument(skip(self))]
     pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
         let frame = Get::new(key).into_frame();
@@ -89,16 +528,26 @@ impl Client {
 
     #[instrument(skip(self))]
     pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
-        (); // instrument
         self.set_cmd(Set::new(key, value, None)).await
     }
 
     #[instrument(skip(self))]
     pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
-        (); // instrument
         self.set_cmd(Set::new(key, value, Some(expiration))).await
     }
 
+    /// Gets `key` and decodes the value with `codec`.
+    pub async fn get_as<T: DeserializeOwned>(&mut self, key: &str, codec: impl Codec) -> crate::Result<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(codec.decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, codec: impl Codec) -> crate::Result<()> { self.set(key, codec.encode(value)?).await }
+
+    pub async fn set_expires_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, expiration: Duration, codec: impl Codec) -> crate::Result<()> { self.set_expires(key, codec.encode(value)?, expiration).await }
+
     async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
         let frame = cmd.into_frame();
         debug!(request = ?frame);
@@ -119,7 +568,7 @@ impl Client {
         self.connection.write_frame(&frame).await?;
 
         match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
+            Frame::Integer(response) => Ok(response as u64),
             frame => Err(frame.to_error()),
         }
     }
@@ -160,7 +609,7 @@ impl Client {
         debug!(?response);
 
         match response {
-            Some(Frame::Error(msg)) => Err(msg.into()),
+            Some(Frame::Error(msg)) => Err(crate::Error::from_reply(&msg)),
             Some(frame) => Ok(frame),
             None => Err(Error::new(ErrorKind::ConnectionReset, "connection reset by server").into()),
         }
@@ -0,0 +1,157 @@
+use crate::clients::{Client, Codec};
+use crate::cmd::{Get, Set};
+use crate::pkg::cluster::{key_slot, SLOTS};
+use crate::pkg::Frame;
+use crate::Error;
+
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+const MAX_REDIRECTS: usize = 5;
+
+pub struct ClusterClient {
+    seeds: Vec<String>,
+    slots: Vec<Option<String>>,
+    connections: HashMap<String, Client>,
+}
+
+impl ClusterClient {
+    pub async fn connect(seeds: &[impl ToString]) -> crate::Result<ClusterClient> {
+        let mut client = ClusterClient {
+            seeds: seeds.iter().map(ToString::to_string).collect(),
+            slots: vec![None; SLOTS],
+            connections: HashMap::new(),
+        };
+
+        client.refresh_slots().await?;
+        Ok(client)
+    }
+
+    pub async fn refresh_slots(&mut self) -> crate::Result<()> {
+        let mut candidates: Vec<String> = self.connections.keys().cloned().collect();
+        candidates.extend(self.seeds.iter().cloned());
+
+        let mut last_err = None;
+
+        for addr in candidates {
+            let ranges = match self.connection(&addr).await {
+                Ok(client) => client.cluster_slots().await,
+                Err(err) => Err(err),
+            };
+
+            match ranges.and_then(valid_ranges) {
+                Ok(ranges) => {
+                    self.slots = vec![None; SLOTS];
+
+                    for (start, end, node) in ranges {
+                        for slot in &mut self.slots[start as usize..=end as usize] {
+                            *slot = Some(node.clone());
+                        }
+                    }
+
+                    return Ok(());
+                }
+                Err(err) => {
+                    self.connections.remove(&addr);
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| "no cluster seed nodes given".into()))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&mut self, key: &str) -> crate::Result<Option<Bytes>> {
+        match self.route(key, Get::new(key).into_frame()).await? {
+            Frame::Simple(value) => Ok(Some(value.into())),
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Null => Ok(None),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set(&mut self, key: &str, value: Bytes) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, None)).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn set_expires(&mut self, key: &str, value: Bytes, expiration: Duration) -> crate::Result<()> {
+        self.set_cmd(Set::new(key, value, Some(expiration))).await
+    }
+
+    pub async fn get_as<T: DeserializeOwned>(&mut self, key: &str, codec: impl Codec) -> crate::Result<Option<T>> {
+        match self.get(key).await? {
+            Some(value) => Ok(Some(codec.decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_as<T: Serialize + ?Sized>(&mut self, key: &str, value: &T, codec: impl Codec) -> crate::Result<()> { self.set(key, codec.encode(value)?).await }
+
+    async fn set_cmd(&mut self, cmd: Set) -> crate::Result<()> {
+        let key = cmd.key().to_string();
+
+        match self.route(&key, cmd.into_frame()).await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    async fn route(&mut self, key: &str, frame: Frame) -> crate::Result<Frame> {
+        let slot = key_slot(key) as usize;
+        let mut addr = self.slots[slot].clone().or_else(|| self.seeds.first().cloned()).ok_or("no cluster nodes known")?;
+        let mut asking = false;
+
+        for _ in 0..MAX_REDIRECTS {
+            let client = self.connection(&addr).await?;
+
+            if asking {
+                client.asking().await?;
+            }
+
+            match client.execute(frame.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(Error::Moved { slot, .. } | Error::Ask { slot, .. }) if slot as usize >= SLOTS => {
+                    return Err(Error::Protocol(format!("redirect to invalid slot {}", slot)));
+                }
+                Err(Error::Moved { slot, addr: target }) => {
+                    debug!(slot, %target, "following MOVED redirect");
+                    self.slots[slot as usize] = Some(target.clone());
+                    addr = target;
+                    asking = false;
+                }
+                Err(Error::Ask { addr: target, .. }) => {
+                    debug!(%target, "following ASK redirect");
+                    addr = target;
+                    asking = true;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err("too many cluster redirects".into())
+    }
+
+    async fn connection(&mut self, addr: &str) -> crate::Result<&mut Client> {
+        if !self.connections.contains_key(addr) {
+            let client = Client::connect(addr).await?;
+            self.connections.insert(addr.to_string(), client);
+        }
+
+        Ok(self.connections.get_mut(addr).unwrap())
+    }
+}
+
+/// Rejects CLUSTER SLOTS ranges that don't fit the slot table.
+fn valid_ranges(ranges: Vec<(u16, u16, String)>) -> crate::Result<Vec<(u16, u16, String)>> {
+    match ranges.iter().find(|(start, end, _)| start > end || *end as usize >= SLOTS) {
+        Some((start, end, _)) => Err(Error::Protocol(format!("invalid CLUSTER SLOTS range {}-{}", start, end))),
+        None => Ok(ranges),
+    }
+}
@@ -0,0 +1,42 @@
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Serialization format for the typed client methods (`get_as`, `set_as`,
+/// ...). Values written with one codec must be read back with the same one.
+pub trait Codec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> crate::Result<Bytes>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> crate::Result<T>;
+}
+
+/// JSON, readable by any other client of the same keys.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+/// bincode, the format snapshots are stored in; compact but only readable
+/// from Rust.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+/// MessagePack with field names, so structs can gain optional fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> crate::Result<Bytes> { Ok(serde_json::to_vec(value)?.into()) }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> crate::Result<T> { Ok(serde_json::from_slice(bytes)?) }
+}
+
+impl Codec for Bincode {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> crate::Result<Bytes> { Ok(bincode::serialize(value)?.into()) }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> crate::Result<T> { Ok(bincode::deserialize(bytes)?) }
+}
+
+impl Codec for MessagePack {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> crate::Result<Bytes> { Ok(rmp_serde::to_vec_named(value)?.into()) }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> crate::Result<T> { Ok(rmp_serde::from_slice(bytes)?) }
+}
@@ -1,7 +1,13 @@
 mod blocking_client;
 mod buffered_client;
+mod caching_client;
 mod client;
+mod codec;
+mod cluster_client;
 
 pub use blocking_client::BlockingClient;
 pub use buffered_client::BufferedClient;
+pub use caching_client::CachingClient;
 pub use client::{Client, Message, Subscriber};
+pub use codec::{Bincode, Codec, Json, MessagePack};
+pub use cluster_client::ClusterClient;
@@ -0,0 +1,100 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub enum Acl {
+    SetUser { username: String, rules: Vec<String> },
+    GetUser { username: String },
+    List,
+    WhoAmI,
+}
+
+impl Acl {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Acl> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "setuser" => {
+                let username = parse.next_string()?;
+                let mut rules = vec![];
+
+                loop {
+                    match parse.next_string() {
+                        Ok(rule) => rules.push(rule),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(Acl::SetUser { username, rules })
+            }
+            "getuser" => Ok(Acl::GetUser { username: parse.next_string()? }),
+            "list" => Ok(Acl::List),
+            "whoami" => Ok(Acl::WhoAmI),
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try ACL SETUSER, GETUSER, LIST or WHOAMI.", subcommand))),
+        }
+    }
+
+    pub fn categories(&self) -> &'static [&'static str] {
+        match self {
+            Acl::WhoAmI => &["slow"],
+            _ => &["admin", "slow", "dangerous"],
+        }
+    }
+
+    #[instrument(skip(self, db, dst, session))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &Session) -> crate::Result<()> {
+        let response = match self {
+            Acl::SetUser { username, rules } => match db.acl().set_user(&username, &rules) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(msg) => Error::Err(msg).to_frame(),
+            },
+            Acl::GetUser { username } => match db.acl().get_user(&username) {
+                Some(user) => Frame::Array(vec![
+                    Frame::Bulk(Bytes::from_static(b"flags")),
+                    Frame::Array(user.flags().into_iter().map(|flag| Frame::Bulk(Bytes::from_static(flag.as_bytes()))).collect()),
+                    Frame::Bulk(Bytes::from_static(b"passwords")),
+                    Frame::Array(user.passwords().map(|hash| Frame::Bulk(Bytes::from(hash.clone()))).collect()),
+                    Frame::Bulk(Bytes::from_static(b"commands")),
+                    Frame::Bulk(Bytes::from(user.commands())),
+                    Frame::Bulk(Bytes::from_static(b"keys")),
+                    Frame::Array(user.keys().iter().map(|key| Frame::Bulk(Bytes::from(key.clone()))).collect()),
+                ]),
+                None => Frame::Null,
+            },
+            Acl::List => Frame::Array(db.acl().list().into_iter().map(|line| Frame::Bulk(Bytes::from(line))).collect()),
+            Acl::WhoAmI => Frame::Bulk(Bytes::from(session.user().to_string())),
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("acl".as_bytes()));
+
+        match self {
+            Acl::SetUser { username, rules } => {
+                frame.push_bulk(Bytes::from("setuser".as_bytes()));
+                frame.push_bulk(Bytes::from(username.into_bytes()));
+                for rule in rules {
+                    frame.push_bulk(Bytes::from(rule.into_bytes()));
+                }
+            }
+            Acl::GetUser { username } => {
+                frame.push_bulk(Bytes::from("getuser".as_bytes()));
+                frame.push_bulk(Bytes::from(username.into_bytes()));
+            }
+            Acl::List => frame.push_bulk(Bytes::from("list".as_bytes())),
+            Acl::WhoAmI => frame.push_bulk(Bytes::from("whoami".as_bytes())),
+        }
+
+        frame
+    }
+}
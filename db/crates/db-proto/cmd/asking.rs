@@ -0,0 +1,30 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Asking;
+
+impl Asking {
+    pub fn new() -> Asking { Asking }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Asking> { Ok(Asking) }
+
+    #[instrument(skip(self, dst, session))]
+    pub async fn apply(self, dst: &mut Connection, session: &mut Session) -> crate::Result<()> {
+        session.set_asking();
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("asking".as_bytes()));
+        frame
+    }
+}
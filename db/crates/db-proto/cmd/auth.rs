@@ -0,0 +1,61 @@
+use crate::pkg::acl::DEFAULT_USER;
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Auth {
+    username: Option<String>,
+    password: String,
+}
+
+impl Auth {
+    pub fn new(username: Option<String>, password: impl ToString) -> Auth {
+        Auth {
+            username,
+            password: password.to_string(),
+        }
+    }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Auth> {
+        let first = parse.next_string()?;
+
+        match parse.next_string() {
+            Ok(password) => Ok(Auth { username: Some(first), password }),
+            Err(ParseError::EndOfStream) => Ok(Auth { username: None, password: first }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    #[instrument(skip(self, db, dst, session))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &mut Session) -> crate::Result<()> {
+        let implicit = self.username.is_none();
+        let username = self.username.unwrap_or_else(|| DEFAULT_USER.to_string());
+
+        let response = if implicit && !db.acl().requires_password(&username) {
+            Error::Err("AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?".into()).to_frame()
+        } else if db.acl().authenticate(&username, &self.password) {
+            session.login(username);
+            Frame::Simple("OK".to_string())
+        } else {
+            Error::WrongPass("invalid username-password pair or user is disabled.".into()).to_frame()
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("auth".as_bytes()));
+        if let Some(username) = self.username {
+            frame.push_bulk(Bytes::from(username.into_bytes()));
+        }
+        frame.push_bulk(Bytes::from(self.password.into_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,249 @@
+use crate::pkg::registry::Filter;
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub enum Client {
+    List { ids: Vec<u64> },
+    Kill { addr: Option<String>, filter: Filter, skipme: bool },
+    SetName { name: String },
+    GetName,
+    Id,
+    Pause { timeout: Duration, writes_only: bool },
+    Unpause,
+    Tracking { on: bool, redirect: Option<u64> },
+}
+
+impl Client {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Client> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "list" => {
+                let mut ids = vec![];
+
+                match parse.next_string() {
+                    Ok(option) if option.eq_ignore_ascii_case("id") => loop {
+                        match parse.next_int() {
+                            Ok(id) => ids.push(id),
+                            Err(ParseError::EndOfStream) if !ids.is_empty() => break,
+                            Err(_) => return Err(Error::Err("Invalid client ID".into())),
+                        }
+                    },
+                    Ok(_) => return Err(Error::Err("syntax error".into())),
+                    Err(ParseError::EndOfStream) => {}
+                    Err(err) => return Err(err.into()),
+                }
+
+                Ok(Client::List { ids })
+            }
+            "kill" => {
+                let first = parse.next_string()?;
+                let mut filter = Filter::default();
+                let mut skipme = true;
+
+                let value = match parse.next_string() {
+                    Ok(value) => value,
+                    Err(ParseError::EndOfStream) => return Ok(Client::Kill { addr: Some(first), filter, skipme }),
+                    Err(err) => return Err(err.into()),
+                };
+
+                let mut option = first;
+                let mut value = value;
+
+                loop {
+                    match &option.to_lowercase()[..] {
+                        "id" => filter.id = Some(value.parse().map_err(|_| Error::Err("client-id should be greater than 0".into()))?),
+                        "addr" => filter.addr = Some(value),
+                        "user" => filter.user = Some(value),
+                        "skipme" => match &value.to_lowercase()[..] {
+                            "yes" => skipme = true,
+                            "no" => skipme = false,
+                            _ => return Err(Error::Err("syntax error".into())),
+                        },
+                        _ => return Err(Error::Err("syntax error".into())),
+                    }
+
+                    option = match parse.next_string() {
+                        Ok(option) => option,
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    };
+                    value = parse.next_string().map_err(|_| Error::Err("syntax error".into()))?;
+                }
+
+                Ok(Client::Kill { addr: None, filter, skipme })
+            }
+            "setname" => Ok(Client::SetName { name: parse.next_string()? }),
+            "getname" => Ok(Client::GetName),
+            "id" => Ok(Client::Id),
+            "pause" => {
+                let timeout = Duration::from_millis(parse.next_int().map_err(|_| Error::Err("timeout is not an integer or out of range".into()))?);
+
+                let writes_only = match parse.next_string() {
+                    Ok(mode) if mode.eq_ignore_ascii_case("write") => true,
+                    Ok(mode) if mode.eq_ignore_ascii_case("all") => false,
+                    Ok(_) => return Err(Error::Err("syntax error".into())),
+                    Err(ParseError::EndOfStream) => false,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(Client::Pause { timeout, writes_only })
+            }
+            "unpause" => Ok(Client::Unpause),
+            "tracking" => {
+                let on = match &parse.next_string()?.to_lowercase()[..] {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(Error::Err("syntax error".into())),
+                };
+
+                let mut redirect = None;
+
+                loop {
+                    match parse.next_string() {
+                        Ok(option) if option.eq_ignore_ascii_case("redirect") => redirect = Some(parse.next_int().map_err(|_| Error::Err("Invalid client ID".into()))?),
+                        Ok(_) => return Err(Error::Err("syntax error".into())),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(Client::Tracking { on, redirect })
+            }
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try CLIENT LIST, KILL, SETNAME, GETNAME, ID, PAUSE, UNPAUSE or TRACKING.", subcommand))),
+        }
+    }
+
+    pub fn categories(&self) -> &'static [&'static str] {
+        match self {
+            Client::SetName { .. } | Client::GetName | Client::Id | Client::Tracking { .. } => &["slow", "connection"],
+            _ => &["admin", "slow", "dangerous", "connection"],
+        }
+    }
+
+    #[instrument(skip(self, db, dst, session))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &mut Session) -> crate::Result<()> {
+        let registry = db.clients();
+        let ok = || Frame::Simple("OK".to_string());
+
+        let response = match self {
+            Client::List { ids } => Frame::Bulk(Bytes::from(registry.list(&ids))),
+            Client::Kill { addr: Some(addr), .. } => {
+                let filter = Filter {
+                    addr: Some(addr),
+                    ..Filter::default()
+                };
+
+                match registry.kill(&filter) {
+                    0 => Error::Err("No such client".into()).to_frame(),
+                    _ => ok(),
+                }
+            }
+            Client::Kill { mut filter, skipme, .. } => {
+                if skipme {
+                    filter.skip = Some(session.id());
+                }
+                Frame::Integer(registry.kill(&filter) as i64)
+            }
+            Client::SetName { name } if name.chars().any(|c| !c.is_ascii_graphic()) => Error::Err("Client names cannot contain spaces, newlines or special characters.".into()).to_frame(),
+            Client::SetName { name } => {
+                if let Some(client) = registry.get(session.id()) {
+                    client.set_name(name);
+                }
+                ok()
+            }
+            Client::GetName => match registry.get(session.id()).map(|client| client.name()) {
+                Some(name) if !name.is_empty() => Frame::Bulk(Bytes::from(name)),
+                _ => Frame::Null,
+            },
+            Client::Id => Frame::Integer(session.id() as i64),
+            Client::Pause { timeout, writes_only } => {
+                registry.pause(timeout, writes_only);
+                ok()
+            }
+            Client::Unpause => {
+                registry.unpause();
+                ok()
+            }
+            Client::Tracking { on: false, .. } => {
+                db.tracking().disable(session.id());
+                ok()
+            }
+            // Invalidations can only be pushed over a separate subscribed
+            // connection, as RESP2 has no out of band replies.
+            Client::Tracking { redirect: None, .. } => Error::Err("Tracking requires REDIRECT to a client subscribed to __redis__:invalidate".into()).to_frame(),
+            Client::Tracking { redirect: Some(redirect), .. } if registry.get(redirect).is_none() => Error::Err("The client ID you want redirect to does not exist".into()).to_frame(),
+            Client::Tracking { redirect: Some(redirect), .. } => {
+                db.tracking().enable(session.id(), redirect);
+                ok()
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("client".as_bytes()));
+
+        match self {
+            Client::List { ids } => {
+                frame.push_bulk(Bytes::from("list".as_bytes()));
+                if !ids.is_empty() {
+                    frame.push_bulk(Bytes::from("id".as_bytes()));
+                    for id in ids {
+                        frame.push_bulk(Bytes::from(id.to_string()));
+                    }
+                }
+            }
+            Client::Kill { addr, filter, skipme } => {
+                frame.push_bulk(Bytes::from("kill".as_bytes()));
+
+                if let Some(addr) = addr {
+                    frame.push_bulk(Bytes::from(addr.into_bytes()));
+                    return frame;
+                }
+
+                let options = [("id", filter.id.map(|id| id.to_string())), ("addr", filter.addr), ("user", filter.user), ("skipme", Some(if skipme { "yes" } else { "no" }.to_string()))];
+
+                for (option, value) in options {
+                    if let Some(value) = value {
+                        frame.push_bulk(Bytes::from(option.as_bytes()));
+                        frame.push_bulk(Bytes::from(value.into_bytes()));
+                    }
+                }
+            }
+            Client::SetName { name } => {
+                frame.push_bulk(Bytes::from("setname".as_bytes()));
+                frame.push_bulk(Bytes::from(name.into_bytes()));
+            }
+            Client::GetName => frame.push_bulk(Bytes::from("getname".as_bytes())),
+            Client::Id => frame.push_bulk(Bytes::from("id".as_bytes())),
+            Client::Pause { timeout, writes_only } => {
+                frame.push_bulk(Bytes::from("pause".as_bytes()));
+                frame.push_bulk(Bytes::from(timeout.as_millis().to_string()));
+                frame.push_bulk(Bytes::from(if writes_only { "write" } else { "all" }.as_bytes()));
+            }
+            Client::Unpause => frame.push_bulk(Bytes::from("unpause".as_bytes())),
+            Client::Tracking { on, redirect } => {
+                frame.push_bulk(Bytes::from("tracking".as_bytes()));
+                frame.push_bulk(Bytes::from(if on { "on" } else { "off" }.as_bytes()));
+
+                if let Some(redirect) = redirect {
+                    frame.push_bulk(Bytes::from("redirect".as_bytes()));
+                    frame.push_bulk(Bytes::from(redirect.to_string()));
+                }
+            }
+        }
+
+        frame
+    }
+}
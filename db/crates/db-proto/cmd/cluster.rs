@@ -0,0 +1,99 @@
+use crate::pkg::cluster::key_slot;
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub enum Cluster {
+    Slots,
+    Nodes,
+    Keyslot { key: String },
+    Info,
+    Myid,
+    SetSlot { slot: u16, state: String, node_id: Option<String> },
+}
+
+impl Cluster {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Cluster> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "slots" => Ok(Cluster::Slots),
+            "nodes" => Ok(Cluster::Nodes),
+            "keyslot" => Ok(Cluster::Keyslot { key: parse.next_string()? }),
+            "info" => Ok(Cluster::Info),
+            "myid" => Ok(Cluster::Myid),
+            "setslot" => {
+                let slot = parse.next_int()?.try_into().map_err(|_| Error::Err("Invalid or out of range slot".into()))?;
+                let state = parse.next_string()?.to_lowercase();
+
+                let node_id = match parse.next_string() {
+                    Ok(id) => Some(id),
+                    Err(ParseError::EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(Cluster::SetSlot { slot, state, node_id })
+            }
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try CLUSTER SLOTS, NODES, KEYSLOT, INFO, MYID or SETSLOT.", subcommand))),
+        }
+    }
+
+    pub fn categories(&self) -> &'static [&'static str] {
+        match self {
+            Cluster::SetSlot { .. } => &["admin", "slow", "dangerous"],
+            _ => &["slow"],
+        }
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let cluster = db.cluster();
+        let disabled = || Error::Err("This instance has cluster support disabled".into()).to_frame();
+
+        let response = match self {
+            Cluster::Keyslot { key } => Frame::Integer(key_slot(&key) as i64),
+            Cluster::Info => Frame::Bulk(Bytes::from(cluster.info())),
+            Cluster::Slots => cluster.slots().unwrap_or_else(disabled),
+            Cluster::Nodes => cluster.nodes().map(|nodes| Frame::Bulk(Bytes::from(nodes))).unwrap_or_else(disabled),
+            Cluster::Myid => cluster.myself().map(|id| Frame::Bulk(Bytes::from(id))).unwrap_or_else(disabled),
+            Cluster::SetSlot { slot, state, node_id } => match cluster.set_slot(slot, &state, node_id.as_deref()) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => err.to_frame(),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("cluster".as_bytes()));
+
+        match self {
+            Cluster::Slots => frame.push_bulk(Bytes::from("slots".as_bytes())),
+            Cluster::Nodes => frame.push_bulk(Bytes::from("nodes".as_bytes())),
+            Cluster::Info => frame.push_bulk(Bytes::from("info".as_bytes())),
+            Cluster::Myid => frame.push_bulk(Bytes::from("myid".as_bytes())),
+            Cluster::Keyslot { key } => {
+                frame.push_bulk(Bytes::from("keyslot".as_bytes()));
+                frame.push_bulk(Bytes::from(key.into_bytes()));
+            }
+            Cluster::SetSlot { slot, state, node_id } => {
+                frame.push_bulk(Bytes::from("setslot".as_bytes()));
+                frame.push_int(slot as i64);
+                frame.push_bulk(Bytes::from(state.into_bytes()));
+                if let Some(id) = node_id {
+                    frame.push_bulk(Bytes::from(id.into_bytes()));
+                }
+            }
+        }
+
+        frame
+    }
+}
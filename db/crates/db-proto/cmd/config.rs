@@ -0,0 +1,89 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub enum Config {
+    Get { pattern: String },
+    Set { params: Vec<(String, String)> },
+    Rewrite,
+}
+
+impl Config {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Config> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => Ok(Config::Get { pattern: parse.next_string()? }),
+            "set" => {
+                let mut params = vec![(parse.next_string()?, parse.next_string()?)];
+
+                loop {
+                    match parse.next_string() {
+                        Ok(name) => params.push((name, parse.next_string().map_err(|_| Error::Err("syntax error".into()))?)),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(Config::Set { params })
+            }
+            "rewrite" => Ok(Config::Rewrite),
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try CONFIG GET, SET or REWRITE.", subcommand))),
+        }
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let config = db.config();
+
+        let response = match self {
+            Config::Get { pattern } => {
+                let mut response = Frame::array();
+                for (name, value) in config.get(db, &pattern) {
+                    response.push_bulk(Bytes::from(name));
+                    response.push_bulk(Bytes::from(value));
+                }
+                response
+            }
+            Config::Set { params } => match params.iter().try_for_each(|(name, value)| config.set(db, name, value)) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err) => err.to_frame(),
+            },
+            Config::Rewrite => match config.rewrite(db) {
+                Ok(()) => Frame::Simple("OK".to_string()),
+                Err(err @ crate::Error::Err(_)) => err.to_frame(),
+                Err(err) => Error::Err(format!("Rewriting config file: {}", err)).to_frame(),
+            },
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("config".as_bytes()));
+
+        match self {
+            Config::Get { pattern } => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                frame.push_bulk(Bytes::from(pattern));
+            }
+            Config::Set { params } => {
+                frame.push_bulk(Bytes::from("set".as_bytes()));
+                for (name, value) in params {
+                    frame.push_bulk(Bytes::from(name));
+                    frame.push_bulk(Bytes::from(value));
+                }
+            }
+            Config::Rewrite => frame.push_bulk(Bytes::from("rewrite".as_bytes())),
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Dbsize;
+
+impl Dbsize {
+    pub fn new() -> Dbsize { Dbsize }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Dbsize> { Ok(Dbsize) }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.len() as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("dbsize".as_bytes()));
+        frame
+    }
+}
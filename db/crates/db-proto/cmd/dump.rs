@@ -1,34 +1,44 @@
 use crate::prelude::*;
+use crate::Error;
 
 use bytes::Bytes;
-use std::fs::File;
-use std::io::Write;
 use std::path::PathBuf;
 use tracing::{info, instrument};
 
 #[derive(Debug)]
 pub struct Dump {
-    path: PathBuf,
+    path: Option<PathBuf>,
 }
 
 impl Dump {
-    pub fn new(path: PathBuf) -> Dump { Dump { path } }
+    pub fn new(path: Option<PathBuf>) -> Dump { Dump { path } }
 
     pub fn parse_frames(parse: &mut Parse) -> crate::Result<Dump> {
-        let path = parse.next_string()?;
-        Ok(Dump { path: PathBuf::from(path) })
+        match parse.next_string() {
+            Ok(path) => Ok(Dump { path: Some(PathBuf::from(path)) }),
+            Err(ParseError::EndOfStream) => Ok(Dump { path: None }),
+            Err(err) => Err(err.into()),
+        }
     }
 
     #[instrument(skip(self, db, dst))]
     pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let serializable_state = db.dump();
-        let serialized = bincode::serialize(&serializable_state)?;
+        let path = match db.data_dir().resolve(self.path.as_deref()) {
+            Ok(path) => path,
+            Err(err) => {
+                dst.write_frame(&err.to_frame()).await?;
+                return Ok(());
+            }
+        };
+
+        let response = match db.dump_to(&path).await {
+            Ok(()) => {
+                info!("Database state dumped to {:?}", path);
+                Frame::Simple("OK".to_string())
+            }
+            Err(err) => Error::Err(format!("failed to dump database: {}", err)).to_frame(),
+        };
 
-        let mut file = File::create(&self.path)?;
-        file.write_all(&serialized)?;
-        info!("Database state dumped to {:?}", self.path);
-
-        let response = Frame::Simple("OK".to_string());
         dst.write_frame(&response).await?;
 
         Ok(())
@@ -37,7 +47,9 @@ impl Dump {
     pub fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
         frame.push_bulk(Bytes::from("dump".as_bytes()));
-        frame.push_bulk(Bytes::from(self.path.to_string_lossy().into_owned()));
+        if let Some(path) = self.path {
+            frame.push_bulk(Bytes::from(path.to_string_lossy().into_owned()));
+        }
         frame
     }
 }
@@ -0,0 +1,46 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Flushall {
+    lazy: bool,
+}
+
+impl Flushall {
+    pub fn new(lazy: bool) -> Flushall { Flushall { lazy } }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Flushall> {
+        let lazy = match parse.next_string() {
+            Ok(mode) if mode.eq_ignore_ascii_case("async") => true,
+            Ok(mode) if mode.eq_ignore_ascii_case("sync") => false,
+            Ok(_) => return Err(Error::Err("syntax error".into())),
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Flushall { lazy })
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        db.flush_all(self.lazy);
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("flushall".as_bytes()));
+        if self.lazy {
+            frame.push_bulk(Bytes::from("async".as_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,70 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Info {
+    section: Option<String>,
+}
+
+impl Info {
+    pub fn new(section: Option<String>) -> Info { Info { section } }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        match parse.next_string() {
+            Ok(section) => Ok(Info { section: Some(section.to_lowercase()) }),
+            Err(ParseError::EndOfStream) => Ok(Info::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let all = matches!(self.section.as_deref(), None | Some("all") | Some("default") | Some("everything"));
+        let wants = |section: &str| all || self.section.as_deref() == Some(section);
+        let mut sections = vec![];
+
+        if wants("server") {
+            sections.push(db.stats().server_info());
+        }
+        if wants("clients") {
+            sections.push(db.stats().clients_info(db.config().maxclients()));
+        }
+        if wants("memory") {
+            sections.push(db.memory().info());
+        }
+        if wants("persistence") {
+            sections.push(db.stats().persistence_info());
+        }
+        if wants("stats") {
+            sections.push(db.stats().stats_info(db.memory().evicted()));
+        }
+        if wants("replication") {
+            sections.push(db.replication().info());
+        }
+        if wants("cluster") {
+            sections.push(format!("# Cluster\r\n{}", db.cluster().info()));
+        }
+        if wants("keyspace") {
+            sections.push(db.keyspace_info());
+        }
+
+        let info = sections.join("\r\n");
+
+        let response = Frame::Bulk(Bytes::from(info));
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        if let Some(section) = self.section {
+            frame.push_bulk(Bytes::from(section.into_bytes()));
+        }
+        frame
+    }
+}
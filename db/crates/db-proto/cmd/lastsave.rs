@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Lastsave;
+
+impl Lastsave {
+    pub fn new() -> Lastsave { Lastsave }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Lastsave> { Ok(Lastsave) }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(db.stats().last_save() as i64);
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("lastsave".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,96 @@
+use crate::pkg::metrics::LATENCY_BUCKETS;
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub enum Latency {
+    Histogram { commands: Vec<String> },
+    Reset,
+}
+
+impl Latency {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Latency> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "histogram" => {
+                let mut commands = vec![];
+
+                loop {
+                    match parse.next_string() {
+                        Ok(command) => commands.push(command.to_lowercase()),
+                        Err(ParseError::EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(Latency::Histogram { commands })
+            }
+            "reset" => Ok(Latency::Reset),
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try LATENCY HISTOGRAM or RESET.", subcommand))),
+        }
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Latency::Histogram { commands } => {
+                let mut response = vec![];
+
+                for (name, stats) in db.metrics().commands() {
+                    if !commands.is_empty() && !commands.contains(&name) {
+                        continue;
+                    }
+
+                    let mut cumulative = 0;
+                    let mut histogram = vec![];
+
+                    for (le, count) in LATENCY_BUCKETS.iter().zip(&stats.buckets) {
+                        cumulative += count;
+                        histogram.push(Frame::Integer((le * 1_000_000.0) as i64));
+                        histogram.push(Frame::Integer(cumulative as i64));
+                    }
+
+                    response.push(Frame::Bulk(Bytes::from(name)));
+                    response.push(Frame::Array(vec![
+                        Frame::Bulk(Bytes::from("calls".as_bytes())),
+                        Frame::Integer(stats.calls as i64),
+                        Frame::Bulk(Bytes::from("histogram_usec".as_bytes())),
+                        Frame::Array(histogram),
+                    ]));
+                }
+
+                Frame::Array(response)
+            }
+            Latency::Reset => {
+                db.metrics().reset();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("latency".as_bytes()));
+
+        match self {
+            Latency::Histogram { commands } => {
+                frame.push_bulk(Bytes::from("histogram".as_bytes()));
+                for command in commands {
+                    frame.push_bulk(Bytes::from(command.into_bytes()));
+                }
+            }
+            Latency::Reset => frame.push_bulk(Bytes::from("reset".as_bytes())),
+        }
+
+        frame
+    }
+}
@@ -1,36 +1,45 @@
-use crate::pkg::db::SerializableState;
 use crate::prelude::*;
+use crate::Error;
 
 use bytes::Bytes;
-use std::fs::File;
-use std::io::Read;
 use std::path::PathBuf;
 use tracing::{info, instrument};
 
 #[derive(Debug)]
 pub struct Load {
-    path: PathBuf,
+    path: Option<PathBuf>,
 }
 
 impl Load {
-    pub fn new(path: PathBuf) -> Load { Load { path } }
+    pub fn new(path: Option<PathBuf>) -> Load { Load { path } }
 
     pub fn parse_frames(parse: &mut Parse) -> crate::Result<Load> {
-        let path = parse.next_string()?;
-        Ok(Load { path: PathBuf::from(path) })
+        match parse.next_string() {
+            Ok(path) => Ok(Load { path: Some(PathBuf::from(path)) }),
+            Err(ParseError::EndOfStream) => Ok(Load { path: None }),
+            Err(err) => Err(err.into()),
+        }
     }
 
     #[instrument(skip(self, db, dst))]
     pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-        let mut file = File::open(&self.path)?;
-        let mut serialized = Vec::new();
-        file.read_to_end(&mut serialized)?;
+        let path = match db.data_dir().resolve(self.path.as_deref()) {
+            Ok(path) => path,
+            Err(err) => {
+                dst.write_frame(&err.to_frame()).await?;
+                return Ok(());
+            }
+        };
+
+        let response = match db.load_from(&path).await {
+            Ok(()) => {
+                db.replication().reset();
+                info!("Database state loaded from {:?}", path);
+                Frame::Simple("OK".to_string())
+            }
+            Err(err) => Error::Err(format!("failed to load database: {}", err)).to_frame(),
+        };
 
-        let serializable_state: SerializableState = bincode::deserialize(&serialized)?;
-        db.load(serializable_state);
-        info!("Database state loaded from {:?}", self.path);
-
-        let response = Frame::Simple("OK".to_string());
         dst.write_frame(&response).await?;
 
         Ok(())
@@ -39,7 +48,9 @@ impl Load {
     pub fn into_frame(self) -> Frame {
         let mut frame = Frame::array();
         frame.push_bulk(Bytes::from("load".as_bytes()));
-        frame.push_bulk(Bytes::from(self.path.to_string_lossy().into_owned()));
+        if let Some(path) = self.path {
+            frame.push_bulk(Bytes::from(path.to_string_lossy().into_owned()));
+        }
         frame
     }
 }
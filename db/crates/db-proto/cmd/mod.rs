@@ -1,22 +1,71 @@
+mod acl;
+mod asking;
+mod auth;
+mod client;
+mod cluster;
+mod config;
+mod dbsize;
 mod dump;
+mod flushall;
+mod flushdb;
 mod get;
+mod info;
+mod lastsave;
+mod latency;
 mod load;
+mod monitor;
+mod move_key;
 mod ping;
+mod psync;
 mod publish;
+mod replconf;
+mod replicaof;
+mod role;
+mod scan;
+mod select;
 mod set;
+mod slowlog;
 mod subscribe;
+mod swapdb;
+mod time;
+mod ttl;
 mod unknown;
 
+pub use acl::Acl;
+pub use asking::Asking;
+pub use auth::Auth;
+pub use client::Client;
+pub use cluster::Cluster;
+pub use config::Config;
+pub use dbsize::Dbsize;
 pub use dump::Dump;
+pub use flushall::Flushall;
+pub use flushdb::Flushdb;
 pub use get::Get;
+pub use info::Info;
+pub use lastsave::Lastsave;
+pub use latency::Latency;
 pub use load::Load;
+pub use monitor::Monitor;
+pub use move_key::Move;
 pub use ping::Ping;
+pub use psync::Psync;
 pub use publish::Publish;
+pub use replconf::Replconf;
+pub use replicaof::Replicaof;
+pub use role::Role;
+pub use scan::Scan;
+pub use select::Select;
 pub use set::Set;
+pub use slowlog::Slowlog;
 pub use subscribe::{Subscribe, Unsubscribe};
+pub use swapdb::Swapdb;
+pub use time::Time;
+pub use ttl::Ttl;
 pub use unknown::Unknown;
 
 use crate::prelude::*;
+use crate::Error;
 
 #[derive(Debug)]
 pub enum Command {
@@ -28,6 +77,30 @@ pub enum Command {
     Ping(Ping),
     Dump(Dump),
     Load(Load),
+    Auth(Auth),
+    Acl(Acl),
+    Replicaof(Replicaof),
+    Replconf(Replconf),
+    Psync(Psync),
+    Role(Role),
+    Info(Info),
+    Cluster(Cluster),
+    Asking(Asking),
+    Dbsize(Dbsize),
+    Time(Time),
+    Lastsave(Lastsave),
+    Slowlog(Slowlog),
+    Latency(Latency),
+    Client(Client),
+    Monitor(Monitor),
+    Config(Config),
+    Select(Select),
+    Move(Move),
+    Swapdb(Swapdb),
+    Flushdb(Flushdb),
+    Flushall(Flushall),
+    Scan(Scan),
+    Ttl(Ttl),
     Unknown(Unknown),
 }
 
@@ -36,33 +109,91 @@ impl Command {
         let mut parse = Parse::new(frame)?;
         let command_name = parse.next_string()?.to_lowercase();
 
-        let command = match &command_name[..] {
-            "get" => Command::Get(Get::parse_frames(&mut parse)?),
-            "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
-            "set" => Command::Set(Set::parse_frames(&mut parse)?),
-            "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
-            "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
-            "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
-            "dump" => Command::Dump(Dump::parse_frames(&mut parse)?),
-            "load" => Command::Load(Load::parse_frames(&mut parse)?),
-            _ => return Ok(Command::Unknown(Unknown::new(command_name))),
+        Command::parse_named(&mut parse, &command_name).map_err(|err| match err {
+            Error::WrongArity { .. } => Error::WrongArity { cmd: command_name.clone() },
+            err => err,
+        })
+    }
+
+    fn parse_named(parse: &mut Parse, command_name: &str) -> crate::Result<Command> {
+        let command = match command_name {
+            "get" => Command::Get(Get::parse_frames(parse)?),
+            "publish" => Command::Publish(Publish::parse_frames(parse)?),
+            "set" => Command::Set(Set::parse_frames(parse)?),
+            "subscribe" => Command::Subscribe(Subscribe::parse_frames(parse)?),
+            "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(parse)?),
+            "ping" => Command::Ping(Ping::parse_frames(parse)?),
+            "dump" => Command::Dump(Dump::parse_frames(parse)?),
+            "load" => Command::Load(Load::parse_frames(parse)?),
+            "auth" => Command::Auth(Auth::parse_frames(parse)?),
+            "acl" => Command::Acl(Acl::parse_frames(parse)?),
+            "replicaof" | "slaveof" => Command::Replicaof(Replicaof::parse_frames(parse)?),
+            "replconf" => Command::Replconf(Replconf::parse_frames(parse)?),
+            "psync" => Command::Psync(Psync::parse_frames(parse)?),
+            "role" => Command::Role(Role::parse_frames(parse)?),
+            "info" => Command::Info(Info::parse_frames(parse)?),
+            "cluster" => Command::Cluster(Cluster::parse_frames(parse)?),
+            "asking" => Command::Asking(Asking::parse_frames(parse)?),
+            "dbsize" => Command::Dbsize(Dbsize::parse_frames(parse)?),
+            "time" => Command::Time(Time::parse_frames(parse)?),
+            "lastsave" => Command::Lastsave(Lastsave::parse_frames(parse)?),
+            "slowlog" => Command::Slowlog(Slowlog::parse_frames(parse)?),
+            "latency" => Command::Latency(Latency::parse_frames(parse)?),
+            "client" => Command::Client(Client::parse_frames(parse)?),
+            "monitor" => Command::Monitor(Monitor::parse_frames(parse)?),
+            "config" => Command::Config(Config::parse_frames(parse)?),
+            "select" => Command::Select(Select::parse_frames(parse)?),
+            "move" => Command::Move(Move::parse_frames(parse)?),
+            "swapdb" => Command::Swapdb(Swapdb::parse_frames(parse)?),
+            "flushdb" => Command::Flushdb(Flushdb::parse_frames(parse)?),
+            "flushall" => Command::Flushall(Flushall::parse_frames(parse)?),
+            "scan" => Command::Scan(Scan::parse_frames(parse)?),
+            "ttl" => Command::Ttl(Ttl::parse_frames(parse, false)?),
+            "pttl" => Command::Ttl(Ttl::parse_frames(parse, true)?),
+            _ => return Ok(Command::Unknown(Unknown::new(command_name.to_string()))),
         };
 
         parse.finish()?;
         Ok(command)
     }
 
-    pub async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &mut Session, shutdown: &mut Shutdown) -> crate::Result<()> {
         use Command::*;
 
+        let db = &db.select(session.db());
+
         match self {
             Get(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
-            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Subscribe(cmd) => cmd.apply(db, dst, session, shutdown).await,
             Ping(cmd) => cmd.apply(dst).await,
             Dump(cmd) => cmd.apply(db, dst).await,
             Load(cmd) => cmd.apply(db, dst).await,
+            Auth(cmd) => cmd.apply(db, dst, session).await,
+            Acl(cmd) => cmd.apply(db, dst, session).await,
+            Replicaof(cmd) => cmd.apply(db, dst).await,
+            Replconf(cmd) => cmd.apply(dst, session).await,
+            Psync(cmd) => cmd.apply(db, dst, session, shutdown).await,
+            Role(cmd) => cmd.apply(db, dst).await,
+            Info(cmd) => cmd.apply(db, dst).await,
+            Cluster(cmd) => cmd.apply(db, dst).await,
+            Asking(cmd) => cmd.apply(dst, session).await,
+            Dbsize(cmd) => cmd.apply(db, dst).await,
+            Time(cmd) => cmd.apply(dst).await,
+            Lastsave(cmd) => cmd.apply(db, dst).await,
+            Slowlog(cmd) => cmd.apply(db, dst).await,
+            Latency(cmd) => cmd.apply(db, dst).await,
+            Client(cmd) => cmd.apply(db, dst, session).await,
+            Monitor(cmd) => cmd.apply(db, dst, shutdown).await,
+            Config(cmd) => cmd.apply(db, dst).await,
+            Select(cmd) => cmd.apply(db, dst, session).await,
+            Move(cmd) => cmd.apply(db, dst).await,
+            Swapdb(cmd) => cmd.apply(db, dst).await,
+            Flushdb(cmd) => cmd.apply(db, dst).await,
+            Flushall(cmd) => cmd.apply(db, dst).await,
+            Scan(cmd) => cmd.apply(db, dst, session).await,
+            Ttl(cmd) => cmd.apply(db, dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
             Unsubscribe(_) => Err("`Unsubscribe` is unsupported in this context".into()),
         }
@@ -71,14 +202,88 @@ impl Command {
     pub fn get_name(&self) -> &str {
         match self {
             Command::Get(_) => "get",
-            Command::Publish(_) => "pub",
+            Command::Publish(_) => "publish",
             Command::Set(_) => "set",
             Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
             Command::Ping(_) => "ping",
             Command::Dump(_) => "dump",
             Command::Load(_) => "load",
+            Command::Auth(_) => "auth",
+            Command::Acl(_) => "acl",
+            Command::Replicaof(_) => "replicaof",
+            Command::Replconf(_) => "replconf",
+            Command::Psync(_) => "psync",
+            Command::Role(_) => "role",
+            Command::Info(_) => "info",
+            Command::Cluster(_) => "cluster",
+            Command::Asking(_) => "asking",
+            Command::Dbsize(_) => "dbsize",
+            Command::Time(_) => "time",
+            Command::Lastsave(_) => "lastsave",
+            Command::Slowlog(_) => "slowlog",
+            Command::Latency(_) => "latency",
+            Command::Client(_) => "client",
+            Command::Monitor(_) => "monitor",
+            Command::Config(_) => "config",
+            Command::Select(_) => "select",
+            Command::Move(_) => "move",
+            Command::Swapdb(_) => "swapdb",
+            Command::Flushdb(_) => "flushdb",
+            Command::Flushall(_) => "flushall",
+            Command::Scan(_) => "scan",
+            Command::Ttl(cmd) => cmd.get_name(),
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
+
+    pub fn categories(&self) -> &'static [&'static str] {
+        match self {
+            Command::Get(_) => &["read", "keyspace", "string", "fast"],
+            Command::Set(_) => &["write", "keyspace", "string", "slow"],
+            Command::Publish(_) => &["pubsub", "fast"],
+            Command::Subscribe(_) | Command::Unsubscribe(_) => &["pubsub", "slow"],
+            Command::Ping(_) | Command::Auth(_) => &["connection", "fast"],
+            Command::Dump(_) => &["admin", "dangerous", "slow"],
+            Command::Load(_) => &["admin", "write", "dangerous", "slow"],
+            Command::Acl(cmd) => cmd.categories(),
+            Command::Replicaof(_) | Command::Replconf(_) | Command::Psync(_) => &["admin", "slow", "dangerous"],
+            Command::Role(_) => &["admin", "fast", "dangerous"],
+            Command::Info(_) => &["slow", "dangerous"],
+            Command::Cluster(cmd) => cmd.categories(),
+            Command::Asking(_) => &["connection", "fast"],
+            Command::Dbsize(_) => &["keyspace", "read", "fast"],
+            Command::Time(_) => &["fast"],
+            Command::Lastsave(_) => &["admin", "fast", "dangerous"],
+            Command::Slowlog(_) => &["admin", "slow", "dangerous"],
+            Command::Latency(_) => &["admin", "slow", "dangerous"],
+            Command::Client(cmd) => cmd.categories(),
+            Command::Monitor(_) => &["admin", "slow", "dangerous"],
+            Command::Config(_) => &["admin", "slow", "dangerous"],
+            Command::Select(_) => &["connection", "fast"],
+            Command::Move(_) => &["keyspace", "write", "fast"],
+            Command::Swapdb(_) => &["keyspace", "write", "fast", "dangerous"],
+            Command::Flushdb(_) => &["keyspace", "write", "slow", "dangerous"],
+            Command::Flushall(_) => &["keyspace", "write", "slow", "dangerous"],
+            Command::Scan(_) => &["keyspace", "read", "slow"],
+            Command::Ttl(_) => &["keyspace", "read", "fast"],
+            Command::Unknown(_) => &[],
+        }
+    }
+
+    pub fn is_write(&self) -> bool { self.categories().contains(&"write") }
+
+    /// Commands that may grow memory, refused with OOM when nothing can be
+    /// evicted. Writes that only free or move data are always let through.
+    pub fn is_denyoom(&self) -> bool { matches!(self, Command::Set(_) | Command::Load(_)) }
+
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Command::Get(cmd) => vec![cmd.key()],
+            Command::Set(cmd) => vec![cmd.key()],
+            Command::Move(cmd) => vec![cmd.key()],
+            Command::Ttl(cmd) => vec![cmd.key()],
+            _ => vec![],
+        }
+    }
 }
@@ -0,0 +1,45 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Monitor;
+
+impl Monitor {
+    pub fn new() -> Monitor { Monitor }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Monitor> { Ok(Monitor) }
+
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let mut rx = db.monitor().subscribe();
+        dst.write_frame(&Frame::Simple("OK".to_string())).await?;
+
+        loop {
+            select! {
+                res = rx.recv() => match res {
+                    Ok(line) => dst.write_frame(&Frame::Simple(String::from_utf8_lossy(&line).into_owned())).await?,
+                    Err(RecvError::Lagged(skipped)) => debug!(skipped, "monitor lagged behind"),
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                res = dst.read_frame() => {
+                    if res?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = shutdown.recv() => {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("monitor".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,48 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Move {
+    key: String,
+    db: u64,
+}
+
+impl Move {
+    pub fn new(key: impl ToString, db: u64) -> Move { Move { key: key.to_string(), db } }
+
+    pub fn key(&self) -> &str { &self.key }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Move> {
+        let key = parse.next_string()?;
+        let db = parse.next_int().map_err(|_| Error::Err("value is not an integer or out of range".into()))?;
+
+        Ok(Move { key, db })
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = if self.db >= db.databases() as u64 {
+            Error::Err("DB index is out of range".into()).to_frame()
+        } else if self.db as usize == db.index() {
+            Error::Err("source and destination objects are the same".into()).to_frame()
+        } else {
+            Frame::Integer(db.move_key(&self.key, self.db as usize) as i64)
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("move".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(Bytes::from(self.db.to_string()));
+        frame
+    }
+}
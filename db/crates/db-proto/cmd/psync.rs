@@ -0,0 +1,83 @@
+use crate::pkg::registry::Filter;
+use crate::pkg::replication::{Feed, Psync as Start};
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, instrument};
+
+#[derive(Debug)]
+pub struct Psync {
+    replid: String,
+    offset: Option<u64>,
+}
+
+impl Psync {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Psync> {
+        let replid = parse.next_string()?;
+        let offset = parse.next_string()?.parse().ok();
+
+        Ok(Psync { replid, offset })
+    }
+
+    #[instrument(skip(self, db, dst, session, shutdown))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &Session, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let replication = db.replication();
+        let start = replication.psync(&self.replid, self.offset, || Ok(bincode::serialize(&db.dump())?)).await?;
+
+        let mut feed = match start {
+            Start::Full { replid, offset, snapshot, feed } => {
+                info!("starting full resync with replica {} at offset {}", session.addr(), offset);
+                dst.write_frame(&Frame::Simple(format!("FULLRESYNC {} {}", replid, offset))).await?;
+                dst.write_frame(&Frame::Bulk(Bytes::from(snapshot))).await?;
+                feed
+            }
+            Start::Continue { replid, backlog, feed } => {
+                info!("continuing replication with replica {} from offset {:?}", session.addr(), self.offset);
+                dst.write_frame(&Frame::Simple(format!("CONTINUE {}", replid))).await?;
+                dst.write_bytes(&backlog).await?;
+                feed
+            }
+        };
+
+        let id = replication.register_replica(session.addr(), session.listening_port());
+
+        let result = async {
+            loop {
+                tokio::select! {
+                    msg = feed.recv() => match msg {
+                        Ok(Feed::Data(bytes)) => dst.write_bytes(&bytes).await?,
+                        Ok(Feed::Reset) | Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return Ok(()),
+                    },
+                    res = dst.read_frame() => match res? {
+                        Some(frame) => {
+                            if let Some(offset) = parse_ack(frame) {
+                                replication.ack(id, offset);
+                            }
+                        }
+                        None => return Ok(()),
+                    },
+                    _ = shutdown.recv() => return Ok(()),
+                }
+            }
+        }
+        .await;
+
+        replication.unregister_replica(id);
+
+        // Close the link once the stream ends, so a reset or lagging replica
+        // reconnects and resyncs instead of waiting on a dead stream.
+        db.clients().kill(&Filter { id: Some(session.id()), ..Filter::default() });
+        result
+    }
+}
+
+fn parse_ack(frame: Frame) -> Option<u64> {
+    match frame {
+        Frame::Array(parts) => match parts.as_slice() {
+            [replconf, ack, offset] if replconf.to_string().eq_ignore_ascii_case("replconf") && ack.to_string().eq_ignore_ascii_case("ack") => offset.to_string().parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -24,7 +24,7 @@ impl Publish {
 
     pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
         let num_subscribers = db.publish(&self.channel, self.message);
-        let response = Frame::Integer(num_subscribers as u64);
+        let response = Frame::Integer(num_subscribers as i64);
 
         dst.write_frame(&response).await?;
         Ok(())
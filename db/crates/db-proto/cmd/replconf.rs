@@ -0,0 +1,50 @@
+use crate::prelude::*;
+use crate::Error;
+
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Replconf {
+    options: Vec<(String, String)>,
+}
+
+impl Replconf {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Replconf> {
+        let mut options = vec![];
+
+        loop {
+            let option = match parse.next_string() {
+                Ok(option) => option.to_lowercase(),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            options.push((option, parse.next_string()?));
+        }
+
+        Ok(Replconf { options })
+    }
+
+    #[instrument(skip(self, dst, session))]
+    pub async fn apply(self, dst: &mut Connection, session: &mut Session) -> crate::Result<()> {
+        let mut response = Some(Frame::Simple("OK".to_string()));
+
+        for (option, value) in &self.options {
+            match &option[..] {
+                "listening-port" => match value.parse() {
+                    Ok(port) => session.set_listening_port(port),
+                    Err(_) => response = Some(Error::Err("invalid listening-port".into()).to_frame()),
+                },
+                "ack" | "getack" => response = None,
+                _ => {}
+            }
+        }
+
+        if let Some(response) = response {
+            debug!(?response);
+            dst.write_frame(&response).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Replicaof {
+    master: Option<(String, u16)>,
+}
+
+impl Replicaof {
+    pub fn new(master: Option<(String, u16)>) -> Replicaof { Replicaof { master } }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Replicaof> {
+        let host = parse.next_string()?;
+        let port = parse.next_string()?;
+
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(Replicaof { master: None });
+        }
+
+        let port = port.parse().map_err(|_| Error::Err("Invalid master port".into()))?;
+        Ok(Replicaof { master: Some((host, port)) })
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        match self.master {
+            Some((host, port)) => db.replication().replicate_from(db.clone(), host, port),
+            None => db.replication().promote(),
+        }
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("replicaof".as_bytes()));
+
+        match self.master {
+            Some((host, port)) => {
+                frame.push_bulk(Bytes::from(host.into_bytes()));
+                frame.push_bulk(Bytes::from(port.to_string()));
+            }
+            None => {
+                frame.push_bulk(Bytes::from("no".as_bytes()));
+                frame.push_bulk(Bytes::from("one".as_bytes()));
+            }
+        }
+
+        frame
+    }
+}
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Role;
+
+impl Role {
+    pub fn new() -> Role { Role }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Role> { Ok(Role) }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = db.replication().role();
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("role".as_bytes()));
+        frame
+    }
+}
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+const DEFAULT_COUNT: u64 = 10;
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    pattern: Option<String>,
+    count: u64,
+}
+
+impl Scan {
+    pub fn new(cursor: u64, pattern: Option<String>, count: Option<u64>) -> Scan {
+        Scan {
+            cursor,
+            pattern,
+            count: count.unwrap_or(DEFAULT_COUNT),
+        }
+    }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Scan> {
+        let cursor = parse.next_int().map_err(|_| Error::Err("invalid cursor".into()))?;
+        let mut scan = Scan::new(cursor, None, None);
+
+        loop {
+            match parse.next_string() {
+                Ok(option) if option.eq_ignore_ascii_case("match") => scan.pattern = Some(parse.next_string()?),
+                Ok(option) if option.eq_ignore_ascii_case("count") => match parse.next_int() {
+                    Ok(count) if count > 0 => scan.count = count,
+                    _ => return Err(Error::Err("value is not an integer or out of range".into())),
+                },
+                Ok(_) => return Err(Error::Err("syntax error".into())),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(scan)
+    }
+
+    #[instrument(skip(self, db, dst, session))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &Session) -> crate::Result<()> {
+        let (cursor, keys) = db.scan(self.cursor, self.pattern.as_deref(), self.count as usize);
+
+        // Keys outside the user's ACL key patterns are left out of the batch.
+        let user = db.acl().get_user(session.user());
+        let keys = keys.into_iter().filter(|key| user.as_ref().is_some_and(|user| user.can_access(key))).map(|key| Frame::Bulk(Bytes::from(key))).collect();
+        let response = Frame::Array(vec![Frame::Bulk(Bytes::from(cursor.to_string())), Frame::Array(keys)]);
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("scan".as_bytes()));
+        frame.push_bulk(Bytes::from(self.cursor.to_string()));
+
+        if let Some(pattern) = self.pattern {
+            frame.push_bulk(Bytes::from("match".as_bytes()));
+            frame.push_bulk(Bytes::from(pattern));
+        }
+
+        frame.push_bulk(Bytes::from("count".as_bytes()));
+        frame.push_bulk(Bytes::from(self.count.to_string()));
+        frame
+    }
+}
@@ -0,0 +1,44 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Select {
+    index: u64,
+}
+
+impl Select {
+    pub fn new(index: u64) -> Select { Select { index } }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse.next_int().map_err(|_| Error::Err("value is not an integer or out of range".into()))?;
+
+        Ok(Select { index })
+    }
+
+    #[instrument(skip(self, db, dst, session))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection, session: &mut Session) -> crate::Result<()> {
+        let response = if self.index >= db.databases() as u64 {
+            Error::Err("DB index is out of range".into()).to_frame()
+        } else if self.index != 0 && db.cluster().is_enabled() {
+            Error::Err("SELECT is not allowed in cluster mode".into()).to_frame()
+        } else {
+            session.select(self.index as usize);
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("select".as_bytes()));
+        frame.push_bulk(Bytes::from(self.index.to_string()));
+        frame
+    }
+}
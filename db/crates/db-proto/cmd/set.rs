@@ -63,7 +63,7 @@ impl Set {
         frame.push_bulk(self.value);
         if let Some(ms) = self.expire {
             frame.push_bulk(Bytes::from("px".as_bytes()));
-            frame.push_int(ms.as_millis() as u64);
+            frame.push_int(ms.as_millis() as i64);
         }
         frame
     }
@@ -0,0 +1,78 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+const DEFAULT_COUNT: usize = 10;
+
+#[derive(Debug)]
+pub enum Slowlog {
+    Get { count: Option<i64> },
+    Len,
+    Reset,
+}
+
+impl Slowlog {
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Slowlog> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => match parse.next_string() {
+                Ok(count) => Ok(Slowlog::Get {
+                    count: Some(count.parse().map_err(|_| Error::Err("value is out of range, must be positive".into()))?),
+                }),
+                Err(ParseError::EndOfStream) => Ok(Slowlog::Get { count: None }),
+                Err(err) => Err(err.into()),
+            },
+            "len" => Ok(Slowlog::Len),
+            "reset" => Ok(Slowlog::Reset),
+            _ => Err(Error::Err(format!("unknown subcommand '{}'. Try SLOWLOG GET, LEN or RESET.", subcommand))),
+        }
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let slowlog = db.slowlog();
+
+        let response = match self {
+            Slowlog::Get { count: Some(count) } if count < -1 => Error::Err("count should be greater than or equal to -1".into()).to_frame(),
+            Slowlog::Get { count } => {
+                let count = match count {
+                    Some(-1) => usize::MAX,
+                    Some(count) => count as usize,
+                    None => DEFAULT_COUNT,
+                };
+                Frame::Array(slowlog.get(count).iter().map(|entry| entry.to_frame()).collect())
+            }
+            Slowlog::Len => Frame::Integer(slowlog.len() as i64),
+            Slowlog::Reset => {
+                slowlog.reset();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("slowlog".as_bytes()));
+
+        match self {
+            Slowlog::Get { count } => {
+                frame.push_bulk(Bytes::from("get".as_bytes()));
+                if let Some(count) = count {
+                    frame.push_bulk(Bytes::from(count.to_string()));
+                }
+            }
+            Slowlog::Len => frame.push_bulk(Bytes::from("len".as_bytes())),
+            Slowlog::Reset => frame.push_bulk(Bytes::from("reset".as_bytes())),
+        }
+
+        frame
+    }
+}
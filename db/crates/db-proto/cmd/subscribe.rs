@@ -1,4 +1,5 @@
 use crate::cmd::{Parse, ParseError, Unknown};
+use crate::pkg::tracking::INVALIDATE_CHANNEL;
 use crate::prelude::*;
 
 use bytes::Bytes;
@@ -17,7 +18,7 @@ pub struct Unsubscribe {
     channels: Vec<String>,
 }
 
-type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+type Messages = Pin<Box<dyn Stream<Item = Frame> + Send>>;
 
 impl Subscribe {
     pub fn new(channels: Vec<String>) -> Subscribe { Subscribe { channels } }
@@ -37,12 +38,17 @@ impl Subscribe {
         Ok(Subscribe { channels })
     }
 
-    pub async fn apply(mut self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+    pub async fn apply(mut self, db: &Db, dst: &mut Connection, session: &Session, shutdown: &mut Shutdown) -> crate::Result<()> {
         let mut subscriptions = StreamMap::new();
+        let client = db.clients().get(session.id());
 
         loop {
             for channel_name in self.channels.drain(..) {
-                subscribe_to_channel(channel_name, &mut subscriptions, db, dst).await?;
+                subscribe_to_channel(channel_name, &mut subscriptions, db, session, dst).await?;
+            }
+
+            if let Some(client) = &client {
+                client.set_subscriptions(subscriptions.len());
             }
 
             select! {
@@ -79,18 +85,34 @@ impl Subscribe {
     }
 }
 
-async fn subscribe_to_channel(channel_name: String, subscriptions: &mut StreamMap<String, Messages>, db: &Db, dst: &mut Connection) -> crate::Result<()> {
-    let mut rx = db.subscribe(channel_name.clone());
-
-    let rx = Box::pin(async_stream::stream! {
-        loop {
-            match rx.recv().await {
-                Ok(msg) => yield msg,
-                Err(broadcast::error::RecvError::Lagged(_)) => {}
-                Err(_) => break,
+async fn subscribe_to_channel(channel_name: String, subscriptions: &mut StreamMap<String, Messages>, db: &Db, session: &Session, dst: &mut Connection) -> crate::Result<()> {
+    let rx: Messages = if channel_name == INVALIDATE_CHANNEL {
+        let mut rx = db.tracking().listen(session.id());
+
+        // A listener that fell behind can't tell which keys it missed, so it
+        // is told to drop everything, same as after a flush.
+        Box::pin(async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(Some(key)) => yield Frame::Array(vec![Frame::Bulk(Bytes::from(key))]),
+                    Ok(None) | Err(broadcast::error::RecvError::Lagged(_)) => yield Frame::Null,
+                    Err(_) => break,
+                }
             }
-        }
-    });
+        })
+    } else {
+        let mut rx = db.subscribe(channel_name.clone());
+
+        Box::pin(async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(msg) => yield Frame::Bulk(msg),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(_) => break,
+                }
+            }
+        })
+    };
 
     subscriptions.insert(channel_name.clone(), rx);
 
@@ -103,7 +125,7 @@ async fn subscribe_to_channel(channel_name: String, subscriptions: &mut StreamMa
 async fn handle_command(frame: Frame, subscribe_to: &mut Vec<String>, subscriptions: &mut StreamMap<String, Messages>, dst: &mut Connection) -> crate::Result<()> {
     match Command::from_frame(frame)? {
         Command::Subscribe(subscribe) => {
-            subscribe_to.extend(subscribe.channels.into_iter());
+            subscribe_to.extend(subscribe.channels);
         }
         Command::Unsubscribe(mut unsubscribe) => {
             if unsubscribe.channels.is_empty() {
@@ -129,7 +151,7 @@ fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"subscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
@@ -137,17 +159,11 @@ fn make_unsubscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"unsubscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
-fn make_message_frame(channel_name: String, msg: Bytes) -> Frame {
-    let mut response = Frame::array();
-    response.push_bulk(Bytes::from_static(b"message"));
-    response.push_bulk(Bytes::from(channel_name));
-    response.push_bulk(msg);
-    response
-}
+fn make_message_frame(channel_name: String, msg: Frame) -> Frame { Frame::Array(vec![Frame::Bulk(Bytes::from_static(b"message")), Frame::Bulk(Bytes::from(channel_name)), msg]) }
 
 impl Unsubscribe {
     pub fn new(channels: &[String]) -> Unsubscribe { Unsubscribe { channels: channels.to_vec() } }
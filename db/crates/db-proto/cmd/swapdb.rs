@@ -0,0 +1,49 @@
+use crate::prelude::*;
+use crate::Error;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Swapdb {
+    first: u64,
+    second: u64,
+}
+
+impl Swapdb {
+    pub fn new(first: u64, second: u64) -> Swapdb { Swapdb { first, second } }
+
+    pub fn parse_frames(parse: &mut Parse) -> crate::Result<Swapdb> {
+        let first = parse.next_int().map_err(|_| Error::Err("invalid first DB index".into()))?;
+        let second = parse.next_int().map_err(|_| Error::Err("invalid second DB index".into()))?;
+
+        Ok(Swapdb { first, second })
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let databases = db.databases() as u64;
+
+        let response = if self.first >= databases || self.second >= databases {
+            Error::Err("DB index is out of range".into()).to_frame()
+        } else if db.cluster().is_enabled() {
+            Error::Err("SWAPDB is not allowed in cluster mode".into()).to_frame()
+        } else {
+            db.swap(self.first as usize, self.second as usize);
+            Frame::Simple("OK".to_string())
+        };
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("swapdb".as_bytes()));
+        frame.push_bulk(Bytes::from(self.first.to_string()));
+        frame.push_bulk(Bytes::from(self.second.to_string()));
+        frame
+    }
+}
@@ -0,0 +1,33 @@
+use crate::pkg::stats::unix_time;
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Default)]
+pub struct Time;
+
+impl Time {
+    pub fn new() -> Time { Time }
+
+    pub fn parse_frames(_parse: &mut Parse) -> crate::Result<Time> { Ok(Time) }
+
+    #[instrument(skip(self, dst))]
+    pub async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let now = unix_time();
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from(now.as_secs().to_string()));
+        response.push_bulk(Bytes::from(now.subsec_micros().to_string()));
+
+        debug!(?response);
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("time".as_bytes()));
+        frame
+    }
+}
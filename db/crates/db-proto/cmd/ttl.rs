@@ -0,0 +1,48 @@
+use crate::prelude::*;
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct Ttl {
+    key: String,
+    millis: bool,
+}
+
+impl Ttl {
+    pub fn new(key: impl ToString, millis: bool) -> Ttl { Ttl { key: key.to_string(), millis } }
+
+    pub fn key(&self) -> &str { &self.key }
+
+    pub fn get_name(&self) -> &str { if self.millis { "pttl" } else { "ttl" } }
+
+    pub fn parse_frames(parse: &mut Parse, millis: bool) -> crate::Result<Ttl> {
+        let key = parse.next_string()?;
+
+        Ok(Ttl { key, millis })
+    }
+
+    #[instrument(skip(self, db, dst))]
+    pub async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let ttl: i64 = match db.ttl(&self.key) {
+            None => -2,
+            Some(None) => -1,
+            Some(Some(ttl)) if self.millis => ttl.as_millis() as i64,
+            Some(Some(ttl)) => ttl.as_millis().div_ceil(1000) as i64,
+        };
+
+        let response = Frame::Integer(ttl);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    pub fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from(self.get_name().to_string()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame
+    }
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::Error;
 use tracing::{debug, instrument};
 
 #[derive(Debug)]
@@ -13,7 +14,7 @@ impl Unknown {
 
     #[instrument(skip(self, dst))]
     pub async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
-        let response = Frame::Error(format!("ERR unknown command '{}'", self.command_name));
+        let response = Error::Err(format!("unknown command '{}'", self.command_name)).to_frame();
 
         debug!(?response);
 
@@ -2,19 +2,20 @@ pub mod clients;
 pub mod cmd;
 pub mod pkg;
 
-pub use clients::{BlockingClient, BufferedClient, Client};
+pub use clients::{BlockingClient, BufferedClient, CachingClient, Client, ClusterClient};
 pub use cmd::Command;
+pub use pkg::error::Error;
 
 pub const DEFAULT_PORT: u16 = 6379;
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
-
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub mod prelude {
     pub use super::pkg::{
+        acl::AccessControl,
         db::{Db, DbDropGuard},
         parse::{Parse, ParseError},
+        session::Session,
         shutdown::Shutdown,
         Connection, Frame,
     };
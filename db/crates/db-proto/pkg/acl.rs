@@ -0,0 +1,293 @@
+use super::pattern;
+use super::session::Session;
+use crate::{Command, Error};
+
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+
+pub const DEFAULT_USER: &str = "default";
+
+pub const CATEGORIES: &[&str] = &["all", "read", "write", "keyspace", "string", "pubsub", "admin", "dangerous", "connection", "fast", "slow"];
+
+#[derive(Debug)]
+pub struct AccessControl {
+    users: RwLock<BTreeMap<String, User>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct User {
+    name: String,
+    enabled: bool,
+    nopass: bool,
+    passwords: BTreeSet<String>,
+    categories: BTreeSet<String>,
+    denied_categories: BTreeSet<String>,
+    commands: BTreeSet<String>,
+    denied_commands: BTreeSet<String>,
+    keys: Vec<String>,
+}
+
+impl AccessControl {
+    pub fn new() -> AccessControl {
+        let mut users = BTreeMap::new();
+        users.insert(DEFAULT_USER.to_string(), User::superuser(DEFAULT_USER));
+
+        AccessControl { users: RwLock::new(users) }
+    }
+
+    pub fn set_requirepass(&self, password: Option<&str>) {
+        let mut users = self.users.write().unwrap();
+        let user = users.entry(DEFAULT_USER.to_string()).or_insert_with(|| User::superuser(DEFAULT_USER));
+
+        user.passwords.clear();
+        user.nopass = password.is_none();
+
+        if let Some(password) = password {
+            user.passwords.insert(hash_password(password));
+        }
+    }
+
+    pub fn session(&self) -> Session {
+        let users = self.users.read().unwrap();
+        let authenticated = users.get(DEFAULT_USER).map(|user| user.enabled && user.nopass).unwrap_or(false);
+
+        Session::new(DEFAULT_USER.to_string(), authenticated)
+    }
+
+    pub fn requires_password(&self, username: &str) -> bool {
+        let users = self.users.read().unwrap();
+        users.get(username).map(|user| !user.nopass).unwrap_or(true)
+    }
+
+    pub fn authenticate(&self, username: &str, password: &str) -> bool {
+        let users = self.users.read().unwrap();
+
+        match users.get(username) {
+            Some(user) if user.enabled => user.nopass || user.passwords.contains(&hash_password(password)),
+            _ => false,
+        }
+    }
+
+    pub fn authorize(&self, session: &Session, cmd: &Command) -> crate::Result<()> {
+        if let Command::Auth(_) = cmd {
+            return Ok(());
+        }
+
+        if !session.is_authenticated() {
+            return Err(Error::NoAuth("Authentication required.".into()));
+        }
+
+        if let Command::Unknown(_) = cmd {
+            return Ok(());
+        }
+
+        let users = self.users.read().unwrap();
+        let user = match users.get(session.user()) {
+            Some(user) => user,
+            None => return Err(Error::NoPerm(format!("User {} has been deleted", session.user()))),
+        };
+
+        if !user.enabled {
+            return Err(Error::NoPerm(format!("User {} is disabled", user.name)));
+        }
+
+        if !user.can_run(cmd.get_name(), cmd.categories()) {
+            return Err(Error::NoPerm(format!("User {} has no permissions to run the '{}' command", user.name, cmd.get_name())));
+        }
+
+        if !cmd.keys().iter().all(|key| user.can_access(key)) {
+            return Err(Error::NoPerm("No permissions to access a key".into()));
+        }
+
+        Ok(())
+    }
+
+    pub fn set_user(&self, username: &str, rules: &[String]) -> Result<(), String> {
+        let mut users = self.users.write().unwrap();
+        let mut user = users.get(username).cloned().unwrap_or_else(|| User::new(username));
+
+        for rule in rules {
+            user.apply_rule(rule)?;
+        }
+
+        users.insert(username.to_string(), user);
+        Ok(())
+    }
+
+    pub fn get_user(&self, username: &str) -> Option<User> { self.users.read().unwrap().get(username).cloned() }
+
+    pub fn list(&self) -> Vec<String> { self.users.read().unwrap().values().map(User::describe).collect() }
+}
+
+impl Default for AccessControl {
+    fn default() -> Self { Self::new() }
+}
+
+impl User {
+    pub fn new(name: impl ToString) -> User {
+        User {
+            name: name.to_string(),
+            enabled: false,
+            nopass: false,
+            passwords: BTreeSet::new(),
+            categories: BTreeSet::new(),
+            denied_categories: BTreeSet::new(),
+            commands: BTreeSet::new(),
+            denied_commands: BTreeSet::new(),
+            keys: vec![],
+        }
+    }
+
+    fn superuser(name: &str) -> User {
+        let mut user = User::new(name);
+
+        user.enabled = true;
+        user.nopass = true;
+        user.categories.insert("all".into());
+        user.keys.push("*".into());
+
+        user
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn passwords(&self) -> impl Iterator<Item = &String> { self.passwords.iter() }
+
+    pub fn keys(&self) -> &[String] { &self.keys }
+
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+
+        if self.nopass {
+            flags.push("nopass");
+        }
+        if self.keys.iter().any(|key| key == "*") {
+            flags.push("allkeys");
+        }
+        if self.categories.contains("all") && self.denied_categories.is_empty() && self.denied_commands.is_empty() {
+            flags.push("allcommands");
+        }
+
+        flags
+    }
+
+    pub fn commands(&self) -> String {
+        let mut rules = vec![];
+
+        if self.categories.is_empty() && self.commands.is_empty() {
+            rules.push("-@all".to_string());
+        }
+
+        rules.extend(self.categories.iter().map(|category| format!("+@{category}")));
+        rules.extend(self.denied_categories.iter().map(|category| format!("-@{category}")));
+        rules.extend(self.commands.iter().map(|command| format!("+{command}")));
+        rules.extend(self.denied_commands.iter().map(|command| format!("-{command}")));
+
+        rules.join(" ")
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("user {}", self.name), if self.enabled { "on" } else { "off" }.to_string()];
+
+        if self.nopass {
+            parts.push("nopass".into());
+        }
+
+        parts.extend(self.passwords.iter().map(|hash| format!("#{hash}")));
+        parts.extend(self.keys.iter().map(|key| format!("~{key}")));
+        parts.push(self.commands());
+
+        parts.join(" ")
+    }
+
+    pub fn can_run(&self, command: &str, categories: &[&str]) -> bool {
+        if self.commands.contains(command) {
+            return true;
+        }
+
+        if self.denied_commands.contains(command) || categories.iter().any(|category| self.denied_categories.contains(*category)) {
+            return false;
+        }
+
+        self.categories.contains("all") || categories.iter().any(|category| self.categories.contains(*category))
+    }
+
+    pub fn can_access(&self, key: &str) -> bool { self.keys.iter().any(|pattern| pattern::matches(pattern, key)) }
+
+    fn apply_rule(&mut self, rule: &str) -> Result<(), String> {
+        match rule.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = vec!["*".into()],
+            "resetkeys" => self.keys.clear(),
+            "allcommands" | "+@all" => {
+                self.categories = BTreeSet::from(["all".to_string()]);
+                self.denied_categories.clear();
+                self.commands.clear();
+                self.denied_commands.clear();
+            }
+            "nocommands" | "-@all" => {
+                self.categories.clear();
+                self.denied_categories.clear();
+                self.commands.clear();
+                self.denied_commands.clear();
+            }
+            "reset" => *self = User::new(&self.name),
+            _ => return self.apply_modifier(rule),
+        }
+
+        Ok(())
+    }
+
+    fn apply_modifier(&mut self, rule: &str) -> Result<(), String> {
+        let syntax_error = || format!("Error in ACL SETUSER modifier '{rule}': Syntax error");
+
+        if let Some(password) = rule.strip_prefix('>') {
+            self.passwords.insert(hash_password(password));
+            self.nopass = false;
+        } else if let Some(password) = rule.strip_prefix('<') {
+            self.passwords.remove(&hash_password(password));
+        } else if let Some(hash) = rule.strip_prefix('#') {
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(syntax_error());
+            }
+            self.passwords.insert(hash.to_lowercase());
+            self.nopass = false;
+        } else if let Some(key) = rule.strip_prefix('~') {
+            self.keys.push(key.to_string());
+        } else if let Some(category) = rule.strip_prefix("+@") {
+            let category = valid_category(category).ok_or_else(syntax_error)?;
+            self.denied_categories.remove(category);
+            self.categories.insert(category.to_string());
+        } else if let Some(category) = rule.strip_prefix("-@") {
+            let category = valid_category(category).ok_or_else(syntax_error)?;
+            self.categories.remove(category);
+            self.denied_categories.insert(category.to_string());
+        } else if let Some(command) = rule.strip_prefix('+') {
+            let command = command.to_lowercase();
+            self.denied_commands.remove(&command);
+            self.commands.insert(command);
+        } else if let Some(command) = rule.strip_prefix('-') {
+            let command = command.to_lowercase();
+            self.commands.remove(&command);
+            self.denied_commands.insert(command);
+        } else {
+            return Err(syntax_error());
+        }
+
+        Ok(())
+    }
+}
+
+fn valid_category(category: &str) -> Option<&'static str> { CATEGORIES.iter().find(|c| c.eq_ignore_ascii_case(category)).copied() }
+
+fn hash_password(password: &str) -> String { Sha256::digest(password.as_bytes()).iter().map(|b| format!("{b:02x}")).collect() }
@@ -0,0 +1,296 @@
+use super::Frame;
+use crate::Error;
+
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::RwLock;
+
+pub const SLOTS: usize = 16384;
+
+#[derive(Debug, Default)]
+pub struct Cluster {
+    topology: RwLock<Option<Topology>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Topology {
+    myself: String,
+    nodes: BTreeMap<String, Node>,
+    slots: Vec<Option<String>>,
+    migrating: HashMap<u16, String>,
+    importing: HashMap<u16, String>,
+}
+
+impl Cluster {
+    pub fn new() -> Cluster { Cluster::default() }
+
+    pub fn is_enabled(&self) -> bool { self.topology.read().unwrap().is_some() }
+
+    pub fn configure(&self, topology: Topology) { *self.topology.write().unwrap() = Some(topology); }
+
+    pub fn route(&self, keys: &[&str], asking: bool, exists: impl Fn(&str) -> bool) -> crate::Result<()> {
+        let topology = self.topology.read().unwrap();
+
+        let (topology, first) = match (&*topology, keys.first()) {
+            (Some(topology), Some(first)) => (topology, first),
+            _ => return Ok(()),
+        };
+
+        let slot = key_slot(first);
+
+        if keys.iter().any(|key| key_slot(key) != slot) {
+            return Err(Error::CrossSlot("Keys in request don't hash to the same slot".into()));
+        }
+
+        match topology.owner(slot) {
+            Some(owner) if owner.id == topology.myself => {
+                if let Some(target) = topology.migrating.get(&slot).and_then(|id| topology.nodes.get(id)) {
+                    if !keys.iter().all(|key| exists(key)) {
+                        return Err(Error::Ask { slot, addr: format!("{}:{}", target.host, target.port) });
+                    }
+                }
+                Ok(())
+            }
+            _ if asking && topology.importing.contains_key(&slot) => Ok(()),
+            Some(owner) => Err(Error::Moved { slot, addr: format!("{}:{}", owner.host, owner.port) }),
+            None => Err(Error::ClusterDown(format!("Hash slot {} not served", slot))),
+        }
+    }
+
+    pub fn slots(&self) -> Option<Frame> {
+        let topology = self.topology.read().unwrap();
+        let topology = topology.as_ref()?;
+        let mut ranges = vec![];
+        let mut start = 0;
+
+        while start < SLOTS {
+            let owner = &topology.slots[start];
+            let mut end = start;
+
+            while end + 1 < SLOTS && topology.slots[end + 1] == *owner {
+                end += 1;
+            }
+
+            if let Some(node) = owner.as_ref().and_then(|id| topology.nodes.get(id)) {
+                ranges.push(Frame::Array(vec![
+                    Frame::Integer(start as i64),
+                    Frame::Integer(end as i64),
+                    Frame::Array(vec![
+                        Frame::Bulk(Bytes::from(node.host.clone())),
+                        Frame::Integer(node.port as i64),
+                        Frame::Bulk(Bytes::from(node.id.clone())),
+                    ]),
+                ]));
+            }
+
+            start = end + 1;
+        }
+
+        Some(Frame::Array(ranges))
+    }
+
+    pub fn nodes(&self) -> Option<String> {
+        let topology = self.topology.read().unwrap();
+        let topology = topology.as_ref()?;
+        let mut out = String::new();
+
+        for node in topology.nodes.values() {
+            let flags = if node.id == topology.myself { "myself,master" } else { "master" };
+            out.push_str(&format!("{} {}:{}@{} {} - 0 0 0 connected", node.id, node.host, node.port, node.port as u32 + 10000, flags));
+
+            for (start, end) in topology.ranges(&node.id) {
+                if start == end {
+                    out.push_str(&format!(" {}", start));
+                } else {
+                    out.push_str(&format!(" {}-{}", start, end));
+                }
+            }
+
+            if node.id == topology.myself {
+                for (slot, target) in &topology.migrating {
+                    out.push_str(&format!(" [{}->-{}]", slot, target));
+                }
+                for (slot, source) in &topology.importing {
+                    out.push_str(&format!(" [{}-<-{}]", slot, source));
+                }
+            }
+
+            out.push('\n');
+        }
+
+        Some(out)
+    }
+
+    pub fn info(&self) -> String {
+        let topology = self.topology.read().unwrap();
+
+        match &*topology {
+            Some(topology) => {
+                let assigned = topology.slots.iter().filter(|owner| owner.is_some()).count();
+                format!(
+                    "cluster_enabled:1\r\ncluster_state:{}\r\ncluster_slots_assigned:{}\r\ncluster_slots_ok:{}\r\ncluster_known_nodes:{}\r\ncluster_size:{}\r\n",
+                    if assigned == SLOTS { "ok" } else { "fail" },
+                    assigned,
+                    assigned,
+                    topology.nodes.len(),
+                    topology.nodes.keys().filter(|id| topology.slots.iter().any(|owner| owner.as_ref() == Some(id))).count()
+                )
+            }
+            None => "cluster_enabled:0\r\n".to_string(),
+        }
+    }
+
+    pub fn myself(&self) -> Option<String> { self.topology.read().unwrap().as_ref().map(|topology| topology.myself.clone()) }
+
+    pub fn set_slot(&self, slot: u16, state: &str, node_id: Option<&str>) -> crate::Result<()> {
+        let mut topology = self.topology.write().unwrap();
+        let topology = topology.as_mut().ok_or_else(|| Error::Err("This instance has cluster support disabled".into()))?;
+
+        if slot as usize >= SLOTS {
+            return Err(Error::Err("Invalid or out of range slot".into()));
+        }
+
+        let node_id = match node_id {
+            Some(id) if topology.nodes.contains_key(id) => Some(id.to_string()),
+            Some(id) => return Err(Error::Err(format!("I don't know about node {}", id))),
+            None => None,
+        };
+
+        match (state, node_id) {
+            ("migrating", Some(id)) => {
+                topology.migrating.insert(slot, id);
+            }
+            ("importing", Some(id)) => {
+                topology.importing.insert(slot, id);
+            }
+            ("node", Some(id)) => {
+                topology.migrating.remove(&slot);
+                topology.importing.remove(&slot);
+                topology.slots[slot as usize] = Some(id);
+            }
+            ("stable", None) => {
+                topology.migrating.remove(&slot);
+                topology.importing.remove(&slot);
+            }
+            _ => return Err(Error::Err("Invalid CLUSTER SETSLOT action or number of arguments".into())),
+        }
+
+        Ok(())
+    }
+}
+
+impl Topology {
+    pub fn parse(src: &str, myself: Option<&str>, port: u16) -> crate::Result<Topology> {
+        let mut nodes = BTreeMap::new();
+        let mut slots = vec![None; SLOTS];
+
+        for (n, line) in src.lines().enumerate().map(|(n, line)| (n + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let id = parts.next().ok_or_else(|| format!("line {}: missing node id", n))?.to_string();
+            let addr = parts.next().ok_or_else(|| format!("line {}: missing node address", n))?;
+            let (host, node_port) = addr.rsplit_once(':').ok_or_else(|| format!("line {}: expected HOST:PORT", n))?;
+            let node_port = node_port.parse().map_err(|_| format!("line {}: invalid port", n))?;
+
+            for range in parts {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let start: usize = start.parse().map_err(|_| format!("line {}: invalid slot range `{}`", n, range))?;
+                let end: usize = end.parse().map_err(|_| format!("line {}: invalid slot range `{}`", n, range))?;
+
+                if start > end || end >= SLOTS {
+                    return Err(format!("line {}: slot range `{}` out of bounds", n, range).into());
+                }
+
+                for slot in &mut slots[start..=end] {
+                    if slot.is_some() {
+                        return Err(format!("line {}: slot range `{}` assigned twice", n, range).into());
+                    }
+                    *slot = Some(id.clone());
+                }
+            }
+
+            nodes.insert(
+                id.clone(),
+                Node {
+                    id,
+                    host: host.to_string(),
+                    port: node_port,
+                },
+            );
+        }
+
+        let myself = match myself {
+            Some(id) if nodes.contains_key(id) => id.to_string(),
+            Some(id) => return Err(format!("node `{}` is not part of the cluster config", id).into()),
+            None => nodes.values().find(|node| node.port == port).map(|node| node.id.clone()).ok_or("no node in the cluster config matches this server's port")?,
+        };
+
+        Ok(Topology {
+            myself,
+            nodes,
+            slots,
+            migrating: HashMap::new(),
+            importing: HashMap::new(),
+        })
+    }
+
+    pub fn load(path: &Path, myself: Option<&str>, port: u16) -> crate::Result<Topology> { Topology::parse(&std::fs::read_to_string(path)?, myself, port) }
+
+    fn owner(&self, slot: u16) -> Option<&Node> { self.slots[slot as usize].as_ref().and_then(|id| self.nodes.get(id)) }
+
+    fn ranges(&self, id: &str) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = vec![];
+
+        for (slot, owner) in self.slots.iter().enumerate() {
+            if owner.as_deref() != Some(id) {
+                continue;
+            }
+
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == slot => *end = slot,
+                _ => ranges.push((slot, slot)),
+            }
+        }
+
+        ranges
+    }
+}
+
+pub fn key_slot(key: &str) -> u16 {
+    let key = key.as_bytes();
+
+    let hashed = match key.iter().position(|&b| b == b'{') {
+        Some(open) => match key[open + 1..].iter().position(|&b| b == b'}') {
+            Some(len) if len > 0 => &key[open + 1..open + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+
+    crc16(hashed) % SLOTS as u16
+}
+
+fn crc16(buf: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+
+    for &byte in buf {
+        crc ^= (byte as u16) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+
+    crc
+}
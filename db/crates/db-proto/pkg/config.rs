@@ -0,0 +1,426 @@
+use super::db::Db;
+use super::frame::{Limits, DEFAULT_MAX_BULK_LEN};
+use super::memory::{self, Policy};
+use super::pattern;
+use super::storage::DataDir;
+use crate::Error;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(300);
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+const REWRITE_MARKER: &str = "# Generated by CONFIG REWRITE";
+
+#[derive(Debug)]
+pub struct Settings {
+    file: RwLock<Option<PathBuf>>,
+    maxclients: Arc<Mutex<ClientLimit>>,
+    connections: Arc<Semaphore>,
+    timeout: AtomicU64,
+    tcp_keepalive: AtomicU64,
+    shutdown_timeout: AtomicU64,
+    proto_max_bulk_len: AtomicUsize,
+    save: RwLock<Vec<SaveRule>>,
+    requirepass: RwLock<String>,
+    fixed: RwLock<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveRule {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+struct Param {
+    name: &'static str,
+    get: fn(&Db) -> String,
+    set: fn(&Db, &str) -> Result<(), String>,
+}
+
+const PARAMS: &[Param] = &[
+    Param {
+        name: "dbfilename",
+        get: |db| db.data_dir().dbfilename().to_string(),
+        set: |db, value| {
+            let data_dir = DataDir::new(db.data_dir().dir(), value);
+            data_dir.resolve(None).map_err(|err| err.to_string())?;
+            db.set_data_dir(data_dir);
+            Ok(())
+        },
+    },
+    Param {
+        name: "requirepass",
+        get: |db| db.config().requirepass(),
+        set: |db, value| {
+            db.config().set_requirepass(db, value);
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxclients",
+        get: |db| db.config().maxclients().to_string(),
+        set: |db, value| {
+            db.config().set_maxclients(parse_number(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "timeout",
+        get: |db| db.config().timeout().as_secs().to_string(),
+        set: |db, value| {
+            db.config().set_timeout(Duration::from_secs(parse_number(value)?));
+            Ok(())
+        },
+    },
+    Param {
+        name: "tcp-keepalive",
+        get: |db| db.config().tcp_keepalive().as_secs().to_string(),
+        set: |db, value| {
+            db.config().set_tcp_keepalive(Duration::from_secs(parse_number(value)?));
+            Ok(())
+        },
+    },
+    Param {
+        name: "shutdown-timeout",
+        get: |db| db.config().shutdown_timeout().as_secs().to_string(),
+        set: |db, value| {
+            db.config().set_shutdown_timeout(Duration::from_secs(parse_number(value)?));
+            Ok(())
+        },
+    },
+    Param {
+        name: "proto-max-bulk-len",
+        get: |db| db.config().proto_max_bulk_len().to_string(),
+        set: |db, value| {
+            db.config().set_proto_max_bulk_len(memory::parse_size(value)?.max(1024 * 1024));
+            Ok(())
+        },
+    },
+    Param {
+        name: "save",
+        get: |db| format_save(&db.config().save()),
+        set: |db, value| {
+            db.config().set_save(parse_save(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory",
+        get: |db| db.memory().maxmemory().to_string(),
+        set: |db, value| {
+            db.memory().set_maxmemory(memory::parse_size(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory-policy",
+        get: |db| db.memory().policy().to_string(),
+        set: |db, value| {
+            db.memory().set_policy(Policy::from_str(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory-samples",
+        get: |db| db.memory().samples().to_string(),
+        set: |db, value| {
+            db.memory().set_samples(parse_number(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "slowlog-log-slower-than",
+        get: |db| db.slowlog().log_slower_than().to_string(),
+        set: |db, value| {
+            db.slowlog().set_log_slower_than(parse_number(value)?);
+            Ok(())
+        },
+    },
+    Param {
+        name: "slowlog-max-len",
+        get: |db| db.slowlog().max_len().to_string(),
+        set: |db, value| {
+            db.slowlog().set_max_len(parse_number(value)?);
+            Ok(())
+        },
+    },
+];
+
+#[derive(Debug, Default)]
+struct ClientLimit {
+    max: usize,
+    /// Permits still to be taken out of the semaphore after a shrink, as
+    /// connections close.
+    deficit: usize,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            file: RwLock::new(None),
+            maxclients: Arc::new(Mutex::new(ClientLimit::default())),
+            connections: Arc::new(Semaphore::new(0)),
+            timeout: AtomicU64::new(0),
+            tcp_keepalive: AtomicU64::new(DEFAULT_TCP_KEEPALIVE.as_secs()),
+            shutdown_timeout: AtomicU64::new(DEFAULT_SHUTDOWN_TIMEOUT.as_secs()),
+            proto_max_bulk_len: AtomicUsize::new(DEFAULT_MAX_BULK_LEN),
+            save: RwLock::new(Vec::new()),
+            requirepass: RwLock::new(String::new()),
+            fixed: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn file(&self) -> Option<PathBuf> { self.file.read().unwrap().clone() }
+
+    pub fn set_file(&self, path: Option<PathBuf>) { *self.file.write().unwrap() = path; }
+
+    pub fn maxclients(&self) -> usize { self.maxclients.lock().unwrap().max }
+
+    /// Grows the connection semaphore right away, first cancelling permits a
+    /// previous shrink still owes; shrinking takes permits out one at a time
+    /// as connections close.
+    pub fn set_maxclients(&self, maxclients: usize) {
+        let mut limit = self.maxclients.lock().unwrap();
+
+        if maxclients > limit.max {
+            let grow = maxclients - limit.max;
+            let cancelled = grow.min(limit.deficit);
+            limit.deficit -= cancelled;
+            self.connections.add_permits(grow - cancelled);
+        } else if maxclients < limit.max {
+            let pending = limit.deficit;
+            limit.deficit += limit.max - maxclients;
+
+            if pending == 0 {
+                let (limit, connections) = (self.maxclients.clone(), self.connections.clone());
+
+                tokio::spawn(async move {
+                    while let Ok(permit) = connections.clone().acquire_owned().await {
+                        let mut limit = limit.lock().unwrap();
+
+                        if limit.deficit == 0 {
+                            break;
+                        }
+
+                        limit.deficit -= 1;
+                        permit.forget();
+
+                        if limit.deficit == 0 {
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+
+        limit.max = maxclients;
+    }
+
+    pub fn connections(&self) -> Arc<Semaphore> { self.connections.clone() }
+
+    pub fn timeout(&self) -> Duration { Duration::from_secs(self.timeout.load(Ordering::Relaxed)) }
+
+    pub fn set_timeout(&self, timeout: Duration) { self.timeout.store(timeout.as_secs(), Ordering::Relaxed); }
+
+    pub fn tcp_keepalive(&self) -> Duration { Duration::from_secs(self.tcp_keepalive.load(Ordering::Relaxed)) }
+
+    pub fn set_tcp_keepalive(&self, interval: Duration) { self.tcp_keepalive.store(interval.as_secs(), Ordering::Relaxed); }
+
+    pub fn shutdown_timeout(&self) -> Duration { Duration::from_secs(self.shutdown_timeout.load(Ordering::Relaxed)) }
+
+    pub fn set_shutdown_timeout(&self, timeout: Duration) { self.shutdown_timeout.store(timeout.as_secs(), Ordering::Relaxed); }
+
+    pub fn proto_max_bulk_len(&self) -> usize { self.proto_max_bulk_len.load(Ordering::Relaxed) }
+
+    pub fn set_proto_max_bulk_len(&self, len: usize) { self.proto_max_bulk_len.store(len, Ordering::Relaxed); }
+
+    pub fn frame_limits(&self) -> Limits {
+        Limits {
+            max_bulk_len: self.proto_max_bulk_len(),
+            ..Limits::default()
+        }
+    }
+
+    pub fn save(&self) -> Vec<SaveRule> { self.save.read().unwrap().clone() }
+
+    pub fn set_save(&self, rules: Vec<SaveRule>) { *self.save.write().unwrap() = rules; }
+
+    pub fn save_due(&self, changes: u64, elapsed: Duration) -> bool { self.save.read().unwrap().iter().any(|rule| changes >= rule.changes && elapsed.as_secs() >= rule.seconds) }
+
+    pub fn requirepass(&self) -> String { self.requirepass.read().unwrap().clone() }
+
+    pub fn set_requirepass(&self, db: &Db, password: &str) {
+        db.acl().set_requirepass(Some(password).filter(|password| !password.is_empty()));
+        *self.requirepass.write().unwrap() = password.to_string();
+    }
+
+    /// Records a startup-only parameter so CONFIG GET and REWRITE can report it.
+    pub fn set_fixed(&self, name: &str, value: impl ToString) { self.fixed.write().unwrap().insert(name.to_string(), value.to_string()); }
+
+    pub fn get(&self, db: &Db, pattern: &str) -> Vec<(String, String)> {
+        let pattern = pattern.to_lowercase();
+        let mut params: BTreeMap<String, String> = self.fixed.read().unwrap().clone();
+
+        for param in PARAMS {
+            params.insert(param.name.to_string(), (param.get)(db));
+        }
+
+        params.into_iter().filter(|(name, _)| pattern::matches(&pattern, name)).collect()
+    }
+
+    pub fn set(&self, db: &Db, name: &str, value: &str) -> crate::Result<()> {
+        let name = name.to_lowercase();
+
+        match param(&name) {
+            Some(param) => (param.set)(db, value).map_err(|err| Error::Err(format!("CONFIG SET failed (possibly related to argument '{}') - {}", name, err))),
+            None if self.fixed.read().unwrap().contains_key(&name) => Err(Error::Err(format!("CONFIG SET failed (possibly related to argument '{}') - can't set immutable config", name))),
+            None => Err(Error::Err(format!("Unknown option or number of arguments for CONFIG SET - '{}'", name))),
+        }
+    }
+
+    /// Applies a setting from the config file or command line with the same
+    /// setter CONFIG SET uses.
+    pub fn apply(&self, db: &Db, name: &str, value: &str) -> Result<(), String> { (param(name).ok_or("unknown setting")?.set)(db, value) }
+
+    /// Rewrites the config file in place: directives that are already present
+    /// are updated where they stand, comments and ordering are preserved and
+    /// anything missing is appended at the end.
+    pub fn rewrite(&self, db: &Db) -> crate::Result<()> {
+        let path = self.file().ok_or_else(|| Error::Err("The server is running without a config file".into()))?;
+        let src = match std::fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut params: BTreeMap<String, String> = self.get(db, "*").into_iter().collect();
+        let known: Vec<String> = params.keys().cloned().collect();
+        let mut lines = Vec::new();
+
+        for line in src.lines() {
+            if line.trim() == REWRITE_MARKER {
+                continue;
+            }
+
+            match split_line(line)?.first().map(|name| name.to_lowercase()) {
+                Some(name) if params.contains_key(&name) => lines.push(directive(&name, &params.remove(&name).unwrap_or_default())),
+                Some(name) if known.contains(&name) => {}
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        if !params.is_empty() {
+            lines.push(REWRITE_MARKER.to_string());
+            lines.extend(params.iter().map(|(name, value)| directive(name, value)));
+        }
+
+        let tmp = path.with_extension("rewrite");
+        std::fs::write(&tmp, lines.join("\n") + "\n")?;
+        std::fs::rename(&tmp, &path)?;
+
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self { Self::new() }
+}
+
+impl fmt::Display for SaveRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{} {}", self.seconds, self.changes) }
+}
+
+/// Parses a redis.conf-style file into `(name, value)` directives. Blank lines
+/// and `#` comments are skipped; arguments may be quoted and multiple
+/// arguments are joined with a single space, so `save 900 1 300 10` yields
+/// `("save", "900 1 300 10")`.
+pub fn parse(src: &str) -> Result<Vec<(String, String)>, String> {
+    let mut directives = Vec::new();
+
+    for (number, line) in src.lines().enumerate() {
+        let args = split_line(line).map_err(|err| format!("line {}: {}", number + 1, err))?;
+
+        if let Some((name, values)) = args.split_first() {
+            directives.push((name.to_lowercase(), values.join(" ")));
+        }
+    }
+
+    Ok(directives)
+}
+
+pub fn parse_save(src: &str) -> Result<Vec<SaveRule>, String> {
+    let numbers = src.split_whitespace().map(parse_number).collect::<Result<Vec<u64>, _>>()?;
+
+    if numbers.len() % 2 != 0 {
+        return Err("save rules must be pairs of <seconds> <changes>".to_string());
+    }
+
+    Ok(numbers.chunks(2).map(|pair| SaveRule { seconds: pair[0], changes: pair[1] }).collect())
+}
+
+pub fn format_save(rules: &[SaveRule]) -> String { rules.iter().map(SaveRule::to_string).collect::<Vec<_>>().join(" ") }
+
+fn param(name: &str) -> Option<&'static Param> { PARAMS.iter().find(|param| param.name == name) }
+
+/// Whether `name` can be changed at runtime with CONFIG SET.
+pub fn is_param(name: &str) -> bool { param(name).is_some() }
+
+fn parse_number<T: FromStr>(src: &str) -> Result<T, String> { src.trim().parse().map_err(|_| format!("argument couldn't be parsed into an integer: '{}'", src)) }
+
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+
+    if chars.peek() == Some(&'#') {
+        return Ok(args);
+    }
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut arg = String::new();
+
+        if c == '"' || c == '\'' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some(ch) if ch == c => break,
+                    Some('\\') if c == '"' => arg.push(chars.next().ok_or("unbalanced quotes")?),
+                    Some(ch) => arg.push(ch),
+                    None => return Err("unbalanced quotes".to_string()),
+                }
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                arg.push(ch);
+                chars.next();
+            }
+        }
+
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+fn directive(name: &str, value: &str) -> String {
+    if !value.is_empty() && !value.contains(['"', '\'', '\\']) {
+        return format!("{} {}", name, value);
+    }
+
+    format!("{} \"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
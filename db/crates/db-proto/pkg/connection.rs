@@ -1,20 +1,58 @@
-use super::frame::{self, Frame};
-use bytes::{Buf, BytesMut};
-use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
-use tokio::net::TcpStream;
+use super::frame::{Frame, Limits};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::slice;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, ReadBuf};
+
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+const MIN_READ_CAPACITY: usize = 4 * 1024;
+
+/// Bulk payloads at least this long are written from their own buffer.
+const MIN_VECTORED_LEN: usize = 1024;
 
-#[derive(Debug)]
 pub struct Connection {
-    stream: BufWriter<TcpStream>,
+    stream: BufWriter<Box<dyn Stream>>,
     buffer: BytesMut,
+    limits: Limits,
+    /// Replies held back by `defer_writes` until `flush_deferred`.
+    deferred: Option<Vec<Bytes>>,
 }
 
+struct Discard;
+
 impl Connection {
-    pub fn new(socket: TcpStream) -> Connection {
+    pub fn new(socket: impl Stream + 'static) -> Connection {
         Connection {
-            stream: BufWriter::new(socket),
+            stream: BufWriter::new(Box::new(socket)),
             buffer: BytesMut::with_capacity(8 * 1024),
+            limits: Limits::default(),
+            deferred: None,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) { self.limits = limits; }
+
+    pub fn discard() -> Connection { Connection::new(Discard) }
+
+    /// Keeps replies in memory instead of writing them to the peer, so a
+    /// client that stops reading cannot block the caller.
+    pub fn defer_writes(&mut self) { self.deferred.get_or_insert_with(Vec::new); }
+
+    /// Writes the replies held back since `defer_writes` and resumes writing
+    /// directly.
+    pub async fn flush_deferred(&mut self) -> io::Result<()> {
+        match self.deferred.take() {
+            Some(chunks) if !chunks.is_empty() => {
+                self.write_chunks(&chunks).await?;
+                self.stream.flush().await
+            }
+            _ => Ok(()),
         }
     }
 
@@ -24,6 +62,11 @@ impl Connection {
                 return Ok(Some(frame));
             }
 
+            // Parsed frames may still hold on to the front of the buffer, so
+            // make sure there is room to read into rather than growing by a
+            // few bytes at a time.
+            self.buffer.reserve(MIN_READ_CAPACITY);
+
             if 0 == self.stream.read_buf(&mut self.buffer).await? {
                 if self.buffer.is_empty() {
                     return Ok(None);
@@ -34,87 +77,92 @@ impl Connection {
         }
     }
 
-    fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
-        use frame::Error::Incomplete;
+    fn parse_frame(&mut self) -> crate::Result<Option<Frame>> { Ok(Frame::decode(&mut self.buffer, &self.limits)?) }
 
-        let mut buf = Cursor::new(&self.buffer[..]);
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> { self.write_frames(slice::from_ref(frame)).await }
 
-        match Frame::check(&mut buf) {
-            Ok(_) => {
-                let len = buf.position() as usize;
-                buf.set_position(0);
+    /// Writes all `frames` with a single flush so they leave as one batch.
+    pub async fn write_frames(&mut self, frames: &[Frame]) -> io::Result<()> {
+        let mut scratch = BytesMut::new();
+        let mut chunks = vec![];
 
-                let frame = Frame::parse(&mut buf)?;
-                self.buffer.advance(len);
+        for frame in frames {
+            encode_vectored(frame, &mut scratch, &mut chunks);
+        }
 
-                Ok(Some(frame))
-            }
+        chunks.push(scratch.freeze());
 
-            Err(Incomplete) => Ok(None),
-            Err(e) => Err(e.into()),
+        if let Some(deferred) = &mut self.deferred {
+            deferred.extend(chunks);
+            return Ok(());
         }
-    }
 
-    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Array(val) => {
-                self.stream.write_u8(b'*').await?;
-                self.write_decimal(val.len() as u64).await?;
+        self.write_chunks(&chunks).await?;
+        self.stream.flush().await
+    }
 
-                for entry in &**val {
-                    self.write_value(entry).await?;
-                }
-            }
-            _ => self.write_value(frame).await?,
+    pub async fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(deferred) = &mut self.deferred {
+            deferred.push(Bytes::copy_from_slice(bytes));
+            return Ok(());
         }
 
+        self.stream.write_all(bytes).await?;
         self.stream.flush().await
     }
 
-    async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Simple(val) => {
-                self.stream.write_u8(b'+').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Error(val) => {
-                self.stream.write_u8(b'-').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Integer(val) => {
-                self.stream.write_u8(b':').await?;
-                self.write_decimal(*val).await?;
-            }
-            Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
-            }
-            Frame::Bulk(val) => {
-                let len = val.len();
+    async fn write_chunks(&mut self, chunks: &[Bytes]) -> io::Result<()> {
+        let mut slices: Vec<IoSlice> = chunks.iter().filter(|chunk| !chunk.is_empty()).map(|chunk| IoSlice::new(chunk)).collect();
+        let mut slices = &mut slices[..];
+
+        while !slices.is_empty() {
+            let n = self.stream.write_vectored(slices).await?;
 
-                self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
+            if n == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
             }
-            Frame::Array(_val) => unreachable!(),
+
+            IoSlice::advance_slices(&mut slices, n);
         }
 
         Ok(())
     }
+}
 
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
-        use std::io::Write;
+/// Encodes `frame` as a list of chunks for a vectored write. Headers and
+/// small values are gathered in `scratch`, while larger bulk payloads are
+/// referenced as they are instead of being copied.
+fn encode_vectored(frame: &Frame, scratch: &mut BytesMut, chunks: &mut Vec<Bytes>) {
+    match frame {
+        Frame::Bulk(val) if val.len() >= MIN_VECTORED_LEN => {
+            scratch.put_slice(format!("${}\r\n", val.len()).as_bytes());
+            chunks.push(scratch.split().freeze());
+            chunks.push(val.clone());
+            scratch.put_slice(b"\r\n");
+        }
+        Frame::Array(val) => {
+            scratch.put_slice(format!("*{}\r\n", val.len()).as_bytes());
 
-        let mut buf = [0u8; 20];
-        let mut buf = Cursor::new(&mut buf[..]);
-        write!(&mut buf, "{}", val)?;
+            for entry in val {
+                encode_vectored(entry, scratch, chunks);
+            }
+        }
+        frame => frame.encode(scratch),
+    }
+}
 
-        let pos = buf.position() as usize;
-        self.stream.write_all(&buf.get_ref()[..pos]).await?;
-        self.stream.write_all(b"\r\n").await?;
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_struct("Connection").field("buffered", &self.buffer.len()).finish_non_exhaustive() }
+}
 
-        Ok(())
-    }
+impl AsyncRead for Discard {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context<'_>, _: &mut ReadBuf<'_>) -> Poll<io::Result<()>> { Poll::Ready(Ok(())) }
+}
+
+impl AsyncWrite for Discard {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> { Poll::Ready(Ok(buf.len())) }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> { Poll::Ready(Ok(())) }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> { Poll::Ready(Ok(())) }
 }
@@ -1,12 +1,34 @@
+use super::acl::AccessControl;
+use super::cluster::Cluster;
+use super::config::Settings;
+use super::memory::{self, Memory, Policy, OOM_MESSAGE};
+use super::metrics::Metrics;
+use super::monitor::Monitor;
+use super::pattern;
+use super::registry::Registry;
+use super::replication::Replication;
+use super::slowlog::SlowLog;
+use super::stats::Stats;
+use super::storage::DataDir;
+use super::tracking::Tracking;
+use crate::Error;
 use tokio::sync::{broadcast, Notify};
 use tokio::time::{self, Duration, Instant};
 
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tracing::debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use tracing::{debug, warn};
+
+pub const DEFAULT_DATABASES: usize = 16;
+
+const ACTIVE_EXPIRE_KEYS: usize = 1000;
+const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(1);
 
 #[derive(Debug)]
 pub struct DbDropGuard {
@@ -16,10 +38,17 @@ pub struct DbDropGuard {
 #[derive(Debug, Clone)]
 pub struct Db {
     shared: Arc<Shared>,
+    index: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SerializableState {
+    databases: Vec<SerializableDatabase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializableDatabase {
+    index: usize,
     entries: HashMap<String, SerializableEntry>,
     expirations: Vec<(u64, String)>,
 }
@@ -32,6 +61,27 @@ pub struct SerializableEntry {
 
 #[derive(Debug)]
 struct Shared {
+    shards: Box<[Shard]>,
+    shards_per_db: usize,
+    databases: Box<[AtomicUsize]>,
+    swap: Mutex<()>,
+    pub_sub: Mutex<HashMap<String, broadcast::Sender<Bytes>>>,
+    acl: AccessControl,
+    data_dir: RwLock<DataDir>,
+    replication: Replication,
+    cluster: Cluster,
+    memory: Memory,
+    stats: Stats,
+    metrics: Metrics,
+    slowlog: SlowLog,
+    clients: Registry,
+    monitor: Monitor,
+    tracking: Tracking,
+    config: Settings,
+}
+
+#[derive(Debug)]
+struct Shard {
     state: Mutex<State>,
     background_task: Notify,
 }
@@ -39,7 +89,11 @@ struct Shared {
 #[derive(Debug)]
 struct State {
     entries: HashMap<String, Entry>,
-    pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
+    keys: Vec<String>,
+    /// Keys by insertion id. SCAN walks this so its cursor stays valid while
+    /// keys are removed.
+    order: BTreeMap<u64, String>,
+    next_id: u64,
     expirations: BTreeSet<(Instant, String)>,
     shutdown: bool,
 }
@@ -48,41 +102,143 @@ struct State {
 struct Entry {
     data: Bytes,
     expires_at: Option<Instant>,
+    size: usize,
+    slot: usize,
+    id: u64,
+    last_access: Instant,
+    lfu: u8,
 }
 
 impl DbDropGuard {
     pub fn new() -> DbDropGuard { DbDropGuard { db: Db::new() } }
+    pub fn with_databases(databases: usize) -> DbDropGuard { DbDropGuard { db: Db::with_layout(databases, Db::default_shards()) } }
+    pub fn with_shards(shards: usize) -> DbDropGuard { DbDropGuard { db: Db::with_shards(shards) } }
     pub fn db(&self) -> Db { self.db.clone() }
 }
 
+impl Default for DbDropGuard {
+    fn default() -> Self { Self::new() }
+}
+
 impl Drop for DbDropGuard {
-    fn drop(&mut self) { self.db.shutdown_purge_task(); }
+    fn drop(&mut self) {
+        self.db.replication().promote();
+        self.db.shutdown_purge_task();
+    }
 }
 
 impl Db {
-    pub fn new() -> Db {
+    pub fn new() -> Db { Db::with_layout(DEFAULT_DATABASES, Db::default_shards()) }
+
+    /// Shards per database used by `new`: four per available core.
+    pub fn default_shards() -> usize { std::thread::available_parallelism().map(|n| n.get() * 4).unwrap_or(16) }
+
+    pub fn with_shards(shards: usize) -> Db { Db::with_layout(DEFAULT_DATABASES, shards) }
+
+    /// Every logical database gets its own `shards` shards; `SWAPDB` only
+    /// swaps which physical range a database index points at.
+    pub fn with_layout(databases: usize, shards: usize) -> Db {
+        let (databases, shards) = (databases.max(1), shards.max(1));
         let shared = Arc::new(Shared {
-            state: Mutex::new(State {
-                entries: HashMap::new(),
-                pub_sub: HashMap::new(),
-                expirations: BTreeSet::new(),
-                shutdown: false,
-            }),
-            background_task: Notify::new(),
+            shards: (0..databases * shards).map(|_| Shard::new()).collect(),
+            shards_per_db: shards,
+            databases: (0..databases).map(AtomicUsize::new).collect(),
+            swap: Mutex::new(()),
+            pub_sub: Mutex::new(HashMap::new()),
+            acl: AccessControl::new(),
+            data_dir: RwLock::new(DataDir::default()),
+            replication: Replication::new(),
+            cluster: Cluster::new(),
+            memory: Memory::new(),
+            stats: Stats::new(),
+            metrics: Metrics::new(),
+            slowlog: SlowLog::new(),
+            clients: Registry::new(),
+            monitor: Monitor::new(),
+            tracking: Tracking::new(),
+            config: Settings::new(),
         });
 
-        tokio::spawn(purge_expired_tasks(shared.clone()));
+        for index in 0..shared.shards.len() {
+            tokio::spawn(purge_expired_tasks(shared.clone(), index));
+        }
+
+        Db { shared, index: 0 }
+    }
 
-        Db { shared }
+    pub fn select(&self, index: usize) -> Db {
+        Db {
+            shared: self.shared.clone(),
+            index,
+        }
     }
 
+    pub fn index(&self) -> usize { self.index }
+
+    pub fn databases(&self) -> usize { self.shared.databases.len() }
+
+    pub fn shards(&self) -> usize { self.shared.shards_per_db }
+
+    pub fn acl(&self) -> &AccessControl { &self.shared.acl }
+
+    pub fn replication(&self) -> &Replication { &self.shared.replication }
+
+    pub fn cluster(&self) -> &Cluster { &self.shared.cluster }
+
+    pub fn memory(&self) -> &Memory { &self.shared.memory }
+
+    pub fn stats(&self) -> &Stats { &self.shared.stats }
+
+    pub fn metrics(&self) -> &Metrics { &self.shared.metrics }
+
+    pub fn slowlog(&self) -> &SlowLog { &self.shared.slowlog }
+
+    pub fn clients(&self) -> &Registry { &self.shared.clients }
+
+    pub fn monitor(&self) -> &Monitor { &self.shared.monitor }
+
+    pub fn tracking(&self) -> &Tracking { &self.shared.tracking }
+
+    pub fn config(&self) -> &Settings { &self.shared.config }
+
+    pub fn data_dir(&self) -> DataDir { self.shared.data_dir.read().unwrap().clone() }
+
+    pub fn set_data_dir(&self, data_dir: DataDir) { *self.shared.data_dir.write().unwrap() = data_dir; }
+
     pub fn get(&self, key: &str) -> Option<Bytes> {
-        let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+        let mut state = self.shard(key).state.lock().unwrap();
+        let stats = &self.shared.stats;
+
+        let entry = match state.entries.get_mut(key) {
+            Some(entry) if entry.is_expired(Instant::now()) => {
+                state.remove(key, &self.shared.memory);
+                self.shared.tracking.invalidate(key);
+                stats.key_expired();
+                stats.keyspace_miss();
+                return None;
+            }
+            Some(entry) => entry,
+            None => {
+                stats.keyspace_miss();
+                return None;
+            }
+        };
+
+        entry.touch();
+        stats.keyspace_hit();
+        Some(entry.data.clone())
+    }
+
+    /// Whether `key` holds a live value, without counting a hit or miss,
+    /// touching its access time or expiring it.
+    pub fn contains(&self, key: &str) -> bool {
+        let state = self.shard(key).state.lock().unwrap();
+        state.entries.get(key).is_some_and(|entry| !entry.is_expired(Instant::now()))
     }
 
     pub fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
-        let mut state = self.shared.state.lock().unwrap();
+        let shard = self.shard(&key);
+        let mut state = shard.state.lock().unwrap();
         let mut notify = false;
 
         let expires_at = expire.map(|duration| {
@@ -92,30 +248,75 @@ impl Db {
             when
         });
 
-        let prev = state.entries.insert(key.clone(), Entry { data: value, expires_at });
+        // Invalidating under the shard lock means a client tracking the key
+        // from here on reads the new value.
+        self.shared.tracking.invalidate(&key);
 
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                state.expirations.remove(&(when, key.clone()));
-            }
+        // Values parsed from a request share the connection's read buffer;
+        // a copy keeps the stored value from pinning all of it.
+        state.insert(key, Bytes::copy_from_slice(&value), expires_at, &self.shared.memory);
+        drop(state);
+
+        self.shared.stats.key_changed();
+
+        if notify {
+            shard.background_task.notify_one();
         }
+    }
 
-        if let Some(when) = expires_at {
-            state.expirations.insert((when, key));
+    /// Returns `None` for a missing key, otherwise the remaining time to live.
+    pub fn ttl(&self, key: &str) -> Option<Option<Duration>> {
+        let state = self.shard(key).state.lock().unwrap();
+        let now = Instant::now();
+
+        match state.entries.get(key) {
+            Some(entry) if entry.is_expired(now) => None,
+            Some(entry) => Some(entry.expires_at.map(|when| when.duration_since(now))),
+            None => None,
         }
+    }
 
-        drop(state);
+    /// Walks the selected database one shard at a time. The cursor packs the
+    /// shard into the low 16 bits and the next insertion id above them, so
+    /// keys removed between calls never shift the others; a returned cursor
+    /// of 0 means the iteration is complete.
+    pub fn scan(&self, cursor: u64, pattern: Option<&str>, count: usize) -> (u64, Vec<String>) {
+        let keyspace = self.keyspace();
+        let now = Instant::now();
+        let mut shard = (cursor & 0xffff) as usize;
+        let mut next = cursor >> 16;
+        let mut keys = Vec::new();
+        let mut visited = 0;
+
+        while shard < keyspace.len() {
+            let state = keyspace[shard].state.lock().unwrap();
+            let mut remaining = state.order.range(next..);
+
+            for (&id, key) in remaining.by_ref().take(count - visited) {
+                next = id + 1;
+                visited += 1;
+
+                if !state.entries[key].is_expired(now) && pattern.map(|pattern| pattern::matches(pattern, key)).unwrap_or(true) {
+                    keys.push(key.clone());
+                }
+            }
 
-        if notify {
-            self.shared.background_task.notify_one();
+            if visited == count && remaining.next().is_some() {
+                return ((next << 16) | shard as u64, keys);
+            }
+
+            shard += 1;
+            next = 0;
         }
+
+        (0, keys)
     }
 
     pub fn subscribe(&self, key: String) -> broadcast::Receiver<Bytes> {
         use std::collections::hash_map::Entry;
-        let mut state = self.shared.state.lock().unwrap();
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
 
-        match state.pub_sub.entry(key) {
+        match pub_sub.entry(key) {
             Entry::Occupied(e) => e.get().subscribe(),
             Entry::Vacant(e) => {
                 let (tx, rx) = broadcast::channel(1024);
@@ -126,58 +327,148 @@ impl Db {
     }
 
     pub fn publish(&self, key: &str, value: Bytes) -> usize {
-        let state = self.shared.state.lock().unwrap();
-        state.pub_sub.get(key).map(|tx| tx.send(value).unwrap_or(0)).unwrap_or(0)
+        let pub_sub = self.shared.pub_sub.lock().unwrap();
+        pub_sub.get(key).map(|tx| tx.send(value).unwrap_or(0)).unwrap_or(0)
     }
 
     pub fn dump(&self) -> SerializableState {
-        let state = self.shared.state.lock().unwrap();
+        let _swap = self.shared.swap.lock().unwrap();
+        let shards = self.shared.lock_all();
         let now = Instant::now();
 
-        SerializableState {
-            entries: state
-                .entries
-                .iter()
-                .map(|(k, v)| {
-                    (
-                        k.clone(),
-                        SerializableEntry {
-                            data: v.data.to_vec(),
-                            expires_at: v.expires_at.map(|instant| instant.duration_since(now).as_secs()),
-                        },
-                    )
-                })
-                .collect(),
-            expirations: state.expirations.iter().map(|(instant, key)| (instant.duration_since(now).as_secs(), key.clone())).collect(),
-        }
+        let databases = (0..self.databases())
+            .map(|index| {
+                let keyspace = &shards[self.shared.keyspace_range(index)];
+
+                SerializableDatabase {
+                    index,
+                    entries: keyspace
+                        .iter()
+                        .flat_map(|state| state.entries.iter())
+                        .filter(|(_, v)| !v.is_expired(now))
+                        .map(|(k, v)| {
+                            (
+                                k.clone(),
+                                SerializableEntry {
+                                    data: v.data.to_vec(),
+                                    expires_at: v.expires_at.map(|instant| instant.duration_since(now).as_secs()),
+                                },
+                            )
+                        })
+                        .collect(),
+                    expirations: keyspace.iter().flat_map(|state| state.expirations.iter()).filter(|(instant, _)| *instant > now).map(|(instant, key)| (instant.duration_since(now).as_secs(), key.clone())).collect(),
+                }
+            })
+            .filter(|database| !database.entries.is_empty())
+            .collect();
+
+        SerializableState { databases }
     }
 
     pub fn load(&self, serializable_state: SerializableState) {
-        let mut state = self.shared.state.lock().unwrap();
+        let _swap = self.shared.swap.lock().unwrap();
+        let mut shards = self.shared.lock_all();
         let now = Instant::now();
 
-        state.entries = serializable_state
-            .entries
-            .into_iter()
-            .map(|(k, v)| {
-                (
-                    k.clone(),
-                    Entry {
-                        data: Bytes::from(v.data),
-                        expires_at: v.expires_at.map(|secs| now + Duration::from_secs(secs)),
-                    },
-                )
-            })
-            .collect();
+        for state in shards.iter_mut() {
+            state.clear(&self.shared.memory);
+        }
+
+        for database in serializable_state.databases {
+            if database.index >= self.databases() {
+                warn!(index = database.index, databases = self.databases(), "skipping database outside the configured range");
+                continue;
+            }
+
+            let range = self.shared.keyspace_range(database.index);
+
+            for (k, v) in database.entries {
+                let index = range.start + self.shared.shard_index(&k);
+                let expires_at = v.expires_at.map(|secs| now + Duration::from_secs(secs));
+                shards[index].insert(k, Bytes::from(v.data), expires_at, &self.shared.memory);
+            }
+        }
+
+        drop(shards);
+        self.shared.tracking.invalidate_all();
+
+        for shard in self.shared.shards.iter() {
+            shard.background_task.notify_one();
+        }
+    }
+
+    pub fn flush(&self, lazy: bool) {
+        let flushed: Vec<_> = self.keyspace().iter().map(|shard| shard.state.lock().unwrap().take(&self.shared.memory)).collect();
+        self.shared.tracking.invalidate_all();
+        self.shared.stats.key_changed();
+
+        if lazy {
+            tokio::task::spawn_blocking(move || drop(flushed));
+        }
+    }
+
+    pub fn flush_all(&self, lazy: bool) {
+        for index in 0..self.databases() {
+            self.select(index).flush(lazy);
+        }
+    }
+
+    /// Moves `key` into database `to`, keeping its expiration. Returns false if
+    /// the key does not exist here or already exists in the target database.
+    pub fn move_key(&self, key: &str, to: usize) -> bool {
+        let from = self.shared.physical(self.index) * self.shared.shards_per_db + self.shared.shard_index(key);
+        let target = self.shared.physical(to) * self.shared.shards_per_db + self.shared.shard_index(key);
+
+        if from == target {
+            return false;
+        }
+
+        let (mut src, mut dst) = if from < target {
+            let src = self.shared.shards[from].state.lock().unwrap();
+            (src, self.shared.shards[target].state.lock().unwrap())
+        } else {
+            let dst = self.shared.shards[target].state.lock().unwrap();
+            (self.shared.shards[from].state.lock().unwrap(), dst)
+        };
+
+        let now = Instant::now();
+
+        if src.entries.get(key).map(|entry| entry.is_expired(now)).unwrap_or(true) || dst.entries.get(key).map(|entry| !entry.is_expired(now)).unwrap_or(false) {
+            return false;
+        }
+
+        let entry = src.remove(key, &self.shared.memory).unwrap();
+        dst.insert(key.to_string(), entry.data, entry.expires_at, &self.shared.memory);
+        drop((src, dst));
+
+        self.shared.tracking.invalidate(key);
+        self.shared.stats.key_changed();
+
+        if entry.expires_at.is_some() {
+            self.shared.shards[target].background_task.notify_one();
+        }
+
+        true
+    }
 
-        state.expirations = serializable_state.expirations.into_iter().map(|(secs, key)| (now + Duration::from_secs(secs), key)).collect();
+    pub fn swap(&self, a: usize, b: usize) {
+        let _swap = self.shared.swap.lock().unwrap();
+        let databases = &self.shared.databases;
+
+        let physical = databases[a].load(Ordering::Acquire);
+        databases[a].store(databases[b].load(Ordering::Acquire), Ordering::Release);
+        databases[b].store(physical, Ordering::Release);
+
+        self.shared.tracking.invalidate_all();
+        self.shared.stats.key_changed();
     }
 
     pub async fn dump_to(&self, path: &PathBuf) -> crate::Result<()> {
         let serializable_state = self.dump();
         let serialized = bincode::serialize(&serializable_state)?;
-        tokio::fs::write(path, serialized).await?;
-        Ok(())
+        let res = tokio::fs::write(path, serialized).await;
+        self.shared.stats.saved(res.is_ok());
+        Ok(res?)
     }
 
     pub async fn load_from(&self, path: &PathBuf) -> crate::Result<()> {
@@ -187,17 +478,164 @@ impl Db {
         Ok(())
     }
 
-    fn shutdown_purge_task(&self) {
-        let mut state = self.shared.state.lock().unwrap();
-        state.shutdown = true;
+    pub fn len(&self) -> usize { self.keyspace().iter().map(|shard| shard.state.lock().unwrap().entries.len()).sum() }
 
-        drop(state);
-        self.shared.background_task.notify_one();
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub fn expiring(&self) -> usize { self.keyspace().iter().map(|shard| shard.state.lock().unwrap().expirations.len()).sum() }
+
+    pub fn pubsub_channels(&self) -> usize { self.shared.pub_sub.lock().unwrap().values().filter(|tx| tx.receiver_count() > 0).count() }
+
+    pub fn keyspace_info(&self) -> String {
+        let now = Instant::now();
+        let mut info = String::from("# Keyspace\r\n");
+
+        for index in 0..self.databases() {
+            let (mut keys, mut expires, mut ttl) = (0, 0, 0u128);
+
+            for shard in self.select(index).keyspace() {
+                let state = shard.state.lock().unwrap();
+                keys += state.entries.len();
+                expires += state.expirations.len();
+                ttl += state.expirations.iter().map(|(when, _)| when.saturating_duration_since(now).as_millis()).sum::<u128>();
+            }
+
+            if keys > 0 {
+                info.push_str(&format!("db{}:keys={},expires={},avg_ttl={}\r\n", index, keys, expires, if expires > 0 { ttl / expires as u128 } else { 0 }));
+            }
+        }
+
+        info
+    }
+
+    pub fn evict(&self) -> crate::Result<()> {
+        let memory = &self.shared.memory;
+
+        while memory.over_limit() {
+            let policy = memory.policy();
+
+            if policy == Policy::NoEviction {
+                return Err(Error::Oom(OOM_MESSAGE.into()));
+            }
+
+            let (index, key) = self.shared.eviction_candidate(policy, memory.samples()).ok_or_else(|| Error::Oom(OOM_MESSAGE.into()))?;
+            let mut state = self.shared.shards[index].state.lock().unwrap();
+
+            if state.remove(&key, memory).is_some() {
+                self.shared.tracking.invalidate(&key);
+                memory.record_eviction();
+                debug!(key, %policy, "evicted key");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn keyspace(&self) -> &[Shard] { &self.shared.shards[self.shared.keyspace_range(self.index)] }
+
+    fn shard(&self, key: &str) -> &Shard { &self.keyspace()[self.shared.shard_index(key)] }
+
+    fn shutdown_purge_task(&self) {
+        for shard in self.shared.shards.iter() {
+            shard.state.lock().unwrap().shutdown = true;
+            shard.background_task.notify_one();
+        }
     }
 }
 
+impl Default for Db {
+    fn default() -> Self { Self::new() }
+}
+
 impl Shared {
-    fn purge_expired_keys(&self) -> Option<Instant> {
+    fn shard_index(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.shards_per_db as u64) as usize
+    }
+
+    fn physical(&self, index: usize) -> usize { self.databases[index].load(Ordering::Acquire) }
+
+    fn keyspace_range(&self, index: usize) -> std::ops::Range<usize> {
+        let start = self.physical(index) * self.shards_per_db;
+        start..start + self.shards_per_db
+    }
+
+    fn lock_all(&self) -> Vec<MutexGuard<'_, State>> { self.shards.iter().map(|shard| shard.state.lock().unwrap()).collect() }
+
+    /// Samples `samples` keys across the shards that hold an evictable key,
+    /// so empty databases never crowd out real candidates.
+    fn eviction_candidate(&self, policy: Policy, samples: usize) -> Option<(usize, String)> {
+        let now = Instant::now();
+        let mut best: Option<(u64, usize, String)> = None;
+
+        let populated: Vec<usize> = (0..self.shards.len())
+            .filter(|&index| {
+                let state = self.shards[index].state.lock().unwrap();
+                if policy.is_volatile() { !state.expirations.is_empty() } else { !state.keys.is_empty() }
+            })
+            .collect();
+
+        if populated.is_empty() {
+            return None;
+        }
+
+        for _ in 0..samples.max(1) {
+            let index = populated[memory::random() as usize % populated.len()];
+            let state = self.shards[index].state.lock().unwrap();
+
+            // The shard may have been emptied since it was counted.
+            let candidate = match policy {
+                Policy::VolatileTtl => state.expirations.iter().next().map(|(when, key)| (u64::MAX - when.duration_since(now).as_millis() as u64, key)),
+                _ if state.keys.is_empty() => None,
+                _ => {
+                    let mut key = &state.keys[memory::random() as usize % state.keys.len()];
+
+                    // Fall back to the shard's next expiring key rather than wasting the draw.
+                    if policy.is_volatile() && state.entries[key].expires_at.is_none() {
+                        match state.expirations.iter().next() {
+                            Some((_, volatile)) => key = volatile,
+                            None => continue,
+                        }
+                    }
+
+                    let entry = &state.entries[key];
+
+                    match policy {
+                        Policy::AllKeysLfu => Some((u8::MAX as u64 - entry.lfu_count(now) as u64, key)),
+                        Policy::AllKeysRandom => Some((memory::random(), key)),
+                        _ => Some((now.duration_since(entry.last_access).as_millis() as u64, key)),
+                    }
+                }
+            };
+
+            if let Some((score, key)) = candidate {
+                if best.as_ref().map(|best| score > best.0).unwrap_or(true) {
+                    best = Some((score, index, key.clone()));
+                }
+            }
+        }
+
+        best.map(|(_, index, key)| (index, key))
+    }
+}
+
+impl Shard {
+    fn new() -> Shard {
+        Shard {
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                keys: Vec::new(),
+                order: BTreeMap::new(),
+                next_id: 0,
+                expirations: BTreeSet::new(),
+                shutdown: false,
+            }),
+            background_task: Notify::new(),
+        }
+    }
+
+    fn purge_expired_keys(&self, memory: &Memory, stats: &Stats, tracking: &Tracking) -> Option<Instant> {
         let mut state = self.state.lock().unwrap();
 
         if state.shutdown {
@@ -206,14 +644,22 @@ impl Shared {
 
         let state = &mut *state;
         let now = Instant::now();
+        let started = std::time::Instant::now();
+
+        for purged in 0.. {
+            let (when, key) = state.expirations.first()?.clone();
 
-        while let Some(&(when, ref key)) = state.expirations.iter().next() {
             if when > now {
                 return Some(when);
             }
 
-            state.entries.remove(key);
-            state.expirations.remove(&(when, key.clone()));
+            if purged == ACTIVE_EXPIRE_KEYS || started.elapsed() > ACTIVE_EXPIRE_BUDGET {
+                return Some(now);
+            }
+
+            state.remove(&key, memory);
+            tracking.invalidate(&key);
+            stats.key_expired();
         }
 
         None
@@ -223,20 +669,107 @@ impl Shared {
 }
 
 impl State {
+    fn insert(&mut self, key: String, data: Bytes, expires_at: Option<Instant>, memory: &Memory) {
+        let size = std::mem::size_of::<Entry>() + 3 * (std::mem::size_of::<String>() + key.len()) + std::mem::size_of::<u64>() + data.len();
+        let now = Instant::now();
+
+        let (slot, id, lfu) = match self.entries.get(&key) {
+            Some(prev) => (prev.slot, prev.id, prev.lfu_count(now)),
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.keys.push(key.clone());
+                self.order.insert(id, key.clone());
+                (self.keys.len() - 1, id, memory::lfu_init())
+            }
+        };
+
+        let entry = Entry {
+            data,
+            expires_at,
+            size,
+            slot,
+            id,
+            last_access: now,
+            lfu,
+        };
+
+        memory.add(size);
+
+        if let Some(prev) = self.entries.insert(key.clone(), entry) {
+            memory.sub(prev.size);
+
+            if let Some(when) = prev.expires_at {
+                self.expirations.remove(&(when, key.clone()));
+            }
+        }
+
+        if let Some(when) = expires_at {
+            self.expirations.insert((when, key));
+        }
+    }
+
+    fn remove(&mut self, key: &str, memory: &Memory) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        memory.sub(entry.size);
+
+        if let Some(when) = entry.expires_at {
+            self.expirations.remove(&(when, key.to_string()));
+        }
+
+        self.order.remove(&entry.id);
+        self.keys.swap_remove(entry.slot);
+
+        if let Some(moved) = self.keys.get(entry.slot) {
+            self.entries.get_mut(moved).unwrap().slot = entry.slot;
+        }
+
+        Some(entry)
+    }
+
+    fn clear(&mut self, memory: &Memory) { drop(self.take(memory)); }
+
+    fn take(&mut self, memory: &Memory) -> HashMap<String, Entry> {
+        memory.sub(self.entries.values().map(|entry| entry.size).sum());
+        self.keys.clear();
+        self.order.clear();
+        self.expirations.clear();
+        std::mem::take(&mut self.entries)
+    }
+
     fn next_expiration(&self) -> Option<Instant> { self.expirations.iter().next().map(|expiration| expiration.0) }
 }
 
-async fn purge_expired_tasks(shared: Arc<Shared>) {
-    while !shared.is_shutdown() {
-        if let Some(when) = shared.purge_expired_keys() {
+async fn purge_expired_tasks(shared: Arc<Shared>, index: usize) {
+    let shard = &shared.shards[index];
+
+    while !shard.is_shutdown() {
+        if let Some(when) = shard.purge_expired_keys(&shared.memory, &shared.stats, &shared.tracking) {
+            if when <= Instant::now() {
+                tokio::task::yield_now().await;
+                continue;
+            }
+
             tokio::select! {
                 _ = time::sleep_until(when) => {}
-                _ = shared.background_task.notified() => {}
+                _ = shard.background_task.notified() => {}
             }
         } else {
-            shared.background_task.notified().await;
+            shard.background_task.notified().await;
         }
     }
 
-    debug!("Purge background task shut down")
+    debug!(shard = index, "Purge background task shut down")
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool { self.expires_at.map(|when| when <= now).unwrap_or(false) }
+
+    fn touch(&mut self) {
+        let now = Instant::now();
+        self.lfu = memory::lfu_increment(self.lfu_count(now));
+        self.last_access = now;
+    }
+
+    fn lfu_count(&self, now: Instant) -> u8 { memory::lfu_decay(self.lfu, now.duration_since(self.last_access).as_secs() / 60) }
 }
@@ -0,0 +1,208 @@
+use super::frame::{self, Frame};
+use super::parse::ParseError;
+use std::{error, fmt, io};
+
+/// Errors produced while serving or issuing commands.
+///
+/// The reply variants mirror the prefix of a RESP error line (`ERR`,
+/// `WRONGTYPE`, `MOVED`, ...) and round trip through `Frame::Error`, so a
+/// client can match on what the server reported instead of its wording.
+#[derive(Debug)]
+pub enum Error {
+    Err(String),
+    WrongType(String),
+    NoAuth(String),
+    NoPerm(String),
+    WrongPass(String),
+    ReadOnly(String),
+    Oom(String),
+    CrossSlot(String),
+    ClusterDown(String),
+    Moved { slot: u16, addr: String },
+    Ask { slot: u16, addr: String },
+    /// A command got too few or too many arguments; `cmd` is filled in by
+    /// `Command::from_frame` once the command name is known
+    WrongArity { cmd: String },
+    /// An error reply whose prefix isn't one of the above
+    Reply { prefix: String, message: String },
+    /// The peer sent something that isn't valid RESP or wasn't expected
+    Protocol(String),
+    Io(io::Error),
+    Other(Box<dyn error::Error + Send + Sync>),
+}
+
+impl Error {
+    pub fn other(err: impl Into<Box<dyn error::Error + Send + Sync>>) -> Error { Error::Other(err.into()) }
+
+    /// Classifies the contents of an error reply sent by the server.
+    pub fn from_reply(line: &str) -> Error {
+        if let Some(err) = Error::known_reply(line) {
+            return err;
+        }
+
+        match line.split_once(' ') {
+            Some((prefix, message)) if is_prefix(prefix) => Error::Reply { prefix: prefix.to_string(), message: message.to_string() },
+            _ if is_prefix(line) => Error::Reply { prefix: line.to_string(), message: String::new() },
+            _ => Error::Err(line.to_string()),
+        }
+    }
+
+    fn known_reply(line: &str) -> Option<Error> {
+        let (prefix, message) = line.split_once(' ').unwrap_or((line, ""));
+        let message = message.to_string();
+
+        let err = match prefix {
+            "ERR" => Error::Err(message),
+            "WRONGTYPE" => Error::WrongType(message),
+            "NOAUTH" => Error::NoAuth(message),
+            "NOPERM" => Error::NoPerm(message),
+            "WRONGPASS" => Error::WrongPass(message),
+            "READONLY" => Error::ReadOnly(message),
+            "OOM" => Error::Oom(message),
+            "CROSSSLOT" => Error::CrossSlot(message),
+            "CLUSTERDOWN" => Error::ClusterDown(message),
+            "MOVED" | "ASK" => {
+                let (slot, addr) = message.split_once(' ')?;
+                let (slot, addr) = (slot.parse().ok()?, addr.to_string());
+
+                match prefix {
+                    "MOVED" => Error::Moved { slot, addr },
+                    _ => Error::Ask { slot, addr },
+                }
+            }
+            _ => return None,
+        };
+
+        Some(err)
+    }
+
+    /// The RESP error prefix; errors that aren't replies are reported as `ERR`.
+    pub fn prefix(&self) -> &str {
+        match self {
+            Error::WrongType(_) => "WRONGTYPE",
+            Error::NoAuth(_) => "NOAUTH",
+            Error::NoPerm(_) => "NOPERM",
+            Error::WrongPass(_) => "WRONGPASS",
+            Error::ReadOnly(_) => "READONLY",
+            Error::Oom(_) => "OOM",
+            Error::CrossSlot(_) => "CROSSSLOT",
+            Error::ClusterDown(_) => "CLUSTERDOWN",
+            Error::Moved { .. } => "MOVED",
+            Error::Ask { .. } => "ASK",
+            Error::Reply { prefix, .. } => prefix,
+            Error::Err(_) | Error::WrongArity { .. } | Error::Protocol(_) | Error::Io(_) | Error::Other(_) => "ERR",
+        }
+    }
+
+    pub fn to_frame(&self) -> Frame {
+        match self {
+            Error::Protocol(msg) => Frame::Error(format!("ERR Protocol error: {}", msg)),
+            Error::Io(_) | Error::Other(_) => Frame::Error(format!("ERR {}", self)),
+            _ => Frame::Error(self.to_string()),
+        }
+    }
+}
+
+fn is_prefix(word: &str) -> bool { word.len() > 1 && word.bytes().all(|b| b.is_ascii_uppercase()) }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Err(msg)
+            | Error::WrongType(msg)
+            | Error::NoAuth(msg)
+            | Error::NoPerm(msg)
+            | Error::WrongPass(msg)
+            | Error::ReadOnly(msg)
+            | Error::Oom(msg)
+            | Error::CrossSlot(msg)
+            | Error::ClusterDown(msg)
+            | Error::Reply { message: msg, .. } => {
+                if msg.is_empty() {
+                    self.prefix().fmt(f)
+                } else {
+                    write!(f, "{} {}", self.prefix(), msg)
+                }
+            }
+            Error::Moved { slot, addr } | Error::Ask { slot, addr } => write!(f, "{} {} {}", self.prefix(), slot, addr),
+            Error::WrongArity { cmd } if cmd.is_empty() => write!(f, "{} wrong number of arguments", self.prefix()),
+            Error::WrongArity { cmd } => write!(f, "{} wrong number of arguments for '{}' command", self.prefix(), cmd),
+            Error::Protocol(msg) => write!(f, "protocol error; {}", msg),
+            Error::Io(err) => err.fmt(f),
+            Error::Other(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Error { Error::Other(msg.into()) }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Error { Error::Other(msg.into()) }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error { Error::Io(err) }
+}
+
+impl From<frame::Error> for Error {
+    fn from(err: frame::Error) -> Error {
+        match err {
+            frame::Error::Incomplete => Error::Protocol("stream ended early".into()),
+            frame::Error::Other(err) => err,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        match err {
+            ParseError::EndOfStream => Error::WrongArity { cmd: String::new() },
+            ParseError::Other(err) => err,
+        }
+    }
+}
+
+macro_rules! impl_from_other {
+    ($($ty:ty),* $(,)?) => {
+        $(impl From<$ty> for Error {
+            fn from(err: $ty) -> Error { Error::Other(err.into()) }
+        })*
+    };
+}
+
+impl_from_other!(
+    std::string::FromUtf8Error,
+    std::str::Utf8Error,
+    std::num::TryFromIntError,
+    std::num::ParseIntError,
+    std::num::ParseFloatError,
+    std::time::SystemTimeError,
+    bincode::Error,
+    serde_json::Error,
+    rmp_serde::encode::Error,
+    rmp_serde::decode::Error,
+    tokio::task::JoinError,
+    tokio::sync::AcquireError,
+    tokio::sync::oneshot::error::RecvError,
+    tokio::time::error::Elapsed,
+    tokio_rustls::rustls::Error,
+    tokio_rustls::rustls::server::VerifierBuilderError,
+    tokio_rustls::rustls::pki_types::InvalidDnsNameError,
+    Box<dyn error::Error + Send + Sync>,
+);
+
+impl<T: fmt::Debug + Send + Sync + 'static> From<tokio::sync::mpsc::error::SendError<T>> for Error {
+    fn from(err: tokio::sync::mpsc::error::SendError<T>) -> Error { Error::Other(err.into()) }
+}
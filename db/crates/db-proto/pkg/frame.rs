@@ -1,20 +1,35 @@
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryInto;
 use std::fmt;
 use std::io::Cursor;
 use std::num::TryFromIntError;
+use std::ops::Range;
 use std::string::FromUtf8Error;
 
 #[derive(Clone, Debug)]
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
     Null,
     Array(Vec<Frame>),
 }
 
+pub const DEFAULT_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+pub const DEFAULT_MAX_ARRAY_LEN: usize = 1024 * 1024;
+
+const MAX_INLINE_LEN: usize = 64 * 1024;
+const MAX_DEPTH: usize = 32;
+
+/// Upper bounds enforced while a frame is still being buffered, so a peer
+/// can't make us allocate for a `$` or `*` length it never intends to send.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_bulk_len: usize,
+    pub max_array_len: usize,
+}
+
 #[derive(Debug)]
 pub enum Error {
     Incomplete,
@@ -33,7 +48,7 @@ impl Frame {
         }
     }
 
-    pub fn push_int(&mut self, value: u64) {
+    pub fn push_int(&mut self, value: i64) {
         match self {
             Frame::Array(vec) => {
                 vec.push(Frame::Integer(value));
@@ -42,46 +57,33 @@ impl Frame {
         }
     }
 
-    pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
-        match get_u8(src)? {
-            b'+' => {
-                get_line(src)?;
-                Ok(())
-            }
-            b'-' => {
-                get_line(src)?;
-                Ok(())
-            }
-            b':' => {
-                let _ = get_decimal(src)?;
-                Ok(())
-            }
-            b'$' => {
-                if b'-' == peek_u8(src)? {
-                    // Skip '-1\r\n'
-                    skip(src, 4)
-                } else {
-                    // Read the bulk string
-                    let len: usize = get_decimal(src)?.try_into()?;
+    /// Parses one frame off the front of `buf` in a single pass, returning
+    /// `Ok(None)` until all of it has been buffered. Lengths are checked
+    /// against `limits` as soon as they are read. Bulk payloads are split out
+    /// of `buf` rather than copied, so they share its allocation.
+    pub fn decode(buf: &mut BytesMut, limits: &Limits) -> Result<Option<Frame>, Error> {
+        let mut src = Cursor::new(&buf[..]);
+        let mut bulks = vec![];
 
-                    // skip that number of bytes + 2 (\r\n).
-                    skip(src, len + 2)
-                }
-            }
-            b'*' => {
-                let len = get_decimal(src)?;
+        let mut frame = match Frame::parse(&mut src, limits, 0, &mut bulks) {
+            Ok(frame) => frame,
+            Err(Error::Incomplete) => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
-                for _ in 0..len {
-                    Frame::check(src)?;
-                }
+        let data = buf.split_to(src.position() as usize).freeze();
+        frame.fill_bulks(&data, &mut bulks.into_iter());
 
-                Ok(())
-            }
-            actual => Err(format!("protocol error; invalid frame type byte `{}`", actual).into()),
-        }
+        Ok(Some(frame))
     }
 
-    pub fn parse(src: &mut Cursor<&[u8]>) -> Result<Frame, Error> {
+    /// Bulk frames are returned empty, with the range of their payload
+    /// pushed to `bulks` in the order they appear.
+    fn parse(src: &mut Cursor<&[u8]>, limits: &Limits, depth: usize, bulks: &mut Vec<Range<usize>>) -> Result<Frame, Error> {
+        if depth > MAX_DEPTH {
+            return Err(protocol("frame nested too deeply"));
+        }
+
         match get_u8(src)? {
             b'+' => {
                 let line = get_line(src)?.to_vec();
@@ -96,47 +98,97 @@ impl Frame {
                 Ok(Frame::Error(string))
             }
             b':' => {
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
+                let value = get_integer(src)?;
+                Ok(Frame::Integer(value))
             }
             b'$' => {
                 if b'-' == peek_u8(src)? {
                     let line = get_line(src)?;
 
                     if line != b"-1" {
-                        return Err("protocol error; invalid frame format".into());
+                        return Err(protocol("invalid frame format"));
                     }
 
                     Ok(Frame::Null)
                 } else {
-                    let len = get_decimal(src)?.try_into()?;
-                    let n = len + 2;
+                    let len: usize = get_decimal(src)?.try_into()?;
 
-                    if src.remaining() < n {
-                        return Err(Error::Incomplete);
+                    if len > limits.max_bulk_len {
+                        return Err(protocol("invalid bulk length"));
                     }
 
-                    let data = Bytes::copy_from_slice(&src.chunk()[..len]);
+                    let start = src.position() as usize;
+                    skip(src, len + 2)?;
 
-                    skip(src, n)?;
-                    Ok(Frame::Bulk(data))
+                    if !src.get_ref()[start + len..].starts_with(b"\r\n") {
+                        return Err(protocol("invalid frame format"));
+                    }
+
+                    bulks.push(start..start + len);
+                    Ok(Frame::Bulk(Bytes::new()))
                 }
             }
             b'*' => {
-                let len = get_decimal(src)?.try_into()?;
-                let mut out = Vec::with_capacity(len);
+                let len = get_decimal(src)?;
+
+                if len > limits.max_array_len as u64 {
+                    return Err(protocol("invalid multibulk length"));
+                }
+
+                // Every element takes at least three bytes, so a length the
+                // buffer can't hold yet doesn't get to size the allocation.
+                let mut out = Vec::with_capacity((len as usize).min(src.remaining() / 3));
 
                 for _ in 0..len {
-                    out.push(Frame::parse(src)?);
+                    out.push(Frame::parse(src, limits, depth + 1, bulks)?);
                 }
 
                 Ok(Frame::Array(out))
             }
-            _ => unimplemented!(),
+            actual => Err(protocol(format!("invalid frame type byte `{}`", actual))),
+        }
+    }
+
+    fn fill_bulks(&mut self, data: &Bytes, bulks: &mut impl Iterator<Item = Range<usize>>) {
+        match self {
+            Frame::Bulk(val) => *val = data.slice(bulks.next().expect("bulk range recorded while parsing")),
+            Frame::Array(entries) => entries.iter_mut().for_each(|entry| entry.fill_bulks(data, bulks)),
+            _ => {}
         }
     }
 
-    pub fn to_error(&self) -> crate::Error { format!("unexpected frame: {}", self).into() }
+    pub fn encode(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::Simple(val) => {
+                dst.put_u8(b'+');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(val) => {
+                dst.put_u8(b'-');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(val) => {
+                dst.put_u8(b':');
+                dst.put_slice(format!("{}\r\n", val).as_bytes());
+            }
+            Frame::Null => dst.put_slice(b"$-1\r\n"),
+            Frame::Bulk(val) => {
+                dst.put_slice(format!("${}\r\n", val.len()).as_bytes());
+                dst.put_slice(val);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Array(val) => {
+                dst.put_slice(format!("*{}\r\n", val.len()).as_bytes());
+                for entry in val {
+                    entry.encode(dst);
+                }
+            }
+        }
+    }
+
+    pub fn to_error(&self) -> crate::Error { crate::Error::Protocol(format!("unexpected frame: {}", self)) }
 }
 
 impl PartialEq<&str> for Frame {
@@ -206,12 +258,19 @@ fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
     use atoi::atoi;
     let line = get_line(src)?;
 
-    atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
+    atoi::<u64>(line).ok_or_else(|| protocol("invalid frame format"))
+}
+
+fn get_integer(src: &mut Cursor<&[u8]>) -> Result<i64, Error> {
+    use atoi::atoi;
+    let line = get_line(src)?;
+
+    atoi::<i64>(line).ok_or_else(|| protocol("invalid frame format"))
 }
 
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     let start = src.position() as usize;
-    let end = src.get_ref().len() - 1;
+    let end = src.get_ref().len().saturating_sub(1);
 
     for i in start..end {
         if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
@@ -220,9 +279,24 @@ fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
         }
     }
 
+    if end.saturating_sub(start) > MAX_INLINE_LEN {
+        return Err(protocol("too big inline request"));
+    }
+
     Err(Error::Incomplete)
 }
 
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_bulk_len: DEFAULT_MAX_BULK_LEN,
+            max_array_len: DEFAULT_MAX_ARRAY_LEN,
+        }
+    }
+}
+
+fn protocol(msg: impl Into<String>) -> Error { Error::Other(crate::Error::Protocol(msg.into())) }
+
 impl From<String> for Error {
     fn from(src: String) -> Error { Error::Other(src.into()) }
 }
@@ -232,11 +306,11 @@ impl From<&str> for Error {
 }
 
 impl From<FromUtf8Error> for Error {
-    fn from(_src: FromUtf8Error) -> Error { "protocol error; invalid frame format".into() }
+    fn from(_src: FromUtf8Error) -> Error { protocol("invalid frame format") }
 }
 
 impl From<TryFromIntError> for Error {
-    fn from(_src: TryFromIntError) -> Error { "protocol error; invalid frame format".into() }
+    fn from(_src: TryFromIntError) -> Error { protocol("invalid frame format") }
 }
 
 impl std::error::Error for Error {}
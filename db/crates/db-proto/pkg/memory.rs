@@ -0,0 +1,183 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+pub const DEFAULT_SAMPLES: usize = 5;
+pub const OOM_MESSAGE: &str = "command not allowed when used memory > 'maxmemory'.";
+
+const LFU_INIT: u8 = 5;
+const LFU_LOG_FACTOR: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileTtl,
+}
+
+#[derive(Debug)]
+pub struct Memory {
+    used: AtomicUsize,
+    maxmemory: AtomicUsize,
+    samples: AtomicUsize,
+    policy: RwLock<Policy>,
+    evicted: AtomicU64,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory {
+            used: AtomicUsize::new(0),
+            maxmemory: AtomicUsize::new(0),
+            samples: AtomicUsize::new(DEFAULT_SAMPLES),
+            policy: RwLock::new(Policy::default()),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn used(&self) -> usize { self.used.load(Ordering::Relaxed) }
+
+    pub fn maxmemory(&self) -> usize { self.maxmemory.load(Ordering::Relaxed) }
+
+    pub fn set_maxmemory(&self, bytes: usize) { self.maxmemory.store(bytes, Ordering::Relaxed); }
+
+    pub fn samples(&self) -> usize { self.samples.load(Ordering::Relaxed) }
+
+    pub fn set_samples(&self, samples: usize) { self.samples.store(samples.max(1), Ordering::Relaxed); }
+
+    pub fn policy(&self) -> Policy { *self.policy.read().unwrap() }
+
+    pub fn set_policy(&self, policy: Policy) { *self.policy.write().unwrap() = policy; }
+
+    pub fn evicted(&self) -> u64 { self.evicted.load(Ordering::Relaxed) }
+
+    pub fn over_limit(&self) -> bool {
+        let maxmemory = self.maxmemory();
+        maxmemory > 0 && self.used() > maxmemory
+    }
+
+    pub fn info(&self) -> String {
+        format!(
+            "# Memory\r\nused_memory:{}\r\nused_memory_human:{}\r\nmaxmemory:{}\r\nmaxmemory_human:{}\r\nmaxmemory_policy:{}\r\nmaxmemory_samples:{}\r\n",
+            self.used(),
+            human_size(self.used()),
+            self.maxmemory(),
+            human_size(self.maxmemory()),
+            self.policy(),
+            self.samples()
+        )
+    }
+
+    pub(crate) fn add(&self, bytes: usize) { self.used.fetch_add(bytes, Ordering::Relaxed); }
+
+    pub(crate) fn sub(&self, bytes: usize) { self.used.fetch_sub(bytes, Ordering::Relaxed); }
+
+    pub(crate) fn record_eviction(&self) { self.evicted.fetch_add(1, Ordering::Relaxed); }
+}
+
+impl Default for Memory {
+    fn default() -> Self { Self::new() }
+}
+
+impl Policy {
+    pub fn is_volatile(&self) -> bool { matches!(self, Policy::VolatileLru | Policy::VolatileTtl) }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Policy, String> {
+        match &s.to_lowercase()[..] {
+            "noeviction" => Ok(Policy::NoEviction),
+            "allkeys-lru" => Ok(Policy::AllKeysLru),
+            "allkeys-lfu" => Ok(Policy::AllKeysLfu),
+            "allkeys-random" => Ok(Policy::AllKeysRandom),
+            "volatile-lru" => Ok(Policy::VolatileLru),
+            "volatile-ttl" => Ok(Policy::VolatileTtl),
+            _ => Err(format!("unknown maxmemory policy `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Policy::NoEviction => "noeviction",
+            Policy::AllKeysLru => "allkeys-lru",
+            Policy::AllKeysLfu => "allkeys-lfu",
+            Policy::AllKeysRandom => "allkeys-random",
+            Policy::VolatileLru => "volatile-lru",
+            Policy::VolatileTtl => "volatile-ttl",
+        })
+    }
+}
+
+pub fn parse_size(src: &str) -> Result<usize, String> {
+    let src = src.trim().to_lowercase();
+    let split = src.find(|c: char| !c.is_ascii_digit()).unwrap_or(src.len());
+    let (digits, unit) = src.split_at(split);
+    let n: usize = digits.parse().map_err(|_| format!("invalid memory size `{}`", src))?;
+
+    let scale = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid memory unit `{}`", unit)),
+    };
+
+    n.checked_mul(scale).ok_or_else(|| format!("memory size `{}` is too large", src))
+}
+
+pub fn human_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{}B", bytes),
+        1024..=1048575 => format!("{:.2}K", bytes as f64 / 1024.0),
+        1048576..=1073741823 => format!("{:.2}M", bytes as f64 / 1048576.0),
+        _ => format!("{:.2}G", bytes as f64 / 1073741824.0),
+    }
+}
+
+pub(crate) fn lfu_init() -> u8 { LFU_INIT }
+
+pub(crate) fn lfu_increment(counter: u8) -> u8 {
+    if counter == u8::MAX {
+        return counter;
+    }
+
+    let base = counter.saturating_sub(LFU_INIT) as u64;
+
+    if random().is_multiple_of(base * LFU_LOG_FACTOR + 1) {
+        counter + 1
+    } else {
+        counter
+    }
+}
+
+pub(crate) fn lfu_decay(counter: u8, idle_minutes: u64) -> u8 { counter.saturating_sub(idle_minutes.min(u8::MAX as u64) as u8) }
+
+pub(crate) fn random() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x
+    })
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+pub const LATENCY_BUCKETS: [f64; 14] = [0.00005, 0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    commands: RwLock<BTreeMap<String, Arc<CommandMetrics>>>,
+}
+
+#[derive(Debug, Default)]
+struct CommandMetrics {
+    calls: AtomicU64,
+    total_micros: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandStats {
+    pub calls: u64,
+    pub total: Duration,
+    pub buckets: Vec<u64>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics { Metrics::default() }
+
+    pub fn record(&self, command: &str, elapsed: Duration) {
+        let metrics = self.commands.read().unwrap().get(command).cloned();
+        let metrics = match metrics {
+            Some(metrics) => metrics,
+            None => self.commands.write().unwrap().entry(command.to_string()).or_default().clone(),
+        };
+
+        metrics.calls.fetch_add(1, Ordering::Relaxed);
+        metrics.total_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| elapsed.as_secs_f64() <= le) {
+            metrics.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn commands(&self) -> Vec<(String, CommandStats)> {
+        self.commands
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, metrics)| {
+                let stats = CommandStats {
+                    calls: metrics.calls.load(Ordering::Relaxed),
+                    total: Duration::from_micros(metrics.total_micros.load(Ordering::Relaxed)),
+                    buckets: metrics.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect(),
+                };
+                (name.clone(), stats)
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) { self.commands.write().unwrap().clear(); }
+}
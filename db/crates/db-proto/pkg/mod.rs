@@ -1,10 +1,26 @@
 mod connection;
 
+pub mod acl;
+pub mod cluster;
+pub mod config;
 pub mod db;
+pub mod error;
 pub mod frame;
+pub mod memory;
+pub mod metrics;
+pub mod monitor;
+pub mod pattern;
+pub mod registry;
+pub mod replication;
+pub mod session;
+pub mod slowlog;
+pub mod stats;
+pub mod storage;
+pub mod tls;
+pub mod tracking;
 
 pub(crate) mod parse;
 pub(crate) mod shutdown;
 
-pub use connection::Connection;
+pub use connection::{Connection, Stream};
 pub use frame::Frame;
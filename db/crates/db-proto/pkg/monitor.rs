@@ -0,0 +1,48 @@
+use super::stats::unix_time;
+use super::Frame;
+
+use bytes::Bytes;
+use tokio::sync::broadcast;
+
+const CAPACITY: usize = 4096;
+
+#[derive(Debug)]
+pub struct Monitor {
+    tx: broadcast::Sender<Bytes>,
+}
+
+impl Monitor {
+    pub fn new() -> Monitor { Monitor { tx: broadcast::channel(CAPACITY).0 } }
+
+    pub fn is_active(&self) -> bool { self.tx.receiver_count() > 0 }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Bytes> { self.tx.subscribe() }
+
+    pub fn feed(&self, frame: &Frame, db: usize, addr: &str) {
+        if !self.is_active() {
+            return;
+        }
+
+        let now = unix_time();
+        let mut line = format!("{}.{:06} [{} {}]", now.as_secs(), now.subsec_micros(), db, addr);
+
+        let parts = match frame {
+            Frame::Array(parts) => parts.as_slice(),
+            frame => std::slice::from_ref(frame),
+        };
+
+        for part in parts {
+            let arg = match part {
+                Frame::Bulk(bytes) => bytes.escape_ascii().to_string(),
+                frame => frame.to_string().escape_default().to_string(),
+            };
+            line.push_str(&format!(" \"{}\"", arg));
+        }
+
+        let _ = self.tx.send(Bytes::from(line));
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self { Self::new() }
+}
@@ -17,7 +17,7 @@ impl Parse {
     pub fn new(frame: Frame) -> Result<Parse, ParseError> {
         let array = match frame {
             Frame::Array(array) => array,
-            frame => return Err(format!("protocol error; expected array, got {:?}", frame).into()),
+            frame => return Err(protocol(format!("expected array, got {:?}", frame))),
         };
 
         Ok(Parse { parts: array.into_iter() })
@@ -28,8 +28,8 @@ impl Parse {
     pub fn next_string(&mut self) -> Result<String, ParseError> {
         match self.next()? {
             Frame::Simple(s) => Ok(s),
-            Frame::Bulk(data) => str::from_utf8(&data[..]).map(|s| s.to_string()).map_err(|_| "protocol error; invalid string".into()),
-            frame => Err(format!("protocol error; expected simple frame or bulk frame, got {:?}", frame).into()),
+            Frame::Bulk(data) => str::from_utf8(&data[..]).map(|s| s.to_string()).map_err(|_| protocol("invalid string")),
+            frame => Err(protocol(format!("expected simple frame or bulk frame, got {:?}", frame))),
         }
     }
 
@@ -37,19 +37,19 @@ impl Parse {
         match self.next()? {
             Frame::Simple(s) => Ok(Bytes::from(s.into_bytes())),
             Frame::Bulk(data) => Ok(data),
-            frame => Err(format!("protocol error; expected simple frame or bulk frame, got {:?}", frame).into()),
+            frame => Err(protocol(format!("expected simple frame or bulk frame, got {:?}", frame))),
         }
     }
 
     pub fn next_int(&mut self) -> Result<u64, ParseError> {
         use atoi::atoi;
-        const MSG: &str = "protocol error; invalid number";
+        const MSG: &str = "invalid number";
 
         match self.next()? {
-            Frame::Integer(v) => Ok(v),
-            Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
-            Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| MSG.into()),
-            frame => Err(format!("protocol error; expected int frame but got {:?}", frame).into()),
+            Frame::Integer(v) => u64::try_from(v).map_err(|_| protocol(MSG)),
+            Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| protocol(MSG)),
+            Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| protocol(MSG)),
+            frame => Err(protocol(format!("expected int frame but got {:?}", frame))),
         }
     }
 
@@ -57,11 +57,17 @@ impl Parse {
         if self.parts.next().is_none() {
             Ok(())
         } else {
-            Err("protocol error; expected end of frame, but there was more".into())
+            Err(ParseError::Other(crate::Error::WrongArity { cmd: String::new() }))
         }
     }
 }
 
+fn protocol(msg: impl Into<String>) -> ParseError { ParseError::Other(crate::Error::Protocol(msg.into())) }
+
+impl From<crate::Error> for ParseError {
+    fn from(src: crate::Error) -> ParseError { ParseError::Other(src) }
+}
+
 impl From<String> for ParseError {
     fn from(src: String) -> ParseError { ParseError::Other(src.into()) }
 }
@@ -0,0 +1,96 @@
+/// Glob-style matching with the same rules as redis: `*`, `?`, `[abc]`,
+/// `[^abc]`, `[a-z]` and `\` to escape the next character.
+pub fn matches(pattern: &str, string: &str) -> bool { match_bytes(pattern.as_bytes(), string.as_bytes()) }
+
+fn match_bytes(mut pattern: &[u8], mut string: &[u8]) -> bool {
+    while let Some(&p) = pattern.first() {
+        match p {
+            b'*' => {
+                while pattern.len() > 1 && pattern[1] == b'*' {
+                    pattern = &pattern[1..];
+                }
+
+                if pattern.len() == 1 {
+                    return true;
+                }
+
+                for i in 0..=string.len() {
+                    if match_bytes(&pattern[1..], &string[i..]) {
+                        return true;
+                    }
+                }
+
+                return false;
+            }
+            b'?' => {
+                if string.is_empty() {
+                    return false;
+                }
+                string = &string[1..];
+            }
+            b'[' => {
+                let Some(&c) = string.first() else { return false };
+                let (matched, rest) = match_class(&pattern[1..], c);
+
+                if !matched {
+                    return false;
+                }
+
+                pattern = rest;
+                string = &string[1..];
+                continue;
+            }
+            b'\\' if pattern.len() >= 2 => {
+                if string.first() != Some(&pattern[1]) {
+                    return false;
+                }
+                pattern = &pattern[1..];
+                string = &string[1..];
+            }
+            _ => {
+                if string.first() != Some(&p) {
+                    return false;
+                }
+                string = &string[1..];
+            }
+        }
+
+        pattern = &pattern[1..];
+    }
+
+    string.is_empty()
+}
+
+fn match_class(mut pattern: &[u8], c: u8) -> (bool, &[u8]) {
+    let negate = pattern.first() == Some(&b'^');
+    let mut matched = false;
+
+    if negate {
+        pattern = &pattern[1..];
+    }
+
+    loop {
+        match pattern {
+            [] => break,
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
+            }
+            [b'\\', e, rest @ ..] => {
+                matched |= *e == c;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (lo, hi) = if start <= end { (*start, *end) } else { (*end, *start) };
+                matched |= lo <= c && c <= hi;
+                pattern = rest;
+            }
+            [e, rest @ ..] => {
+                matched |= *e == c;
+                pattern = rest;
+            }
+        }
+    }
+
+    (matched != negate, pattern)
+}
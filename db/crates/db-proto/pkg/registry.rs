@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+
+#[derive(Debug)]
+pub struct Registry {
+    clients: Mutex<BTreeMap<u64, Arc<Client>>>,
+    next_id: AtomicU64,
+    pause: Mutex<Option<Pause>>,
+    unpaused: Notify,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    id: u64,
+    addr: String,
+    created: Instant,
+    state: Mutex<State>,
+    subscriptions: AtomicUsize,
+    killed: AtomicBool,
+    kill: Notify,
+}
+
+#[derive(Debug)]
+struct State {
+    name: String,
+    user: String,
+    command: String,
+    last_interaction: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    writes_only: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub id: Option<u64>,
+    pub addr: Option<String>,
+    pub user: Option<String>,
+    pub skip: Option<u64>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            clients: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+            pause: Mutex::new(None),
+            unpaused: Notify::new(),
+        }
+    }
+
+    pub fn register(&self, addr: &str, user: &str) -> Arc<Client> {
+        let now = Instant::now();
+        let client = Arc::new(Client {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            addr: addr.to_string(),
+            created: now,
+            state: Mutex::new(State {
+                name: String::new(),
+                user: user.to_string(),
+                command: "NULL".to_string(),
+                last_interaction: now,
+            }),
+            subscriptions: AtomicUsize::new(0),
+            killed: AtomicBool::new(false),
+            kill: Notify::new(),
+        });
+
+        self.clients.lock().unwrap().insert(client.id, client.clone());
+        client
+    }
+
+    pub fn unregister(&self, id: u64) { self.clients.lock().unwrap().remove(&id); }
+
+    pub fn get(&self, id: u64) -> Option<Arc<Client>> { self.clients.lock().unwrap().get(&id).cloned() }
+
+    pub fn list(&self, ids: &[u64]) -> String {
+        self.clients
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|client| ids.is_empty() || ids.contains(&client.id))
+            .map(|client| client.describe() + "\n")
+            .collect()
+    }
+
+    pub fn kill(&self, filter: &Filter) -> usize {
+        let clients: Vec<Arc<Client>> = self.clients.lock().unwrap().values().filter(|client| filter.matches(client)).cloned().collect();
+
+        for client in &clients {
+            client.kill();
+        }
+
+        clients.len()
+    }
+
+    pub fn pause(&self, timeout: Duration, writes_only: bool) {
+        *self.pause.lock().unwrap() = Some(Pause {
+            until: Instant::now() + timeout,
+            writes_only,
+        });
+    }
+
+    pub fn unpause(&self) {
+        *self.pause.lock().unwrap() = None;
+        self.unpaused.notify_waiters();
+    }
+
+    pub async fn wait_unpaused(&self, write: bool) {
+        loop {
+            let unpaused = self.unpaused.notified();
+
+            let until = match *self.pause.lock().unwrap() {
+                Some(pause) if pause.until > Instant::now() && (write || !pause.writes_only) => pause.until,
+                _ => return,
+            };
+
+            tokio::select! {
+                _ = time::sleep_until(until) => {}
+                _ = unpaused => {}
+            }
+        }
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self { Self::new() }
+}
+
+impl Client {
+    pub fn id(&self) -> u64 { self.id }
+
+    pub fn addr(&self) -> &str { &self.addr }
+
+    pub fn name(&self) -> String { self.state.lock().unwrap().name.clone() }
+
+    pub fn set_name(&self, name: String) { self.state.lock().unwrap().name = name; }
+
+    pub fn begin(&self, command: &str, user: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.command = command.to_string();
+        state.user = user.to_string();
+        state.last_interaction = Instant::now();
+    }
+
+    pub fn set_subscriptions(&self, count: usize) { self.subscriptions.store(count, Ordering::Relaxed); }
+
+    pub fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+        self.kill.notify_waiters();
+    }
+
+    pub async fn killed(&self) {
+        let kill = self.kill.notified();
+
+        if self.killed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        kill.await
+    }
+
+    pub fn describe(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let subscriptions = self.subscriptions.load(Ordering::Relaxed);
+
+        format!(
+            "id={} addr={} name={} age={} idle={} flags={} sub={} cmd={} user={}",
+            self.id,
+            self.addr,
+            state.name,
+            self.created.elapsed().as_secs(),
+            state.last_interaction.elapsed().as_secs(),
+            if subscriptions > 0 { "P" } else { "N" },
+            subscriptions,
+            state.command,
+            state.user
+        )
+    }
+}
+
+impl Filter {
+    fn matches(&self, client: &Client) -> bool {
+        self.id.map(|id| id == client.id).unwrap_or(true)
+            && self.addr.as_ref().map(|addr| *addr == client.addr).unwrap_or(true)
+            && self.user.as_ref().map(|user| *user == client.state.lock().unwrap().user).unwrap_or(true)
+            && self.skip.map(|id| id != client.id).unwrap_or(true)
+    }
+}
@@ -0,0 +1,547 @@
+use super::acl::DEFAULT_USER;
+use super::db::{Db, SerializableState};
+use super::session::Session;
+use super::tls::TlsConnector;
+use super::{Connection, Frame};
+use crate::cmd::{Auth, Command};
+
+use bytes::{Bytes, BytesMut};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio::sync::{broadcast, RwLock, RwLockReadGuard};
+use tokio::task::AbortHandle;
+use tokio::time::{self, Duration, Instant};
+use tracing::{error, info, warn};
+
+pub const BACKLOG_SIZE: usize = 1024 * 1024;
+
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct Replication {
+    state: Mutex<State>,
+    feed: broadcast::Sender<Feed>,
+    /// Shared by writes from the moment they are applied until they are
+    /// propagated, exclusive while a full resync takes its snapshot, so every
+    /// write is either in the snapshot or on the feed after it, never both.
+    ordering: RwLock<()>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Feed {
+    Data(Bytes),
+    Reset,
+}
+
+#[derive(Debug)]
+pub enum Psync {
+    Full {
+        replid: String,
+        offset: u64,
+        snapshot: Vec<u8>,
+        feed: broadcast::Receiver<Feed>,
+    },
+    Continue {
+        replid: String,
+        backlog: Bytes,
+        feed: broadcast::Receiver<Feed>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkState {
+    Connect,
+    Connecting,
+    Sync,
+    Connected,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MasterAuth {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// How a replica secures its link to the master. The certificate is checked
+/// against `server_name`, or the master's host when it is not set.
+#[derive(Clone)]
+pub struct MasterTls {
+    pub connector: TlsConnector,
+    pub server_name: Option<String>,
+}
+
+#[derive(Debug)]
+struct State {
+    replid: String,
+    offset: u64,
+    backlog: BytesMut,
+    backlog_start: u64,
+    listening_port: u16,
+    role: Role,
+    replicas: BTreeMap<u64, ReplicaInfo>,
+    next_replica_id: u64,
+    masterauth: MasterAuth,
+    master_tls: Option<MasterTls>,
+    selected: Option<usize>,
+    master_db: usize,
+}
+
+#[derive(Debug)]
+enum Role {
+    Master,
+    Replica { host: String, port: u16, link: LinkState, task: AbortHandle },
+}
+
+#[derive(Debug)]
+struct ReplicaInfo {
+    addr: String,
+    port: Option<u16>,
+    ack: u64,
+    last_ack: Instant,
+}
+
+impl Replication {
+    pub fn new() -> Replication {
+        let (feed, _) = broadcast::channel(1024);
+
+        Replication {
+            feed,
+            ordering: RwLock::new(()),
+            state: Mutex::new(State {
+                replid: new_replid(),
+                offset: 0,
+                backlog: BytesMut::new(),
+                backlog_start: 0,
+                listening_port: 0,
+                role: Role::Master,
+                replicas: BTreeMap::new(),
+                next_replica_id: 0,
+                masterauth: MasterAuth::default(),
+                master_tls: None,
+                selected: None,
+                master_db: 0,
+            }),
+        }
+    }
+
+    pub fn is_replica(&self) -> bool { matches!(self.state.lock().unwrap().role, Role::Replica { .. }) }
+
+    pub fn set_masterauth(&self, auth: MasterAuth) { self.state.lock().unwrap().masterauth = auth; }
+
+    pub fn set_master_tls(&self, tls: Option<MasterTls>) { self.state.lock().unwrap().master_tls = tls; }
+
+    pub fn set_listening_port(&self, port: u16) { self.state.lock().unwrap().listening_port = port; }
+
+    /// Must be held while a write is applied and until it is propagated.
+    pub async fn write_lock(&self) -> RwLockReadGuard<'_, ()> { self.ordering.read().await }
+
+    /// Appends a write executed against database `db`, preceded by a `SELECT`
+    /// whenever the stream was last positioned on a different database.
+    pub fn propagate(&self, db: usize, frame: &Frame) {
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf);
+
+        let mut state = self.state.lock().unwrap();
+
+        if state.selected != Some(db) {
+            let mut select = Frame::array();
+            select.push_bulk(Bytes::from_static(b"select"));
+            select.push_bulk(Bytes::from(db.to_string()));
+
+            let mut prefix = BytesMut::new();
+            select.encode(&mut prefix);
+            prefix.unsplit(buf);
+            buf = prefix;
+
+            state.selected = Some(db);
+        }
+
+        let bytes = buf.freeze();
+        state.append(&bytes);
+
+        let _ = self.feed.send(Feed::Data(bytes));
+    }
+
+    /// Appends a frame received from our own master verbatim, so offsets stay
+    /// in step with the master's stream.
+    pub fn relay(&self, frame: &Frame) {
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf);
+        let bytes = buf.freeze();
+
+        let mut state = self.state.lock().unwrap();
+        state.append(&bytes);
+
+        let _ = self.feed.send(Feed::Data(bytes));
+    }
+
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        state.replid = new_replid();
+        state.backlog.clear();
+        state.backlog_start = state.offset;
+
+        let _ = self.feed.send(Feed::Reset);
+    }
+
+    pub async fn psync(&self, replid: &str, offset: Option<u64>, snapshot: impl FnOnce() -> crate::Result<Vec<u8>>) -> crate::Result<Psync> {
+        let _writes = self.ordering.write().await;
+        let feed = self.feed.subscribe();
+
+        let (replid, offset) = {
+            let mut state = self.state.lock().unwrap();
+            state.selected = None;
+
+            match offset {
+                Some(offset) if replid == state.replid && offset >= state.backlog_start && offset <= state.offset => {
+                    let start = (offset - state.backlog_start) as usize;
+
+                    return Ok(Psync::Continue {
+                        replid: state.replid.clone(),
+                        backlog: Bytes::copy_from_slice(&state.backlog[start..]),
+                        feed,
+                    });
+                }
+                _ => (state.replid.clone(), state.offset),
+            }
+        };
+
+        // The ordering guard alone keeps writes out until the snapshot is
+        // taken, so commands that only read the replication state carry on.
+        Ok(Psync::Full {
+            replid,
+            offset,
+            snapshot: snapshot()?,
+            feed,
+        })
+    }
+
+    pub fn register_replica(&self, addr: &str, port: Option<u16>) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_replica_id;
+
+        state.next_replica_id += 1;
+        state.replicas.insert(
+            id,
+            ReplicaInfo {
+                addr: addr.to_string(),
+                port,
+                ack: 0,
+                last_ack: Instant::now(),
+            },
+        );
+
+        id
+    }
+
+    pub fn ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.state.lock().unwrap().replicas.get_mut(&id) {
+            replica.ack = offset;
+            replica.last_ack = Instant::now();
+        }
+    }
+
+    pub fn unregister_replica(&self, id: u64) { self.state.lock().unwrap().replicas.remove(&id); }
+
+    pub fn replicate_from(&self, db: Db, host: String, port: u16) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Role::Replica { task, .. } = &state.role {
+            task.abort();
+        }
+
+        let task = tokio::spawn(replicate(db, host.clone(), port)).abort_handle();
+        state.role = Role::Replica {
+            host,
+            port,
+            link: LinkState::Connect,
+            task,
+        };
+
+        let _ = self.feed.send(Feed::Reset);
+    }
+
+    pub fn promote(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Role::Replica { task, .. } = &state.role {
+            task.abort();
+            state.role = Role::Master;
+            state.replid = new_replid();
+        }
+    }
+
+    pub fn role(&self) -> Frame {
+        let state = self.state.lock().unwrap();
+
+        match &state.role {
+            Role::Master => Frame::Array(vec![
+                Frame::Bulk(Bytes::from_static(b"master")),
+                Frame::Integer(state.offset as i64),
+                Frame::Array(
+                    state
+                        .replicas
+                        .values()
+                        .map(|replica| {
+                            Frame::Array(vec![
+                                Frame::Bulk(Bytes::from(replica.ip().to_string())),
+                                Frame::Bulk(Bytes::from(replica.port.map(|port| port.to_string()).unwrap_or_default())),
+                                Frame::Bulk(Bytes::from(replica.ack.to_string())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ]),
+            Role::Replica { host, port, link, .. } => Frame::Array(vec![
+                Frame::Bulk(Bytes::from_static(b"slave")),
+                Frame::Bulk(Bytes::from(host.clone())),
+                Frame::Integer(*port as i64),
+                Frame::Bulk(Bytes::from_static(link.name().as_bytes())),
+                Frame::Integer(state.offset as i64),
+            ]),
+        }
+    }
+
+    pub fn info(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut info = String::from("# Replication\r\n");
+
+        match &state.role {
+            Role::Master => {
+                info.push_str("role:master\r\n");
+                info.push_str(&format!("connected_slaves:{}\r\n", state.replicas.len()));
+
+                for (i, replica) in state.replicas.values().enumerate() {
+                    info.push_str(&format!(
+                        "slave{}:ip={},port={},state=online,offset={},lag={}\r\n",
+                        i,
+                        replica.ip(),
+                        replica.port.map(|port| port.to_string()).unwrap_or_default(),
+                        replica.ack,
+                        replica.last_ack.elapsed().as_secs()
+                    ));
+                }
+            }
+            Role::Replica { host, port, link, .. } => {
+                info.push_str("role:slave\r\n");
+                info.push_str(&format!("master_host:{}\r\nmaster_port:{}\r\n", host, port));
+                info.push_str(&format!("master_link_status:{}\r\n", if *link == LinkState::Connected { "up" } else { "down" }));
+                info.push_str(&format!("master_sync_in_progress:{}\r\n", (*link == LinkState::Sync) as u8));
+                info.push_str(&format!("slave_repl_offset:{}\r\n", state.offset));
+                info.push_str("slave_read_only:1\r\n");
+            }
+        }
+
+        info.push_str(&format!("master_replid:{}\r\n", state.replid));
+        info.push_str(&format!("master_repl_offset:{}\r\n", state.offset));
+        info.push_str("repl_backlog_active:1\r\n");
+        info.push_str(&format!("repl_backlog_size:{}\r\n", BACKLOG_SIZE));
+        info.push_str(&format!("repl_backlog_first_byte_offset:{}\r\n", state.backlog_start));
+        info.push_str(&format!("repl_backlog_histlen:{}\r\n", state.backlog.len()));
+
+        info
+    }
+
+    fn set_link(&self, link_state: LinkState) {
+        if let Role::Replica { link, .. } = &mut self.state.lock().unwrap().role {
+            *link = link_state;
+        }
+    }
+
+    fn position(&self) -> (String, u64) {
+        let state = self.state.lock().unwrap();
+        (state.replid.clone(), state.offset)
+    }
+
+    fn resynced(&self, replid: String, offset: u64) {
+        let mut state = self.state.lock().unwrap();
+
+        state.replid = replid;
+        state.offset = offset;
+        state.backlog.clear();
+        state.backlog_start = offset;
+        state.master_db = 0;
+    }
+
+    fn master_db(&self) -> usize { self.state.lock().unwrap().master_db }
+
+    fn set_master_db(&self, db: usize) { self.state.lock().unwrap().master_db = db; }
+
+    fn masterauth(&self) -> MasterAuth { self.state.lock().unwrap().masterauth.clone() }
+
+    fn master_tls(&self) -> Option<MasterTls> { self.state.lock().unwrap().master_tls.clone() }
+
+    fn listening_port(&self) -> u16 { self.state.lock().unwrap().listening_port }
+}
+
+impl Default for Replication {
+    fn default() -> Self { Self::new() }
+}
+
+impl State {
+    fn append(&mut self, bytes: &[u8]) {
+        self.backlog.extend_from_slice(bytes);
+        self.offset += bytes.len() as u64;
+
+        if self.backlog.len() > BACKLOG_SIZE {
+            let excess = self.backlog.len() - BACKLOG_SIZE;
+            let _ = self.backlog.split_to(excess);
+            self.backlog_start += excess as u64;
+        }
+    }
+}
+
+impl fmt::Debug for MasterTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.debug_struct("MasterTls").field("server_name", &self.server_name).finish_non_exhaustive() }
+}
+
+impl ReplicaInfo {
+    fn ip(&self) -> &str { self.addr.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(&self.addr) }
+}
+
+impl LinkState {
+    fn name(&self) -> &'static str {
+        match self {
+            LinkState::Connect => "connect",
+            LinkState::Connecting => "connecting",
+            LinkState::Sync => "sync",
+            LinkState::Connected => "connected",
+        }
+    }
+}
+
+async fn replicate(db: Db, host: String, port: u16) {
+    loop {
+        db.replication().set_link(LinkState::Connecting);
+
+        match sync_with_master(&db, &host, port).await {
+            Ok(()) => info!("replication link to {}:{} closed", host, port),
+            Err(err) => error!(cause = %err, "replication link to {}:{} failed", host, port),
+        }
+
+        db.replication().set_link(LinkState::Connect);
+        time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn sync_with_master(db: &Db, host: &str, port: u16) -> crate::Result<()> {
+    let replication = db.replication();
+    let socket = TcpStream::connect((host, port)).await?;
+
+    let mut master = match replication.master_tls() {
+        Some(tls) => {
+            let server_name = ServerName::try_from(tls.server_name.unwrap_or_else(|| host.to_string()))?;
+            Connection::new(tls.connector.connect(server_name, socket).await?)
+        }
+        None => Connection::new(socket),
+    };
+
+    let auth = replication.masterauth();
+    if let Some(password) = auth.password {
+        master.write_frame(&Auth::new(auth.username, password).into_frame()).await?;
+        expect_ok(&mut master).await?;
+    }
+
+    let mut replconf = Frame::array();
+    replconf.push_bulk(Bytes::from_static(b"replconf"));
+    replconf.push_bulk(Bytes::from_static(b"listening-port"));
+    replconf.push_bulk(Bytes::from(replication.listening_port().to_string()));
+    master.write_frame(&replconf).await?;
+    expect_ok(&mut master).await?;
+
+    let (replid, offset) = replication.position();
+    let mut psync = Frame::array();
+    psync.push_bulk(Bytes::from_static(b"psync"));
+    psync.push_bulk(Bytes::from(replid));
+    psync.push_bulk(Bytes::from(offset.to_string()));
+    master.write_frame(&psync).await?;
+
+    match master.read_frame().await? {
+        Some(Frame::Simple(line)) if line.starts_with("FULLRESYNC") => {
+            let mut parts = line.split_whitespace().skip(1);
+            let replid = parts.next().ok_or("malformed FULLRESYNC reply")?.to_string();
+            let offset = parts.next().and_then(|offset| offset.parse().ok()).ok_or("malformed FULLRESYNC reply")?;
+
+            replication.set_link(LinkState::Sync);
+
+            let snapshot = match master.read_frame().await? {
+                Some(Frame::Bulk(snapshot)) => snapshot,
+                frame => return Err(format!("expected snapshot from master, got {:?}", frame).into()),
+            };
+
+            let state: SerializableState = bincode::deserialize(&snapshot)?;
+            db.load(state);
+            replication.resynced(replid, offset);
+
+            info!("full resync with master {}:{} complete", host, port);
+        }
+        Some(Frame::Simple(line)) if line.starts_with("CONTINUE") => info!("partial resync with master {}:{} accepted", host, port),
+        Some(frame) => return Err(format!("unexpected PSYNC reply: {}", frame).into()),
+        None => return Ok(()),
+    }
+
+    replication.set_link(LinkState::Connected);
+    stream_from_master(db, &mut master).await
+}
+
+async fn stream_from_master(db: &Db, master: &mut Connection) -> crate::Result<()> {
+    let mut sink = Connection::discard();
+    let mut session = Session::new(DEFAULT_USER.to_string(), true);
+    session.select(db.replication().master_db());
+    let (_notify, rx) = broadcast::channel(1);
+    let mut shutdown = super::shutdown::Shutdown::new(rx);
+    let mut ack = time::interval(ACK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            res = master.read_frame() => {
+                let frame = match res? {
+                    Some(frame) => frame,
+                    None => return Ok(()),
+                };
+
+                let replicated = frame.clone();
+                let _ordering = db.replication().write_lock().await;
+
+                match Command::from_frame(frame) {
+                    Ok(cmd) => cmd.apply(db, &mut sink, &mut session, &mut shutdown).await?,
+                    Err(err) => warn!(cause = %err, "ignoring malformed command from master"),
+                }
+
+                db.replication().set_master_db(session.db());
+                db.replication().relay(&replicated);
+            }
+            _ = ack.tick() => {
+                let (_, offset) = db.replication().position();
+
+                let mut frame = Frame::array();
+                frame.push_bulk(Bytes::from_static(b"replconf"));
+                frame.push_bulk(Bytes::from_static(b"ack"));
+                frame.push_bulk(Bytes::from(offset.to_string()));
+                master.write_frame(&frame).await?;
+            }
+        }
+    }
+}
+
+async fn expect_ok(master: &mut Connection) -> crate::Result<()> {
+    match master.read_frame().await? {
+        Some(Frame::Simple(response)) if response == "OK" => Ok(()),
+        Some(frame) => Err(frame.to_error()),
+        None => Err("master closed the connection".into()),
+    }
+}
+
+fn new_replid() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let seed = format!("{}:{}:{:?}", nanos, std::process::id(), std::thread::current().id());
+
+    Sha256::digest(seed.as_bytes())[..20].iter().map(|b| format!("{b:02x}")).collect()
+}
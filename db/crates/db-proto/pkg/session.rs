@@ -0,0 +1,53 @@
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    user: String,
+    authenticated: bool,
+    addr: String,
+    listening_port: Option<u16>,
+    asking: bool,
+    db: usize,
+}
+
+impl Session {
+    pub fn new(user: String, authenticated: bool) -> Session {
+        Session {
+            id: 0,
+            user,
+            authenticated,
+            addr: String::new(),
+            listening_port: None,
+            asking: false,
+            db: 0,
+        }
+    }
+
+    pub fn id(&self) -> u64 { self.id }
+
+    pub fn set_id(&mut self, id: u64) { self.id = id; }
+
+    pub fn addr(&self) -> &str { &self.addr }
+
+    pub fn set_addr(&mut self, addr: String) { self.addr = addr; }
+
+    pub fn listening_port(&self) -> Option<u16> { self.listening_port }
+
+    pub fn set_listening_port(&mut self, port: u16) { self.listening_port = Some(port); }
+
+    pub fn set_asking(&mut self) { self.asking = true; }
+
+    pub fn take_asking(&mut self) -> bool { std::mem::take(&mut self.asking) }
+
+    pub fn db(&self) -> usize { self.db }
+
+    pub fn select(&mut self, db: usize) { self.db = db; }
+
+    pub fn user(&self) -> &str { &self.user }
+
+    pub fn is_authenticated(&self) -> bool { self.authenticated }
+
+    pub fn login(&mut self, user: String) {
+        self.user = user;
+        self.authenticated = true;
+    }
+}
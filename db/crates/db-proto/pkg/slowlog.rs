@@ -0,0 +1,148 @@
+use super::stats::unix_time;
+use super::Frame;
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_LOG_SLOWER_THAN: i64 = 10_000;
+pub const DEFAULT_MAX_LEN: usize = 128;
+
+const MAX_ARGS: usize = 32;
+const MAX_ARG_LEN: usize = 128;
+
+const REDACTED: &str = "(redacted)";
+const SECRET_PARAMS: &[&str] = &["requirepass", "masterauth"];
+
+#[derive(Debug)]
+pub struct SlowLog {
+    entries: Mutex<VecDeque<Entry>>,
+    next_id: AtomicU64,
+    log_slower_than: AtomicI64,
+    max_len: AtomicUsize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub duration: Duration,
+    pub args: Vec<Bytes>,
+    pub addr: String,
+    pub name: String,
+}
+
+impl SlowLog {
+    pub fn new() -> SlowLog {
+        SlowLog {
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+            log_slower_than: AtomicI64::new(DEFAULT_LOG_SLOWER_THAN),
+            max_len: AtomicUsize::new(DEFAULT_MAX_LEN),
+        }
+    }
+
+    pub fn log_slower_than(&self) -> i64 { self.log_slower_than.load(Ordering::Relaxed) }
+
+    pub fn set_log_slower_than(&self, micros: i64) { self.log_slower_than.store(micros, Ordering::Relaxed); }
+
+    pub fn max_len(&self) -> usize { self.max_len.load(Ordering::Relaxed) }
+
+    pub fn set_max_len(&self, max_len: usize) {
+        self.max_len.store(max_len, Ordering::Relaxed);
+        self.entries.lock().unwrap().truncate(max_len);
+    }
+
+    pub fn record(&self, frame: &Frame, duration: Duration, addr: &str, name: &str) {
+        let threshold = self.log_slower_than();
+
+        if threshold < 0 || (duration.as_micros() as i64) < threshold {
+            return;
+        }
+
+        let mut args = match frame {
+            Frame::Array(parts) => parts.iter().map(truncate).collect::<Vec<_>>(),
+            frame => vec![truncate(frame)],
+        };
+
+        redact(&mut args);
+
+        let args = match args.len() > MAX_ARGS {
+            true => args[..MAX_ARGS - 1].iter().cloned().chain([Bytes::from(format!("... ({} more arguments)", args.len() - MAX_ARGS + 1))]).collect(),
+            false => args,
+        };
+
+        let entry = Entry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: unix_time().as_secs(),
+            duration,
+            args,
+            addr: addr.to_string(),
+            name: name.to_string(),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_front(entry);
+        entries.truncate(self.max_len());
+    }
+
+    pub fn get(&self, count: usize) -> Vec<Entry> { self.entries.lock().unwrap().iter().take(count).cloned().collect() }
+
+    pub fn len(&self) -> usize { self.entries.lock().unwrap().len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub fn reset(&self) { self.entries.lock().unwrap().clear(); }
+}
+
+impl Default for SlowLog {
+    fn default() -> Self { Self::new() }
+}
+
+impl Entry {
+    pub fn to_frame(&self) -> Frame {
+        Frame::Array(vec![
+            Frame::Integer(self.id as i64),
+            Frame::Integer(self.timestamp as i64),
+            Frame::Integer(self.duration.as_micros() as i64),
+            Frame::Array(self.args.iter().cloned().map(Frame::Bulk).collect()),
+            Frame::Bulk(Bytes::from(self.addr.clone())),
+            Frame::Bulk(Bytes::from(self.name.clone())),
+        ])
+    }
+}
+
+/// Copies the argument so the entry does not keep the request's read buffer
+/// alive.
+fn truncate(frame: &Frame) -> Bytes {
+    let arg = match frame {
+        Frame::Bulk(bytes) => bytes.clone(),
+        frame => Bytes::from(frame.to_string()),
+    };
+
+    match arg.len() > MAX_ARG_LEN {
+        true => Bytes::from([&arg[..MAX_ARG_LEN], format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN).as_bytes()].concat()),
+        false => Bytes::copy_from_slice(&arg),
+    }
+}
+
+/// Replaces passwords with a placeholder: everything after `AUTH`, the rules
+/// of `ACL SETUSER` and the values of secret `CONFIG SET` parameters.
+fn redact(args: &mut [Bytes]) {
+    let lower = |arg: &Bytes| String::from_utf8_lossy(arg).to_lowercase();
+    let name = args.first().map(lower).unwrap_or_default();
+    let sub = args.get(1).map(lower).unwrap_or_default();
+
+    let secrets: Vec<usize> = match (name.as_str(), sub.as_str()) {
+        ("auth", _) => (1..args.len()).collect(),
+        ("acl", "setuser") => (3..args.len()).collect(),
+        ("config", "set") => (3..args.len()).step_by(2).filter(|&i| SECRET_PARAMS.contains(&lower(&args[i - 1]).as_str())).collect(),
+        _ => vec![],
+    };
+
+    for i in secrets {
+        args[i] = Bytes::from_static(REDACTED.as_bytes());
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub struct Stats {
+    started: Instant,
+    port: AtomicUsize,
+    connected_clients: AtomicUsize,
+    total_connections_received: AtomicU64,
+    total_commands_processed: AtomicU64,
+    ops_second: AtomicU64,
+    ops_current: AtomicU64,
+    ops_previous: AtomicU64,
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    expired_keys: AtomicU64,
+    dirty: AtomicU64,
+    last_save: AtomicU64,
+    last_save_ok: AtomicUsize,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats {
+            started: Instant::now(),
+            port: AtomicUsize::new(0),
+            connected_clients: AtomicUsize::new(0),
+            total_connections_received: AtomicU64::new(0),
+            total_commands_processed: AtomicU64::new(0),
+            ops_second: AtomicU64::new(unix_time().as_secs()),
+            ops_current: AtomicU64::new(0),
+            ops_previous: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            dirty: AtomicU64::new(0),
+            last_save: AtomicU64::new(unix_time().as_secs()),
+            last_save_ok: AtomicUsize::new(1),
+        }
+    }
+
+    pub fn set_port(&self, port: u16) { self.port.store(port as usize, Ordering::Relaxed); }
+
+    pub fn connected_clients(&self) -> usize { self.connected_clients.load(Ordering::Relaxed) }
+
+    pub fn total_connections_received(&self) -> u64 { self.total_connections_received.load(Ordering::Relaxed) }
+
+    pub fn client_connected(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+        self.total_connections_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) { self.connected_clients.fetch_sub(1, Ordering::Relaxed); }
+
+    pub fn command_processed(&self) {
+        let now = unix_time().as_secs();
+        let second = self.ops_second.load(Ordering::Relaxed);
+
+        if now != second && self.ops_second.compare_exchange(second, now, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            let count = self.ops_current.swap(0, Ordering::Relaxed);
+            self.ops_previous.store(if now == second + 1 { count } else { 0 }, Ordering::Relaxed);
+        }
+
+        self.ops_current.fetch_add(1, Ordering::Relaxed);
+        self.total_commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ops_per_sec(&self) -> u64 {
+        match unix_time().as_secs().saturating_sub(self.ops_second.load(Ordering::Relaxed)) {
+            0 => self.ops_previous.load(Ordering::Relaxed),
+            1 => self.ops_current.load(Ordering::Relaxed),
+            _ => 0,
+        }
+    }
+
+    pub fn keyspace_hit(&self) { self.keyspace_hits.fetch_add(1, Ordering::Relaxed); }
+
+    pub fn keyspace_miss(&self) { self.keyspace_misses.fetch_add(1, Ordering::Relaxed); }
+
+    pub fn key_expired(&self) { self.expired_keys.fetch_add(1, Ordering::Relaxed); }
+
+    pub fn expired_keys(&self) -> u64 { self.expired_keys.load(Ordering::Relaxed) }
+
+    pub fn key_changed(&self) { self.dirty.fetch_add(1, Ordering::Relaxed); }
+
+    pub fn saved(&self, ok: bool) {
+        self.last_save_ok.store(ok as usize, Ordering::Relaxed);
+
+        if ok {
+            self.dirty.store(0, Ordering::Relaxed);
+            self.last_save.store(unix_time().as_secs(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn last_save(&self) -> u64 { self.last_save.load(Ordering::Relaxed) }
+
+    pub fn changes_since_last_save(&self) -> u64 { self.dirty.load(Ordering::Relaxed) }
+
+    pub fn uptime(&self) -> u64 { self.started.elapsed().as_secs() }
+
+    pub fn server_info(&self) -> String {
+        let uptime = self.uptime();
+
+        format!(
+            "# Server\r\ndb_version:{}\r\nos:{} {}\r\nprocess_id:{}\r\ntcp_port:{}\r\nuptime_in_seconds:{}\r\nuptime_in_days:{}\r\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            std::process::id(),
+            self.port.load(Ordering::Relaxed),
+            uptime,
+            uptime / 86400
+        )
+    }
+
+    pub fn clients_info(&self, maxclients: usize) -> String { format!("# Clients\r\nconnected_clients:{}\r\nmaxclients:{}\r\n", self.connected_clients(), maxclients) }
+
+    pub fn persistence_info(&self) -> String {
+        format!(
+            "# Persistence\r\nloading:0\r\nrdb_changes_since_last_save:{}\r\nrdb_last_save_time:{}\r\nrdb_last_bgsave_status:{}\r\n",
+            self.changes_since_last_save(),
+            self.last_save(),
+            if self.last_save_ok.load(Ordering::Relaxed) == 1 { "ok" } else { "err" }
+        )
+    }
+
+    pub fn stats_info(&self, evicted_keys: u64) -> String {
+        format!(
+            "# Stats\r\ntotal_connections_received:{}\r\ntotal_commands_processed:{}\r\ninstantaneous_ops_per_sec:{}\r\nexpired_keys:{}\r\nevicted_keys:{}\r\nkeyspace_hits:{}\r\nkeyspace_misses:{}\r\n",
+            self.total_connections_received(),
+            self.total_commands_processed.load(Ordering::Relaxed),
+            self.ops_per_sec(),
+            self.expired_keys(),
+            evicted_keys,
+            self.keyspace_hits.load(Ordering::Relaxed),
+            self.keyspace_misses.load(Ordering::Relaxed)
+        )
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self { Self::new() }
+}
+
+pub fn unix_time() -> std::time::Duration { SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() }
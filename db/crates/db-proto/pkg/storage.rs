@@ -0,0 +1,53 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+pub const DEFAULT_DBFILENAME: &str = "db-state.bin";
+
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    dir: PathBuf,
+    dbfilename: String,
+}
+
+impl DataDir {
+    pub fn new(dir: impl Into<PathBuf>, dbfilename: impl ToString) -> DataDir {
+        DataDir {
+            dir: dir.into(),
+            dbfilename: dbfilename.to_string(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    pub fn dbfilename(&self) -> &str { &self.dbfilename }
+
+    pub fn resolve(&self, name: Option<&Path>) -> crate::Result<PathBuf> {
+        let name = name.unwrap_or(Path::new(&self.dbfilename));
+
+        if name.as_os_str().is_empty() || !name.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(format!("invalid snapshot path {:?}; must be a relative path inside the data directory", name).into());
+        }
+
+        let dir = self.dir.canonicalize()?;
+        let path = dir.join(name);
+
+        let resolved = match path.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let parent = path.parent().ok_or_else(|| Error::from(ErrorKind::NotFound))?.canonicalize()?;
+                parent.join(path.file_name().ok_or_else(|| Error::from(ErrorKind::NotFound))?)
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if !resolved.starts_with(&dir) {
+            return Err(format!("snapshot path {:?} escapes the data directory", name).into());
+        }
+
+        Ok(resolved)
+    }
+}
+
+impl Default for DataDir {
+    fn default() -> Self { DataDir::new(".", DEFAULT_DBFILENAME) }
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+pub use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+#[derive(Debug)]
+pub struct TlsConfig {
+    roots: RootCertStore,
+    identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+}
+
+impl TlsConfig {
+    pub fn new() -> TlsConfig {
+        TlsConfig {
+            roots: RootCertStore::empty(),
+            identity: None,
+        }
+    }
+
+    pub fn add_ca_file(self, path: &Path) -> crate::Result<TlsConfig> { self.add_ca_pem(&std::fs::read(path)?) }
+
+    pub fn add_ca_pem(mut self, pem: &[u8]) -> crate::Result<TlsConfig> {
+        for cert in read_certs(pem)? {
+            self.roots.add(cert)?;
+        }
+
+        Ok(self)
+    }
+
+    pub fn with_client_cert(mut self, cert_pem: &[u8], key_pem: &[u8]) -> crate::Result<TlsConfig> {
+        self.identity = Some((read_certs(cert_pem)?, read_key(key_pem)?));
+        Ok(self)
+    }
+
+    pub fn connector(&self) -> crate::Result<TlsConnector> {
+        let builder = ClientConfig::builder().with_root_certificates(self.roots.clone());
+
+        let config = match &self.identity {
+            Some((certs, key)) => builder.with_client_auth_cert(certs.clone(), key.clone_key())?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self { Self::new() }
+}
+
+pub fn acceptor(cert_pem: &[u8], key_pem: &[u8], client_ca_pem: Option<&[u8]>) -> crate::Result<TlsAcceptor> {
+    let builder = match client_ca_pem {
+        Some(pem) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(pem)? {
+                roots.add(cert)?;
+            }
+
+            ServerConfig::builder().with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build()?)
+        }
+        None => ServerConfig::builder().with_no_client_auth(),
+    };
+
+    let config = builder.with_single_cert(read_certs(cert_pem)?, read_key(key_pem)?)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+pub fn read_certs(mut pem: &[u8]) -> crate::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut pem).collect::<Result<Vec<_>, _>>()?;
+
+    if certs.is_empty() {
+        return Err("no certificates found in PEM input".into());
+    }
+
+    Ok(certs)
+}
+
+pub fn read_key(mut pem: &[u8]) -> crate::Result<PrivateKeyDer<'static>> { rustls_pemfile::private_key(&mut pem)?.ok_or_else(|| "no private key found in PEM input".into()) }
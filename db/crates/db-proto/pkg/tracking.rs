@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Channel a redirect target subscribes to for invalidation messages.
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+const LISTENER_CAPACITY: usize = 1024;
+
+/// Server side of `CLIENT TRACKING`. Keys read by a tracking client are
+/// remembered until they next change, at which point the key is sent to the
+/// client's redirect target and forgotten until it is read again.
+///
+/// Keys are tracked by name alone, so a write in one database invalidates
+/// the same key read from another.
+#[derive(Debug, Default)]
+pub struct Tracking {
+    state: Mutex<State>,
+    clients: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    keys: HashMap<String, HashSet<u64>>,
+    /// Reverse of `keys`, so a client's keys are dropped when it stops tracking
+    tracked: HashMap<u64, HashSet<String>>,
+    /// Tracking client id to the id of the client its invalidations go to
+    redirects: HashMap<u64, u64>,
+    /// Redirect targets subscribed to `INVALIDATE_CHANNEL`; `None` asks the
+    /// target to drop everything it has cached.
+    listeners: HashMap<u64, broadcast::Sender<Option<String>>>,
+}
+
+impl Tracking {
+    pub fn new() -> Tracking { Tracking::default() }
+
+    pub fn enable(&self, client: u64, redirect: u64) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.redirects.insert(client, redirect).is_none() {
+            self.clients.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Stops tracking for `client` and drops the keys it read.
+    pub fn disable(&self, client: u64) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.redirects.remove(&client).is_some() {
+            self.clients.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        for key in state.tracked.remove(&client).unwrap_or_default() {
+            if let Some(clients) = state.keys.get_mut(&key) {
+                clients.remove(&client);
+
+                if clients.is_empty() {
+                    state.keys.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn redirect(&self, client: u64) -> Option<u64> { self.state.lock().unwrap().redirects.get(&client).copied() }
+
+    /// Called when a connection closes, whether it was tracking or listening.
+    pub fn forget(&self, client: u64) {
+        self.disable(client);
+        self.state.lock().unwrap().listeners.remove(&client);
+    }
+
+    pub fn listen(&self, client: u64) -> broadcast::Receiver<Option<String>> {
+        let mut state = self.state.lock().unwrap();
+        state.listeners.entry(client).or_insert_with(|| broadcast::channel(LISTENER_CAPACITY).0).subscribe()
+    }
+
+    /// Must be called before the keys are read, so a write racing with the
+    /// read is either seen by it or invalidates it.
+    pub fn track(&self, client: u64, keys: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+
+        if !state.redirects.contains_key(&client) {
+            return;
+        }
+
+        for key in keys {
+            state.keys.entry(key.to_string()).or_default().insert(client);
+            state.tracked.entry(client).or_default().insert(key.to_string());
+        }
+    }
+
+    /// Number of keys with at least one client waiting for their invalidation.
+    pub fn tracked_keys(&self) -> usize { self.state.lock().unwrap().keys.len() }
+
+    pub fn is_tracking(&self, client: u64) -> bool { self.clients.load(Ordering::Relaxed) > 0 && self.state.lock().unwrap().redirects.contains_key(&client) }
+
+    /// Called after `key` was modified or removed.
+    pub fn invalidate(&self, key: &str) {
+        if self.clients.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        let Some(clients) = state.keys.remove(key) else {
+            return;
+        };
+
+        for client in clients {
+            if let Some(tracked) = state.tracked.get_mut(&client) {
+                tracked.remove(key);
+            }
+
+            if let Some(tx) = state.redirects.get(&client).and_then(|target| state.listeners.get(target)) {
+                let _ = tx.send(Some(key.to_string()));
+            }
+        }
+    }
+
+    /// Called after a whole database was flushed, loaded or swapped.
+    pub fn invalidate_all(&self) {
+        if self.clients.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.keys.clear();
+        state.tracked.clear();
+
+        let targets: HashSet<u64> = state.redirects.values().copied().collect();
+
+        for target in targets {
+            if let Some(tx) = state.listeners.get(&target) {
+                let _ = tx.send(None);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use db_proto::pkg::metrics::LATENCY_BUCKETS;
+use db_proto::{prelude::*, Result};
+use std::fmt::Write;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{self, Duration};
+use tracing::{debug, info, warn};
+
+const MAX_REQUEST: usize = 8192;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(64);
+
+/// Never returns: accept errors such as running out of file descriptors are
+/// logged and retried with backoff, so metrics can't take the server down.
+pub(crate) async fn serve(listener: TcpListener, db: Db) {
+    info!(addr = ?listener.local_addr().ok(), "serving metrics");
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(err) => {
+                warn!(cause = %err, ?backoff, "failed to accept metrics connection");
+                time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+
+        backoff = MIN_BACKOFF;
+        let db = db.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = respond(socket, &db).await {
+                debug!(cause = ?err, "metrics request failed");
+            }
+        });
+    }
+}
+
+async fn respond(mut socket: TcpStream, db: &Db) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = socket.read(&mut buf).await?;
+
+        if n == 0 || request.len() + n > MAX_REQUEST {
+            return Err("incomplete metrics request".into());
+        }
+
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(db)),
+        (Some("GET"), Some(_)) => ("404 Not Found", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+
+    Ok(())
+}
+
+fn render(db: &Db) -> String {
+    let mut out = String::new();
+    let stats = db.stats();
+    let commands = db.metrics().commands();
+    let keyspaces: Vec<Db> = (0..db.databases()).map(|index| db.select(index)).collect();
+
+    let gauges = [
+        ("db_connected_clients", "gauge", "Number of client connections", stats.connected_clients() as u64),
+        ("db_max_clients", "gauge", "Maximum number of client connections", db.config().maxclients() as u64),
+        ("db_connections_received_total", "counter", "Total number of connections accepted", stats.total_connections_received()),
+        ("db_keys", "gauge", "Number of keys in the keyspace", keyspaces.iter().map(Db::len).sum::<usize>() as u64),
+        ("db_expiring_keys", "gauge", "Number of keys with an expiration", keyspaces.iter().map(Db::expiring).sum::<usize>() as u64),
+        ("db_expired_keys_total", "counter", "Total number of keys removed by expiration", stats.expired_keys()),
+        ("db_evicted_keys_total", "counter", "Total number of keys evicted due to maxmemory", db.memory().evicted()),
+        ("db_used_memory_bytes", "gauge", "Memory used by keys and values", db.memory().used() as u64),
+        ("db_pubsub_channels", "gauge", "Number of pub/sub channels with at least one subscriber", db.pubsub_channels() as u64),
+    ];
+
+    for (name, kind, help, value) in gauges {
+        let _ = write!(out, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n");
+    }
+
+    out.push_str("# HELP db_commands_total Total number of commands processed\n# TYPE db_commands_total counter\n");
+    for (name, command) in &commands {
+        let _ = writeln!(out, "db_commands_total{{cmd=\"{}\"}} {}", name, command.calls);
+    }
+
+    out.push_str("# HELP db_command_duration_seconds Command execution latency\n# TYPE db_command_duration_seconds histogram\n");
+    for (name, command) in &commands {
+        let mut cumulative = 0;
+
+        for (le, count) in LATENCY_BUCKETS.iter().zip(&command.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "db_command_duration_seconds_bucket{{cmd=\"{}\",le=\"{}\"}} {}", name, le, cumulative);
+        }
+
+        let _ = writeln!(out, "db_command_duration_seconds_bucket{{cmd=\"{}\",le=\"+Inf\"}} {}", name, command.calls);
+        let _ = writeln!(out, "db_command_duration_seconds_sum{{cmd=\"{}\"}} {}", name, command.total.as_secs_f64());
+        let _ = writeln!(out, "db_command_duration_seconds_count{{cmd=\"{}\"}} {}", name, command.calls);
+    }
+
+    out
+}
@@ -1,111 +1,575 @@
-use db_proto::{prelude::*, Result};
-use std::future::Future;
-use std::path::PathBuf;
+mod metrics;
+
+use db_proto::pkg::cluster::Topology;
+use db_proto::pkg::config::{self, SaveRule, DEFAULT_SHUTDOWN_TIMEOUT, DEFAULT_TCP_KEEPALIVE};
+use db_proto::pkg::db::DEFAULT_DATABASES;
+use db_proto::pkg::memory::{Policy, DEFAULT_SAMPLES};
+use db_proto::pkg::frame::DEFAULT_MAX_BULK_LEN;
+use db_proto::pkg::registry::{Client, Filter};
+use db_proto::pkg::replication::{MasterAuth, MasterTls};
+use db_proto::pkg::slowlog;
+use db_proto::pkg::stats;
+use db_proto::pkg::storage::{DataDir, DEFAULT_DBFILENAME};
+use db_proto::pkg::tls::{self, TlsAcceptor, TlsConfig};
+use db_proto::pkg::Stream;
+use db_proto::{prelude::*, Error, Result};
+use std::future::{self, Future};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use socket2::{SockRef, TcpKeepalive};
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpListener, UnixListener};
 use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
 
 pub use db_proto::DEFAULT_PORT;
 pub const MAX_CONNECTIONS: usize = 250;
 
+/// How long a TLS client gets to complete its handshake before the connection
+/// is dropped.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
-struct Listener {
-    db_holder: DbDropGuard,
-    listener: TcpListener,
+pub struct Config {
+    pub bind: String,
+    pub port: u16,
+    pub config_file: Option<PathBuf>,
+    pub dir: PathBuf,
+    pub dbfilename: Option<String>,
+    pub requirepass: Option<String>,
+    pub tls: Option<TlsFiles>,
+    pub unixsocket: Option<PathBuf>,
+    pub unixsocketperm: Option<u32>,
+    pub replicaof: Option<(String, u16)>,
+    pub masteruser: Option<String>,
+    pub masterauth: Option<String>,
+    pub master_tls: Option<MasterTlsFiles>,
+    pub cluster_config: Option<PathBuf>,
+    pub cluster_node_id: Option<String>,
+    pub maxmemory: usize,
+    pub maxmemory_policy: Policy,
+    pub maxmemory_samples: usize,
+    pub metrics_addr: Option<String>,
+    pub slowlog_log_slower_than: i64,
+    pub slowlog_max_len: usize,
+    pub maxclients: usize,
+    pub timeout: Duration,
+    pub save: Vec<SaveRule>,
+    pub databases: usize,
+    pub tcp_keepalive: Duration,
+    pub shutdown_timeout: Duration,
+    pub proto_max_bulk_len: usize,
+    /// Runtime settings from the config file and command line, applied in
+    /// order on top of the fields above with the setters CONFIG SET uses.
+    pub params: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    pub ca_cert_file: Option<PathBuf>,
+}
+
+/// Lets a replica reach a TLS master: the CA bundle the master's certificate
+/// must chain to, an optional client certificate, and the name to verify.
+#[derive(Debug, Clone)]
+pub struct MasterTlsFiles {
+    pub ca_cert_file: PathBuf,
+    pub cert_file: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+    pub server_name: Option<String>,
+}
+
+struct Listener<L: Accept> {
+    db: Db,
+    listener: L,
     limit_connections: Arc<Semaphore>,
     notify_shutdown: broadcast::Sender<()>,
     shutdown_complete_tx: mpsc::Sender<()>,
-    db_path: Option<PathBuf>,
+    tls: Option<TlsAcceptor>,
+}
+
+trait Accept {
+    type Stream: Stream + 'static;
+
+    async fn accept(&mut self) -> io::Result<(Self::Stream, String)>;
+
+    fn keepalive(_socket: &Self::Stream, _interval: Duration) -> io::Result<()> { Ok(()) }
+
+    fn nodelay(_socket: &Self::Stream) -> io::Result<()> { Ok(()) }
 }
 
 #[derive(Debug)]
 struct Handler {
     db: Db,
     connection: Connection,
+    session: Session,
+    client: Arc<Client>,
     shutdown: Shutdown,
     _shutdown_complete: mpsc::Sender<()>,
 }
 
-pub async fn run(listener: TcpListener, shutdown: impl Future, db_path: Option<PathBuf>) {
+pub async fn run(listener: TcpListener, shutdown: impl Future, config: Config) -> Result<()> {
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
-    let db_holder = DbDropGuard::new();
+    let db_holder = DbDropGuard::with_databases(config.databases);
+    let db = db_holder.db();
+
+    let settings = db.config();
+    settings.set_file(config.config_file.clone());
+    settings.set_requirepass(&db, config.requirepass.as_deref().unwrap_or_default());
+    settings.set_maxclients(config.maxclients);
+    settings.set_timeout(config.timeout);
+    settings.set_save(config.save.clone());
+    settings.set_tcp_keepalive(config.tcp_keepalive);
+    settings.set_shutdown_timeout(config.shutdown_timeout);
+    settings.set_proto_max_bulk_len(config.proto_max_bulk_len);
 
-    if let Some(path) = &db_path {
+    let memory = db.memory();
+    memory.set_maxmemory(config.maxmemory);
+    memory.set_policy(config.maxmemory_policy);
+    memory.set_samples(config.maxmemory_samples);
+
+    db.slowlog().set_log_slower_than(config.slowlog_log_slower_than);
+    db.slowlog().set_max_len(config.slowlog_max_len);
+
+    for (name, value) in &config.params {
+        settings.apply(&db, name, value).map_err(|err| format!("invalid '{}' setting: {}", name, err))?;
+    }
+
+    std::fs::create_dir_all(&config.dir).expect("Failed to create data directory");
+    let data_dir = DataDir::new(&config.dir, config.dbfilename.as_deref().unwrap_or(DEFAULT_DBFILENAME));
+    let persist = config.dbfilename.is_some();
+    data_dir.resolve(None).expect("Invalid snapshot file");
+    db.set_data_dir(data_dir);
+
+    let local_addr = listener.local_addr().ok();
+    let port = local_addr.map(|addr| addr.port()).unwrap_or_default();
+    config.register_fixed(&db, local_addr.map(|addr| addr.ip().to_string()).unwrap_or(config.bind.clone()), port);
+
+    let tls = config.tls.map(|files| files.acceptor().expect("Failed to configure TLS"));
+
+    if let Some(path) = &config.cluster_config {
+        let topology = Topology::load(path, config.cluster_node_id.as_deref(), port).expect("Failed to load cluster config");
+        db.cluster().configure(topology);
+    }
+
+    db.stats().set_port(port);
+
+    let replication = db.replication();
+    replication.set_listening_port(port);
+    replication.set_masterauth(MasterAuth {
+        username: config.masteruser,
+        password: config.masterauth,
+    });
+    replication.set_master_tls(config.master_tls.map(|files| files.master_tls().expect("Failed to configure replication TLS")));
+
+    if persist {
+        let path = db.data_dir().resolve(None).expect("Invalid snapshot file");
         if path.exists() {
             info!("Loading database from {:?}", path);
-            db_holder.db().load_from(path).await.expect("Failed to load database");
+            db.load_from(&path).await.expect("Failed to load database");
         }
     }
 
+    if let Some((host, port)) = config.replicaof {
+        info!("replicating from {}:{}", host, port);
+        replication.replicate_from(db.clone(), host, port);
+    }
+
     let mut server = Listener {
-        db_path,
+        tls,
         listener,
-        db_holder,
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        db: db.clone(),
+        limit_connections: db.config().connections(),
         notify_shutdown,
         shutdown_complete_tx,
     };
 
+    let mut unix_server = config.unixsocket.as_deref().map(|path| {
+        let listener = bind_unix(path, config.unixsocketperm).expect("Failed to bind unix socket");
+        server.sibling(listener)
+    });
+
+    let metrics_listener = match &config.metrics_addr {
+        Some(addr) => Some(TcpListener::bind(addr).await.expect("Failed to bind metrics listener")),
+        None => None,
+    };
+
+    let metrics = metrics_listener.map(|listener| tokio::spawn(metrics::serve(listener, db.clone())));
+
+    let autosave = autosave(db.clone());
+
+    let unix_run = async {
+        match &mut unix_server {
+            Some(server) => server.run().await,
+            None => future::pending().await,
+        }
+    };
+
     tokio::select! {
         res = server.run() => {
             if let Err(err) = res {
                 error!(cause = %err, "failed to accept");
             }
         }
+        res = unix_run => {
+            if let Err(err) = res {
+                error!(cause = %err, "failed to accept on unix socket");
+            }
+        }
         _ = shutdown => {
             info!("shutting down");
-            if let Some(path) = &server.db_path {
-                info!("Saving database to {:?}", path);
-                server.db_holder.db().dump_to(path).await.expect("Failed to save database");
+        }
+    }
+
+    autosave.abort();
+
+    if let Some(metrics) = metrics {
+        metrics.abort();
+    }
+    drop(unix_server);
+    drop(server);
+
+    let deadline = db.config().shutdown_timeout();
+
+    if time::timeout(deadline, shutdown_complete_rx.recv()).await.is_err() {
+        warn!(timeout = ?deadline, "connections still busy after shutdown timeout, closing them");
+        db.clients().kill(&Filter::default());
+        let _ = shutdown_complete_rx.recv().await;
+    }
+
+    if persist {
+        let path = db.data_dir().resolve(None).expect("Invalid snapshot file");
+        info!("Saving database to {:?}", path);
+        db.dump_to(&path).await.expect("Failed to save database");
+    }
+
+    if let Some(path) = &config.unixsocket {
+        let _ = std::fs::remove_file(path);
+    }
+
+    Ok(())
+}
+
+fn autosave(db: Db) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+
+            let stats = db.stats();
+            let changes = stats.changes_since_last_save();
+            let elapsed = Duration::from_secs(stats::unix_time().as_secs().saturating_sub(stats.last_save()));
+
+            if changes == 0 || !db.config().save_due(changes, elapsed) {
+                continue;
+            }
+
+            match db.data_dir().resolve(None) {
+                Ok(path) => {
+                    info!(changes, "Saving database to {:?}", path);
+                    if let Err(err) = db.dump_to(&path).await {
+                        error!(cause = %err, "background save failed");
+                    }
+                }
+                Err(err) => error!(cause = %err, "background save failed"),
             }
         }
+    })
+}
+
+/// Binds inside a private directory and renames the socket into place, so it
+/// is never reachable with looser permissions than `perm`. Only a stale
+/// socket is replaced; any other file at `path` is left alone.
+fn bind_unix(path: &Path, perm: Option<u32>) -> io::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and is not a socket", path.display()))),
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        Err(_) => {}
     }
 
-    let Listener {
-        shutdown_complete_tx,
-        notify_shutdown,
-        ..
-    } = server;
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unix socket path has no file name"))?;
+    let private = path.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+
+    let staged = private.join(name);
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        if let Some(mode) = perm {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        }
 
-    drop(notify_shutdown);
-    drop(shutdown_complete_tx);
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
 
-    let _ = shutdown_complete_rx.recv().await;
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&private);
+    bound
 }
 
-impl Listener {
+impl Config {
+    /// Loads a redis.conf-style file on top of the defaults.
+    pub fn load(path: &Path) -> Result<Config> {
+        let src = std::fs::read_to_string(path)?;
+        let mut config = Config {
+            config_file: Some(path.to_path_buf()),
+            ..Config::default()
+        };
+        let mut tls: (Option<PathBuf>, Option<PathBuf>, Option<PathBuf>) = (None, None, None);
+        let mut master_tls: (Option<PathBuf>, Option<PathBuf>, Option<PathBuf>, Option<String>) = (None, None, None, None);
+
+        for (name, value) in config::parse(&src)? {
+            let mut res = Ok(());
+
+            match &name[..] {
+                "tls-cert-file" => tls.0 = Some(value.into()),
+                "tls-key-file" => tls.1 = Some(value.into()),
+                "tls-ca-cert-file" => tls.2 = Some(value.into()),
+                "tls-replication-ca-cert-file" => master_tls.0 = Some(value.into()),
+                "tls-replication-cert-file" => master_tls.1 = Some(value.into()),
+                "tls-replication-key-file" => master_tls.2 = Some(value.into()),
+                "tls-replication-server-name" => master_tls.3 = Some(value),
+                _ => res = config.set(&name, &value),
+            }
+
+            res.map_err(|err| format!("{:?}: invalid '{}' directive: {}", path, name, err))?;
+        }
+
+        if let (Some(cert_file), Some(key_file), ca_cert_file) = tls {
+            config.tls = Some(TlsFiles { cert_file, key_file, ca_cert_file });
+        }
+
+        match master_tls {
+            (Some(ca_cert_file), cert_file, key_file, server_name) => {
+                config.master_tls = Some(MasterTlsFiles {
+                    ca_cert_file,
+                    cert_file,
+                    key_file,
+                    server_name,
+                })
+            }
+            (None, None, None, None) => {}
+            _ => return Err(format!("{:?}: tls-replication-* directives require tls-replication-ca-cert-file", path).into()),
+        }
+
+        Ok(config)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> std::result::Result<(), String> {
+        let optional = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+        let number = |value: &str| value.parse::<u64>().map_err(|_| format!("'{}' is not a number", value));
+
+        match name {
+            "bind" => self.bind = value.to_string(),
+            "port" => self.port = value.parse().map_err(|_| format!("invalid port '{}'", value))?,
+            "dir" => self.dir = value.into(),
+            "dbfilename" if !value.is_empty() && Path::new(value).file_name().and_then(|name| name.to_str()) != Some(value) => return Err("dbfilename can't be a path, just a filename".into()),
+            "dbfilename" => self.dbfilename = optional(value),
+            "unixsocket" => self.unixsocket = optional(value).map(PathBuf::from),
+            "unixsocketperm" => self.unixsocketperm = Some(u32::from_str_radix(value, 8).map_err(|err| err.to_string())?),
+            "replicaof" => {
+                let (host, port) = value.split_once(' ').ok_or("expected <host> <port>")?;
+                self.replicaof = Some((host.to_string(), port.trim().parse().map_err(|_| format!("invalid port '{}'", port))?));
+            }
+            "masteruser" => self.masteruser = optional(value),
+            "masterauth" => self.masterauth = optional(value),
+            "cluster-config-file" => self.cluster_config = optional(value).map(PathBuf::from),
+            "cluster-node-id" => self.cluster_node_id = optional(value),
+            "metrics-addr" => self.metrics_addr = optional(value),
+            "databases" => self.databases = number(value)?.max(1) as usize,
+            _ if config::is_param(name) => self.params.push((name.to_string(), value.to_string())),
+            _ => return Err("unknown directive".to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn register_fixed(&self, db: &Db, bind: String, port: u16) {
+        let settings = db.config();
+        settings.set_fixed("bind", bind);
+        settings.set_fixed("port", port);
+        settings.set_fixed("databases", db.databases());
+        settings.set_fixed("dir", db.data_dir().dir().display());
+
+        let fixed = [
+            ("unixsocket", self.unixsocket.as_ref().map(|path| path.display().to_string())),
+            ("unixsocketperm", self.unixsocketperm.map(|mode| format!("{:o}", mode))),
+            ("tls-cert-file", self.tls.as_ref().map(|tls| tls.cert_file.display().to_string())),
+            ("tls-key-file", self.tls.as_ref().map(|tls| tls.key_file.display().to_string())),
+            ("tls-ca-cert-file", self.tls.as_ref().and_then(|tls| tls.ca_cert_file.as_ref()).map(|path| path.display().to_string())),
+            ("replicaof", self.replicaof.as_ref().map(|(host, port)| format!("{} {}", host, port))),
+            ("masteruser", self.masteruser.clone()),
+            ("masterauth", self.masterauth.clone()),
+            ("tls-replication-ca-cert-file", self.master_tls.as_ref().map(|tls| tls.ca_cert_file.display().to_string())),
+            ("tls-replication-cert-file", self.master_tls.as_ref().and_then(|tls| tls.cert_file.as_ref()).map(|path| path.display().to_string())),
+            ("tls-replication-key-file", self.master_tls.as_ref().and_then(|tls| tls.key_file.as_ref()).map(|path| path.display().to_string())),
+            ("tls-replication-server-name", self.master_tls.as_ref().and_then(|tls| tls.server_name.clone())),
+            ("cluster-config-file", self.cluster_config.as_ref().map(|path| path.display().to_string())),
+            ("cluster-node-id", self.cluster_node_id.clone()),
+            ("metrics-addr", self.metrics_addr.clone()),
+        ];
+
+        for (name, value) in fixed {
+            if let Some(value) = value {
+                settings.set_fixed(name, value);
+            }
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            config_file: None,
+            dir: PathBuf::from("."),
+            dbfilename: None,
+            requirepass: None,
+            tls: None,
+            unixsocket: None,
+            unixsocketperm: None,
+            replicaof: None,
+            masteruser: None,
+            masterauth: None,
+            master_tls: None,
+            cluster_config: None,
+            cluster_node_id: None,
+            maxmemory: 0,
+            maxmemory_policy: Policy::default(),
+            maxmemory_samples: DEFAULT_SAMPLES,
+            metrics_addr: None,
+            slowlog_log_slower_than: slowlog::DEFAULT_LOG_SLOWER_THAN,
+            slowlog_max_len: slowlog::DEFAULT_MAX_LEN,
+            maxclients: MAX_CONNECTIONS,
+            timeout: Duration::ZERO,
+            save: Vec::new(),
+            databases: DEFAULT_DATABASES,
+            tcp_keepalive: DEFAULT_TCP_KEEPALIVE,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            proto_max_bulk_len: DEFAULT_MAX_BULK_LEN,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl Accept for TcpListener {
+    type Stream = tokio::net::TcpStream;
+
+    async fn accept(&mut self) -> io::Result<(Self::Stream, String)> {
+        let (socket, addr) = TcpListener::accept(self).await?;
+        Ok((socket, addr.to_string()))
+    }
+
+    fn keepalive(socket: &Self::Stream, interval: Duration) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new().with_time(interval).with_interval((interval / 3).max(Duration::from_secs(1)));
+        SockRef::from(socket).set_tcp_keepalive(&keepalive)
+    }
+
+    fn nodelay(socket: &Self::Stream) -> io::Result<()> { socket.set_nodelay(true) }
+}
+
+impl Accept for UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept(&mut self) -> io::Result<(Self::Stream, String)> {
+        let path = self.local_addr()?.as_pathname().map(|path| path.display().to_string()).unwrap_or_default();
+        UnixListener::accept(self).await.map(|(socket, _)| (socket, format!("{}:0", path)))
+    }
+}
+
+impl<L: Accept> Listener<L> {
+    fn sibling<M: Accept>(&self, listener: M) -> Listener<M> {
+        Listener {
+            listener,
+            db: self.db.clone(),
+            limit_connections: self.limit_connections.clone(),
+            notify_shutdown: self.notify_shutdown.clone(),
+            shutdown_complete_tx: self.shutdown_complete_tx.clone(),
+            tls: None,
+        }
+    }
+
     async fn run(&mut self) -> Result<()> {
         info!("accepting inbound connections");
 
         loop {
             let permit = self.limit_connections.clone().acquire_owned().await.unwrap();
-            let socket = self.accept().await?;
+            let (socket, addr) = self.accept().await?;
+            self.db.stats().client_connected();
 
-            let mut handler = Handler {
-                db: self.db_holder.db(),
-                connection: Connection::new(socket),
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
-            };
+            let keepalive = self.db.config().tcp_keepalive();
+            if !keepalive.is_zero() {
+                if let Err(err) = L::keepalive(&socket, keepalive) {
+                    debug!(cause = ?err, "failed to enable tcp keepalive");
+                }
+            }
+
+            if let Err(err) = L::nodelay(&socket) {
+                debug!(cause = ?err, "failed to disable nagle's algorithm");
+            }
+
+            let db = self.db.clone();
+            let tls = self.tls.clone();
+            let shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+            let shutdown_complete = self.shutdown_complete_tx.clone();
 
             tokio::spawn(async move {
-                if let Err(err) = handler.run().await {
-                    error!(cause = ?err, "connection error");
+                let connection = match handshake(socket, tls).await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        db.stats().client_disconnected();
+                        return error!(cause = ?err, "tls handshake failed");
+                    }
+                };
+
+                let mut session = db.acl().session();
+                let client = db.clients().register(&addr, session.user());
+                session.set_id(client.id());
+                session.set_addr(addr);
+
+                let mut handler = Handler {
+                    session,
+                    client: client.clone(),
+                    db: db.clone(),
+                    connection,
+                    shutdown,
+                    _shutdown_complete: shutdown_complete,
+                };
+
+                tokio::select! {
+                    res = handler.run() => {
+                        if let Err(err) = res {
+                            error!(cause = ?err, "connection error");
+                        }
+                    }
+                    _ = client.killed() => {
+                        debug!(id = client.id(), "client killed");
+                    }
                 }
+
+                drop(handler);
+                db.clients().unregister(client.id());
+                db.tracking().forget(client.id());
+                db.stats().client_disconnected();
                 drop(permit);
             });
         }
     }
 
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    async fn accept(&mut self) -> crate::Result<(L::Stream, String)> {
         let mut backoff = 1;
 
         loop {
             match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+                Ok(socket) => return Ok(socket),
                 Err(err) => {
                     if backoff > 64 {
                         return Err(err.into());
@@ -119,12 +583,59 @@ impl Listener {
     }
 }
 
+impl TlsFiles {
+    fn acceptor(&self) -> Result<TlsAcceptor> {
+        let cert = std::fs::read(&self.cert_file)?;
+        let key = std::fs::read(&self.key_file)?;
+        let ca = self.ca_cert_file.as_ref().map(std::fs::read).transpose()?;
+
+        tls::acceptor(&cert, &key, ca.as_deref())
+    }
+}
+
+impl MasterTlsFiles {
+    fn master_tls(&self) -> Result<MasterTls> {
+        let mut config = TlsConfig::new().add_ca_file(&self.ca_cert_file)?;
+
+        match (&self.cert_file, &self.key_file) {
+            (Some(cert_file), Some(key_file)) => config = config.with_client_cert(&std::fs::read(cert_file)?, &std::fs::read(key_file)?)?,
+            (None, None) => {}
+            _ => return Err("a replication client certificate needs both a cert and a key file".into()),
+        }
+
+        Ok(MasterTls {
+            connector: config.connector()?,
+            server_name: self.server_name.clone(),
+        })
+    }
+}
+
+async fn handshake<S: Stream + 'static>(socket: S, tls: Option<TlsAcceptor>) -> Result<Connection> {
+    match tls {
+        Some(acceptor) => Ok(Connection::new(time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(socket)).await??)),
+        None => Ok(Connection::new(socket)),
+    }
+}
+
 impl Handler {
     #[instrument(skip(self))]
     async fn run(&mut self) -> Result<()> {
         while !self.shutdown.is_shutdown() {
+            let timeout = self.db.config().timeout();
+            self.connection.set_limits(self.db.config().frame_limits());
+
             let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => res?,
+                res = self.connection.read_frame() => match res {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        let _ = self.connection.write_frame(&err.to_frame()).await;
+                        return Err(err);
+                    }
+                },
+                _ = idle(timeout) => {
+                    debug!(id = self.client.id(), "closing idle client");
+                    return Ok(());
+                }
                 _ = self.shutdown.recv() => {
                     return Ok(());
                 }
@@ -135,12 +646,100 @@ impl Handler {
                 None => return Ok(()),
             };
 
-            let cmd = Command::from_frame(frame)?;
+            let replicated = frame.clone();
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    self.connection.write_frame(&err.to_frame()).await?;
+                    continue;
+                }
+            };
             debug!(?cmd);
 
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown).await?;
+            self.db.stats().command_processed();
+
+            let name = match &cmd {
+                Command::Unknown(_) => "unknown".to_string(),
+                cmd => cmd.get_name().to_string(),
+            };
+
+            self.client.begin(&name, self.session.user());
+
+            if let Err(err) = self.db.acl().authorize(&self.session, &cmd) {
+                self.connection.write_frame(&err.to_frame()).await?;
+                continue;
+            }
+
+            let asking = self.session.take_asking();
+
+            if let Err(err) = self.db.cluster().route(&cmd.keys(), asking, |key| self.db.contains(key)) {
+                self.connection.write_frame(&err.to_frame()).await?;
+                continue;
+            }
+
+            let write = cmd.is_write();
+
+            if write && self.db.replication().is_replica() {
+                self.connection.write_frame(&Error::ReadOnly("You can't write against a read only replica.".into()).to_frame()).await?;
+                continue;
+            }
+
+            if !matches!(cmd, Command::Client(_)) {
+                self.db.clients().wait_unpaused(write).await;
+            }
+
+            if !matches!(cmd, Command::Auth(_) | Command::Acl(_) | Command::Monitor(_) | Command::Config(_)) {
+                self.db.monitor().feed(&replicated, self.session.db(), self.session.addr());
+            }
+
+            if cmd.is_denyoom() {
+                if let Err(err) = self.db.evict() {
+                    self.connection.write_frame(&err.to_frame()).await?;
+                    continue;
+                }
+            }
+
+            if !write && self.db.tracking().is_tracking(self.session.id()) {
+                self.db.tracking().track(self.session.id(), &cmd.keys());
+            }
+
+            // LOAD resets the replication stream itself, which sends replicas
+            // through a full resync instead of replaying it against their own files.
+            let propagate = write && !matches!(cmd, Command::Load(_));
+            let blocking = matches!(cmd, Command::Subscribe(_) | Command::Monitor(_) | Command::Psync(_));
+            // The reply of a write is held back until the ordering guard is
+            // released, so a client that stops reading cannot stall a resync.
+            let ordering = if write { Some(self.db.replication().write_lock().await) } else { None };
+
+            if write {
+                self.connection.defer_writes();
+            }
+
+            let start = Instant::now();
+            cmd.apply(&self.db, &mut self.connection, &mut self.session, &mut self.shutdown).await?;
+            let elapsed = start.elapsed();
+
+            if propagate {
+                self.db.replication().propagate(self.session.db(), &replicated);
+            }
+
+            drop(ordering);
+            self.connection.flush_deferred().await?;
+
+            self.db.metrics().record(&name, elapsed);
+
+            if !blocking {
+                self.db.slowlog().record(&replicated, elapsed, self.session.addr(), &self.client.name());
+            }
         }
 
         Ok(())
     }
 }
+
+async fn idle(timeout: Duration) {
+    match timeout {
+        Duration::ZERO => future::pending().await,
+        timeout => time::sleep(timeout).await,
+    }
+}
@@ -0,0 +1,68 @@
+use db_proto::pkg::db::DbDropGuard;
+use db_proto::{prelude::Db, Result};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const KEYS: usize = 10_000;
+const RUN_FOR: Duration = Duration::from_secs(1);
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let max_threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    let threads: Vec<usize> = (0..).map(|n| 1 << n).take_while(|&n| n <= max_threads).collect();
+
+    println!("{:>8} {:>8} {:>14}", "shards", "threads", "ops/sec");
+
+    for shards in [1, Db::default_shards()] {
+        let guard = DbDropGuard::with_shards(shards);
+        let db = guard.db();
+
+        for i in 0..KEYS {
+            db.set(format!("key:{}", i), "value".into(), None);
+        }
+
+        for &threads in &threads {
+            println!("{:>8} {:>8} {:>14.0}", shards, threads, throughput(&db, threads));
+        }
+    }
+
+    Ok(())
+}
+
+fn throughput(db: &Db, threads: usize) -> f64 {
+    let ops = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for t in 0..threads {
+            let (ops, done) = (&ops, &done);
+
+            scope.spawn(move || {
+                let mut n = t * 7919;
+                let mut count = 0;
+
+                while !done.load(Ordering::Relaxed) {
+                    let key = format!("key:{}", n % KEYS);
+
+                    if n % 4 == 0 {
+                        db.set(key, "value".into(), None);
+                    } else {
+                        db.get(&key);
+                    }
+
+                    n += 1;
+                    count += 1;
+                }
+
+                ops.fetch_add(count, Ordering::Relaxed);
+            });
+        }
+
+        thread::sleep(RUN_FOR);
+        done.store(true, Ordering::Relaxed);
+    });
+
+    ops.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64()
+}
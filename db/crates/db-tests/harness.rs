@@ -0,0 +1,71 @@
+use db_proto::clients::Client;
+use db_proto::pkg::Frame;
+use db_server::Config;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// A `db_server::run` instance on an ephemeral port of the current runtime.
+/// Dropping it aborts the server; `stop` shuts it down the way a signal would,
+/// including the final snapshot when persistence is configured.
+pub struct TestServer {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<db_proto::Result<()>>,
+}
+
+impl TestServer {
+    pub async fn start() -> TestServer { TestServer::with_config(Config::default()).await }
+
+    pub async fn with_config(config: Config) -> TestServer { TestServer::serve(TestServer::bind().await, config).await }
+
+    /// Binds an ephemeral port up front, for configs that must name the
+    /// server's own address, such as a cluster topology.
+    pub async fn bind() -> TcpListener { TcpListener::bind("127.0.0.1:0").await.expect("failed to bind test listener") }
+
+    pub async fn serve(listener: TcpListener, config: Config) -> TestServer {
+        let addr = listener.local_addr().unwrap();
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let handle = tokio::spawn(db_server::run(listener, signal, config));
+
+        TestServer { addr, shutdown: Some(shutdown), handle }
+    }
+
+    pub fn addr(&self) -> SocketAddr { self.addr }
+
+    pub fn port(&self) -> u16 { self.addr.port() }
+
+    pub async fn client(&self) -> Client { Client::connect(self.addr).await.expect("failed to connect to test server") }
+
+    pub async fn stop(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+
+        (&mut self.handle).await.expect("server task panicked").expect("server failed");
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) { self.handle.abort(); }
+}
+
+/// Builds a command frame from its arguments, for commands without a typed
+/// client method or requests that are meant to be malformed.
+pub fn command(args: &[&str]) -> Frame { Frame::Array(args.iter().map(|arg| Frame::Bulk(arg.to_string().into())).collect()) }
+
+/// Sends `args` and returns the reply as is, leaving server errors as
+/// `Frame::Error` instead of turning them into `Err`.
+pub async fn raw(client: &mut Client, args: &[&str]) -> Frame {
+    let mut replies = client.pipeline(&[command(args)]).await.expect("connection failed");
+    replies.pop().unwrap()
+}
+
+/// Returns the message of an error reply, panicking on anything else.
+pub fn error(frame: &Frame) -> &str {
+    match frame {
+        Frame::Error(msg) => msg,
+        frame => panic!("expected an error, got {:?}", frame),
+    }
+}
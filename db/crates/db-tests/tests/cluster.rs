@@ -0,0 +1,164 @@
+use db_proto::clients::ClusterClient;
+use db_proto::pkg::cluster::{key_slot, Cluster, Topology};
+use db_proto::pkg::{Connection, Frame};
+use db_proto::Error;
+use db_server::Config;
+use db_tests::{command, raw, TestServer};
+use std::path::Path;
+use tokio::net::TcpListener;
+
+/// "bar" hashes to slot 5061 and "foo" to slot 12182.
+const TOPOLOGY: &str = "a 127.0.0.1:{a} 0-8191\nb 127.0.0.1:{b} 8192-16383\n";
+
+/// Two nodes splitting the slots in half, sharing one static topology file.
+struct Pair {
+    a: TestServer,
+    b: TestServer,
+}
+
+impl Pair {
+    async fn start(dir: &Path) -> Pair {
+        let (a, b) = (TestServer::bind().await, TestServer::bind().await);
+        let path = dir.join("nodes.conf");
+        let topology = TOPOLOGY.replace("{a}", &a.local_addr().unwrap().port().to_string()).replace("{b}", &b.local_addr().unwrap().port().to_string());
+        std::fs::write(&path, topology).unwrap();
+
+        let config = |id: &str| Config {
+            cluster_config: Some(path.clone()),
+            cluster_node_id: Some(id.to_string()),
+            ..Config::default()
+        };
+
+        Pair {
+            a: TestServer::serve(a, config("a")).await,
+            b: TestServer::serve(b, config("b")).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn keys_are_redirected_to_their_owner() {
+    let dir = tempfile::tempdir().unwrap();
+    let pair = Pair::start(dir.path()).await;
+    let (mut a, mut b) = (pair.a.client().await, pair.b.client().await);
+    let b_addr = format!("127.0.0.1:{}", pair.b.port());
+
+    assert!(matches!(a.get("foo").await, Err(Error::Moved { slot: 12182, addr }) if addr == b_addr));
+    assert!(matches!(raw(&mut a, &["set", "foo", "1"]).await, Frame::Error(msg) if msg == format!("MOVED 12182 {}", b_addr)));
+
+    b.set("foo", "1".into()).await.unwrap();
+    a.set("bar", "2".into()).await.unwrap();
+    assert_eq!(b.get("foo").await.unwrap().unwrap(), "1");
+    assert!(matches!(b.get("bar").await, Err(Error::Moved { slot: 5061, .. })));
+
+    // Keyless commands are served by any node.
+    assert_eq!(a.ping(None).await.unwrap(), "PONG");
+    assert_eq!(a.cluster_slots().await.unwrap(), vec![(0, 8191, format!("127.0.0.1:{}", pair.a.port())), (8192, 16383, b_addr)]);
+    assert!(a.cluster_nodes().await.unwrap().lines().any(|line| line.starts_with("a ") && line.contains("myself")));
+}
+
+#[tokio::test]
+async fn migrating_slots_answer_with_ask() {
+    let dir = tempfile::tempdir().unwrap();
+    let pair = Pair::start(dir.path()).await;
+    let (mut a, mut b) = (pair.a.client().await, pair.b.client().await);
+    let b_addr = format!("127.0.0.1:{}", pair.b.port());
+
+    a.set("bar", "stays".into()).await.unwrap();
+    a.execute(command(&["cluster", "setslot", "5061", "migrating", "b"])).await.unwrap();
+    b.execute(command(&["cluster", "setslot", "5061", "importing", "a"])).await.unwrap();
+
+    // Keys still on the source are served there; missing ones may have moved.
+    assert_eq!(a.get("bar").await.unwrap().unwrap(), "stays");
+    assert_eq!(key_slot("{bar}.moved"), 5061);
+    assert!(matches!(a.get("{bar}.moved").await, Err(Error::Ask { slot: 5061, addr }) if addr == b_addr));
+
+    // Checking where a key lives doesn't count as reading it.
+    let stats = a.info(Some("stats")).await.unwrap();
+    assert!(stats.contains("keyspace_hits:1\r\n") && stats.contains("keyspace_misses:0\r\n"), "{}", stats);
+
+    // The target only accepts the slot for a request preceded by ASKING.
+    assert!(matches!(b.set("{bar}.moved", "1".into()).await, Err(Error::Moved { slot: 5061, .. })));
+    b.asking().await.unwrap();
+    b.set("{bar}.moved", "1".into()).await.unwrap();
+    assert!(matches!(b.get("{bar}.moved").await, Err(Error::Moved { .. })));
+
+    let replies = b.pipeline(&[command(&["asking"]), command(&["get", "{bar}.moved"])]).await.unwrap();
+    assert!(replies[1] == "1");
+}
+
+#[test]
+fn keys_in_different_slots_are_rejected() {
+    let cluster = Cluster::new();
+    cluster.configure(Topology::parse(&TOPOLOGY.replace("{a}", "7000").replace("{b}", "7001"), Some("a"), 7000).unwrap());
+
+    assert!(cluster.route(&["bar", "{bar}.other"], false, |_| true).is_ok());
+    assert!(matches!(cluster.route(&["bar", "baz"], false, |_| true), Err(Error::CrossSlot(_))));
+    assert!(matches!(cluster.route(&["foo", "{foo}.other"], false, |_| true), Err(Error::Moved { slot: 12182, .. })));
+}
+
+#[tokio::test]
+async fn cluster_client_follows_and_caches_redirects() {
+    let dir = tempfile::tempdir().unwrap();
+    let pair = Pair::start(dir.path()).await;
+    let mut client = ClusterClient::connect(&[pair.a.addr()]).await.unwrap();
+
+    client.set("foo", "on b".into()).await.unwrap();
+    client.set("bar", "on a".into()).await.unwrap();
+    assert_eq!(pair.b.client().await.get("foo").await.unwrap().unwrap(), "on b");
+    assert_eq!(client.get("bar").await.unwrap().unwrap(), "on a");
+
+    // Hand slot 12182 over to a. The client's map still points at b, so the
+    // next request is answered with MOVED and the slot is remapped.
+    for node in [&pair.a, &pair.b] {
+        node.client().await.execute(command(&["cluster", "setslot", "12182", "node", "a"])).await.unwrap();
+    }
+
+    client.set("foo", "on a".into()).await.unwrap();
+    assert_eq!(pair.a.client().await.get("foo").await.unwrap().unwrap(), "on a");
+
+    // With b gone, "foo" is only reachable through the remapped slot.
+    pair.b.stop().await;
+    assert_eq!(client.get("foo").await.unwrap().unwrap(), "on a");
+    client.refresh_slots().await.unwrap();
+    assert_eq!(client.get("bar").await.unwrap().unwrap(), "on a");
+}
+
+/// A node that answers CLUSTER SLOTS with `slots` and every other command
+/// with `reply`, both raw RESP.
+async fn fake_node(slots: String, reply: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let (slots, reply) = (slots.clone(), reply.clone());
+
+            tokio::spawn(async move {
+                let mut connection = Connection::new(socket);
+
+                while let Ok(Some(Frame::Array(args))) = connection.read_frame().await {
+                    let response = if args[0] == "cluster" { &slots } else { &reply };
+                    connection.write_bytes(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn cluster_client_rejects_slots_out_of_range() {
+    let addr = fake_node("*1\r\n*3\r\n:0\r\n:20000\r\n*2\r\n$9\r\n127.0.0.1\r\n:1\r\n".into(), "+OK\r\n".into()).await;
+    assert!(matches!(ClusterClient::connect(&[&addr]).await, Err(Error::Protocol(_))));
+
+    // With no slots mapped every request goes to the seed, which redirects.
+    let addr = fake_node("*0\r\n".into(), format!("-MOVED 20000 {}\r\n", addr)).await;
+    let mut client = ClusterClient::connect(&[&addr]).await.unwrap();
+    assert!(matches!(client.get("foo").await, Err(Error::Protocol(_))));
+
+    let addr = fake_node("*0\r\n".into(), format!("-ASK 65535 {}\r\n", addr)).await;
+    let mut client = ClusterClient::connect(&[&addr]).await.unwrap();
+    assert!(matches!(client.get("foo").await, Err(Error::Protocol(_))));
+}
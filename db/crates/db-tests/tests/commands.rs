@@ -0,0 +1,504 @@
+use bytes::Bytes;
+use db_proto::pkg::registry::Filter;
+use db_proto::pkg::Frame;
+use db_server::Config;
+use db_tests::{command, error, raw, TestServer};
+use std::time::Duration;
+
+#[tokio::test]
+async fn ping_get_set() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_eq!(client.ping(None).await.unwrap(), "PONG");
+    assert_eq!(client.ping(Some("hello".into())).await.unwrap(), "hello");
+
+    assert_eq!(client.get("missing").await.unwrap(), None);
+    client.set("key", "value".into()).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "value");
+
+    client.set("key", "other".into()).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "other");
+
+    assert!(raw(&mut client, &["set", "key", "value", "EX", "10"]).await == "OK");
+    assert!(raw(&mut client, &["set", "key", "value", "PX", "10000"]).await == "OK");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn large_and_pipelined_values() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    let large: Bytes = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect::<Vec<u8>>().into();
+    client.set("large", large.clone()).await.unwrap();
+    assert_eq!(client.get("large").await.unwrap().unwrap(), large);
+
+    let values: Vec<String> = (0..200).map(|i| "v".repeat(i * 37)).collect();
+    let sets: Vec<Frame> = values.iter().enumerate().map(|(i, value)| command(&["set", &format!("key:{}", i), value])).collect();
+    assert!(client.pipeline(&sets).await.unwrap().iter().all(|reply| *reply == "OK"));
+
+    let gets: Vec<Frame> = (0..values.len()).map(|i| command(&["get", &format!("key:{}", i)])).collect();
+    let replies = client.pipeline(&gets).await.unwrap();
+    assert!(replies.iter().zip(&values).all(|(reply, value)| *reply == value.as_str()));
+}
+
+#[tokio::test]
+async fn unknown_command() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_eq!(error(&raw(&mut client, &["bogus", "arg"]).await), "ERR unknown command 'bogus'");
+    assert_eq!(client.ping(None).await.unwrap(), "PONG");
+}
+
+#[tokio::test]
+async fn ttl_and_pttl() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set("plain", "v".into()).await.unwrap();
+    client.set_expires("volatile", "v".into(), Duration::from_secs(100)).await.unwrap();
+
+    assert!(matches!(raw(&mut client, &["ttl", "missing"]).await, Frame::Integer(-2)));
+    assert!(matches!(raw(&mut client, &["ttl", "plain"]).await, Frame::Integer(-1)));
+    assert!(matches!(raw(&mut client, &["ttl", "volatile"]).await, Frame::Integer(99..=100)));
+    assert!(matches!(raw(&mut client, &["pttl", "volatile"]).await, Frame::Integer(99_000..=100_000)));
+
+    assert_eq!(client.pttl("plain").await.unwrap(), None);
+    assert!(client.pttl("volatile").await.unwrap().unwrap() > Duration::from_secs(99));
+    assert!(client.pttl("missing").await.is_err());
+}
+
+#[tokio::test]
+async fn scan_visits_every_key_once() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    for i in 0..500 {
+        client.set(&format!("key:{}", i), "v".into()).await.unwrap();
+    }
+    client.set("other", "v".into()).await.unwrap();
+
+    let mut keys = Vec::new();
+    let mut cursor = 0;
+
+    loop {
+        let (next, batch) = client.scan(cursor, Some("key:*"), Some(37)).await.unwrap();
+        keys.extend(batch);
+        cursor = next;
+
+        if cursor == 0 {
+            break;
+        }
+    }
+
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 500);
+    assert!(keys.iter().all(|key| key.starts_with("key:")));
+}
+
+#[tokio::test]
+async fn scan_survives_removals_between_calls() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    for i in 0..500 {
+        client.set(&format!("key:{}", i), "v".into()).await.unwrap();
+    }
+
+    let mut keys = Vec::new();
+    let mut cursor = 0;
+
+    loop {
+        let (next, batch) = client.scan(cursor, None, Some(37)).await.unwrap();
+
+        // Move half of what was just returned out of the database.
+        for key in batch.iter().filter(|key| key[4..].parse::<u32>().unwrap() % 2 == 0) {
+            assert!(client.move_key(key, 1).await.unwrap());
+        }
+
+        keys.extend(batch);
+        cursor = next;
+
+        if cursor == 0 {
+            break;
+        }
+    }
+
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 500);
+    assert_eq!(client.dbsize().await.unwrap(), 250);
+}
+
+#[tokio::test]
+async fn dbsize_time_lastsave() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_eq!(client.dbsize().await.unwrap(), 0);
+    client.set("a", "1".into()).await.unwrap();
+    client.set("b", "2".into()).await.unwrap();
+    assert_eq!(client.dbsize().await.unwrap(), 2);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    let time = client.time().await.unwrap();
+    assert!(time.abs_diff(now) < Duration::from_secs(5));
+
+    client.lastsave().await.unwrap();
+}
+
+#[tokio::test]
+async fn select_move_swapdb_flush() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set("key", "zero".into()).await.unwrap();
+    client.select(1).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap(), None);
+    client.set("key", "one".into()).await.unwrap();
+
+    client.select(0).await.unwrap();
+    assert!(!client.move_key("key", 1).await.unwrap());
+    client.set("moved", "v".into()).await.unwrap();
+    assert!(client.move_key("moved", 1).await.unwrap());
+    assert_eq!(client.get("moved").await.unwrap(), None);
+    assert!(client.select(16).await.is_err());
+    assert!(client.move_key("key", 0).await.is_err());
+
+    client.swapdb(0, 1).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "one");
+    assert_eq!(client.get("moved").await.unwrap().unwrap(), "v");
+
+    client.flushdb(false).await.unwrap();
+    assert_eq!(client.dbsize().await.unwrap(), 0);
+    client.select(1).await.unwrap();
+    assert_eq!(client.dbsize().await.unwrap(), 1);
+
+    client.flushall(true).await.unwrap();
+    assert_eq!(client.dbsize().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn auth_and_acl() {
+    let config = Config {
+        requirepass: Some("secret".to_string()),
+        ..Config::default()
+    };
+
+    let server = TestServer::with_config(config).await;
+    let mut client = server.client().await;
+
+    assert!(error(&raw(&mut client, &["get", "key"]).await).starts_with("NOAUTH"));
+    assert!(client.auth(None, "wrong").await.is_err());
+    client.auth(None, "secret").await.unwrap();
+    assert_eq!(client.acl_whoami().await.unwrap(), "default");
+
+    let rules = ["on", ">pass", "~cache:*", "+@read"].map(String::from);
+    client.acl_setuser("reader", &rules).await.unwrap();
+    assert!(client.acl_getuser("reader").await.unwrap().is_some());
+    assert!(client.acl_getuser("nobody").await.unwrap().is_none());
+    assert!(client.acl_list().await.unwrap().iter().any(|user| user.starts_with("user reader on")));
+    assert!(client.acl_setuser("reader", &["bogus-rule".to_string()]).await.is_err());
+
+    client.set("cache:a", "1".into()).await.unwrap();
+
+    let mut reader = server.client().await;
+    reader.auth(Some("reader"), "pass").await.unwrap();
+    assert_eq!(reader.get("cache:a").await.unwrap().unwrap(), "1");
+    assert!(error(&raw(&mut reader, &["get", "secret:a"]).await).starts_with("NOPERM"));
+    assert!(error(&raw(&mut reader, &["set", "cache:a", "2"]).await).starts_with("NOPERM"));
+
+    client.set("secret:a", "1".into()).await.unwrap();
+    assert_eq!(reader.scan(0, None, Some(100)).await.unwrap(), (0, vec!["cache:a".to_string()]));
+    assert_eq!(client.scan(0, None, Some(100)).await.unwrap().1.len(), 2);
+
+    // Disabling a user locks out connections that already authenticated as it.
+    client.acl_setuser("reader", &["off".to_string()]).await.unwrap();
+    assert!(error(&raw(&mut reader, &["get", "cache:a"]).await).starts_with("NOPERM"));
+}
+
+#[tokio::test]
+async fn info_and_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("db.conf");
+    std::fs::write(&file, "# test config\nmaxmemory 0\n").unwrap();
+
+    let config = Config {
+        config_file: Some(file.clone()),
+        dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+
+    let server = TestServer::with_config(config).await;
+    let mut client = server.client().await;
+
+    let info = client.info(None).await.unwrap();
+    assert!(info.contains("# Server"));
+    assert!(info.contains(&format!("tcp_port:{}", server.port())));
+    assert!(client.info(Some("keyspace")).await.unwrap().starts_with("# Keyspace"));
+
+    assert_eq!(client.config_get("maxmemory").await.unwrap(), vec![("maxmemory".to_string(), "0".to_string())]);
+    client.config_set(&[("maxmemory".to_string(), "100mb".to_string())]).await.unwrap();
+    assert_eq!(client.config_get("maxmemory").await.unwrap()[0].1, "104857600");
+    assert!(client.config_set(&[("maxmemory".to_string(), "99999999999999gb".to_string())]).await.is_err());
+    assert_eq!(client.config_get("maxmemory").await.unwrap()[0].1, "104857600");
+    assert!(client.config_set(&[("port".to_string(), "1".to_string())]).await.is_err());
+    assert!(matches!(client.config_set(&[("dir".to_string(), "/tmp".to_string())]).await, Err(err) if err.to_string().contains("immutable")));
+    assert_eq!(client.config_get("dir").await.unwrap().len(), 1);
+    assert!(client.config_set(&[("nope".to_string(), "1".to_string())]).await.is_err());
+    assert!(client.config_get("slowlog-*").await.unwrap().len() >= 2);
+
+    client.config_rewrite().await.unwrap();
+    let rewritten = std::fs::read_to_string(&file).unwrap();
+    assert!(rewritten.contains("# test config"));
+    assert!(rewritten.contains("maxmemory 104857600"));
+}
+
+#[tokio::test]
+async fn config_file_settings_match_config_set() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("db.conf");
+    std::fs::write(&file, "proto-max-bulk-len 1kb\nmaxclients 7\nmaxmemory-policy allkeys-lru\n").unwrap();
+
+    let server = TestServer::with_config(Config::load(&file).unwrap()).await;
+    let mut client = server.client().await;
+
+    assert_eq!(client.config_get("proto-max-bulk-len").await.unwrap()[0].1, "1048576");
+    assert_eq!(client.config_get("maxclients").await.unwrap()[0].1, "7");
+    assert_eq!(client.config_get("maxmemory-policy").await.unwrap()[0].1, "allkeys-lru");
+
+    std::fs::write(&file, "maxmemory-policy sometimes\n").unwrap();
+    let listener = TestServer::bind().await;
+    assert!(db_server::run(listener, std::future::pending::<()>(), Config::load(&file).unwrap()).await.is_err());
+}
+
+#[tokio::test]
+async fn eviction_with_the_default_databases() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    let value = Bytes::from(vec![b'x'; 256]);
+
+    for policy in ["allkeys-lru", "allkeys-random", "volatile-lru", "volatile-ttl"] {
+        client.flushall(false).await.unwrap();
+        client.config_set(&[("maxmemory-policy".to_string(), policy.to_string()), ("maxmemory".to_string(), "64kb".to_string())]).await.unwrap();
+
+        // Only database 0 holds keys; every write must still find a victim.
+        for i in 0..1000 {
+            let key = format!("key:{}", i);
+            match policy.starts_with("volatile") {
+                true => client.set_expires(&key, value.clone(), Duration::from_secs(600 + i)).await.unwrap(),
+                false => client.set(&key, value.clone()).await.unwrap(),
+            }
+        }
+
+        assert!(client.dbsize().await.unwrap() < 1000, "{} never evicted", policy);
+    }
+
+    let info = client.info(Some("stats")).await.unwrap();
+    assert!(!info.contains("evicted_keys:0\r"));
+}
+
+#[tokio::test]
+async fn writes_that_free_memory_pass_the_oom_check() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    for i in 0..100 {
+        client.set(&format!("key:{}", i), Bytes::from(vec![b'x'; 256])).await.unwrap();
+    }
+
+    client.config_set(&[("maxmemory".to_string(), "1kb".to_string())]).await.unwrap();
+    assert!(error(&raw(&mut client, &["set", "more", "1"]).await).starts_with("OOM"));
+
+    assert!(client.move_key("key:0", 1).await.unwrap());
+    client.swapdb(0, 1).await.unwrap();
+    client.flushdb(false).await.unwrap();
+    client.flushall(false).await.unwrap();
+    client.set("more", "1".into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn slowlog_and_latency() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.config_set(&[("slowlog-log-slower-than".to_string(), "0".to_string())]).await.unwrap();
+    client.slowlog_reset().await.unwrap();
+    client.set("key", "value".into()).await.unwrap();
+    client.get("key").await.unwrap();
+
+    assert!(client.slowlog_len().await.unwrap() >= 2);
+    assert!(matches!(client.slowlog_get(Some(1)).await.unwrap(), Frame::Array(entries) if entries.len() == 1));
+    client.slowlog_reset().await.unwrap();
+    assert!(client.slowlog_len().await.unwrap() <= 1);
+
+    // Credentials never reach the log, and blocking commands are not logged.
+    client.slowlog_reset().await.unwrap();
+    client.config_set(&[("requirepass".to_string(), "hunter2".to_string())]).await.unwrap();
+    client.auth(None, "hunter2").await.unwrap();
+    raw(&mut client, &["acl", "setuser", "alice", "on", ">secret", "~*", "+@all"]).await;
+    client.config_set(&[("requirepass".to_string(), "".to_string())]).await.unwrap();
+    let mut subscriber = server.client().await.subscribe(vec!["channel".to_string()]).await.unwrap();
+    subscriber.unsubscribe(&[]).await.unwrap();
+    drop(subscriber);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let log = format!("{:?}", client.slowlog_get(None).await.unwrap());
+    assert!(log.contains("(redacted)"));
+    assert!(!log.contains("hunter2") && !log.contains("secret"));
+    assert!(!log.to_lowercase().contains("subscribe"));
+
+    let histogram = client.latency_histogram(&["get".to_string()]).await.unwrap();
+    assert!(matches!(&histogram, Frame::Array(parts) if !parts.is_empty()));
+    client.latency_reset().await.unwrap();
+}
+
+#[tokio::test]
+async fn client_commands() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    let mut other = server.client().await;
+
+    assert_eq!(client.client_getname().await.unwrap(), None);
+    client.client_setname("tester").await.unwrap();
+    assert_eq!(client.client_getname().await.unwrap().as_deref(), Some("tester"));
+    assert!(client.client_setname("has space").await.is_err());
+
+    let id = client.client_id().await.unwrap();
+    let other_id = other.client_id().await.unwrap();
+    assert_ne!(id, other_id);
+
+    let list = client.client_list().await.unwrap();
+    assert!(list.contains(&format!("id={} ", id)));
+    assert!(list.contains("name=tester"));
+    assert!(list.contains(&format!("id={} ", other_id)));
+
+    let filter = Filter {
+        id: Some(other_id),
+        ..Filter::default()
+    };
+    assert_eq!(client.client_kill(filter).await.unwrap(), 1);
+    assert!(other.ping(None).await.is_err());
+
+    client.client_pause(Duration::from_millis(200), true).await.unwrap();
+    assert_eq!(client.ping(None).await.unwrap(), "PONG");
+    client.client_unpause().await.unwrap();
+    client.set("key", "value".into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn client_pause_delays_writes() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    let mut writer = server.client().await;
+
+    client.client_pause(Duration::from_millis(300), true).await.unwrap();
+
+    let start = std::time::Instant::now();
+    writer.set("key", "value".into()).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn monitor_streams_commands() {
+    use tokio_stream::StreamExt;
+
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    let lines = server.client().await.monitor().await.unwrap();
+    tokio::pin!(lines);
+
+    client.set("watched", "value".into()).await.unwrap();
+
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next()).await.unwrap().unwrap().unwrap();
+    assert!(line.contains(" [0 127.0.0.1:") && line.contains("\"set\" \"watched\" \"value\""), "{}", line);
+
+    client.select(3).await.unwrap();
+    let _ = tokio::time::timeout(Duration::from_secs(5), lines.next()).await.unwrap();
+    client.get("watched").await.unwrap();
+
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next()).await.unwrap().unwrap().unwrap();
+    assert!(line.contains(" [3 127.0.0.1:") && line.contains("\"get\" \"watched\""), "{}", line);
+}
+
+#[tokio::test]
+async fn cluster_commands_without_cluster() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_eq!(client.cluster_keyslot("foo").await.unwrap(), 12182);
+    assert_eq!(client.cluster_keyslot("{user1000}.following").await.unwrap(), client.cluster_keyslot("{user1000}.followers").await.unwrap());
+    assert!(client.cluster_slots().await.is_err());
+    assert!(client.cluster_nodes().await.is_err());
+    assert!(error(&raw(&mut client, &["cluster", "myid"]).await).contains("cluster support disabled"));
+    assert!(raw(&mut client, &["asking"]).await == "OK");
+}
+
+#[tokio::test]
+async fn dump_and_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+
+    let server = TestServer::with_config(config).await;
+    let mut client = server.client().await;
+
+    client.set("key", "value".into()).await.unwrap();
+    client.dump(Some("snapshot.db".as_ref())).await.unwrap();
+    assert!(dir.path().join("snapshot.db").exists());
+
+    client.flushall(false).await.unwrap();
+    client.load(Some("snapshot.db".as_ref())).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "value");
+
+    assert!(client.dump(Some("../escape.db".as_ref())).await.is_err());
+    assert!(client.load(Some("missing.db".as_ref())).await.is_err());
+}
+
+#[tokio::test]
+async fn replication() {
+    let master = TestServer::start().await;
+    let replica = TestServer::start().await;
+    let mut writer = master.client().await;
+    let mut reader = replica.client().await;
+
+    writer.set("before", "1".into()).await.unwrap();
+    reader.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+
+    let synced = async {
+        loop {
+            if reader.get("before").await.unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), synced).await.expect("replica never synced");
+
+    writer.select(3).await.unwrap();
+    writer.set("after", "2".into()).await.unwrap();
+    reader.select(3).await.unwrap();
+
+    let streamed = async {
+        loop {
+            if reader.get("after").await.unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), streamed).await.expect("write never reached the replica");
+
+    assert!(matches!(writer.role().await.unwrap(), Frame::Array(parts) if parts[0] == "master"));
+    assert!(matches!(reader.role().await.unwrap(), Frame::Array(parts) if parts[0] == "slave"));
+    assert!(error(&raw(&mut reader, &["set", "k", "v"]).await).starts_with("READONLY"));
+
+    reader.replicaof(None).await.unwrap();
+    reader.set("k", "v".into()).await.unwrap();
+}
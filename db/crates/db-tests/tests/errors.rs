@@ -0,0 +1,86 @@
+use db_proto::pkg::Frame;
+use db_proto::Error;
+use db_server::Config;
+use db_tests::{command, error, raw, TestServer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[test]
+fn reply_classification() {
+    assert!(matches!(Error::from_reply("ERR syntax error"), Error::Err(msg) if msg == "syntax error"));
+    assert!(matches!(Error::from_reply("NOAUTH Authentication required."), Error::NoAuth(_)));
+    assert!(matches!(Error::from_reply("MOVED 3999 127.0.0.1:6381"), Error::Moved { slot: 3999, addr } if addr == "127.0.0.1:6381"));
+    assert!(matches!(Error::from_reply("ASK 12 10.0.0.1:7000"), Error::Ask { slot: 12, .. }));
+    assert!(matches!(Error::from_reply("MOVED nonsense"), Error::Reply { prefix, .. } if prefix == "MOVED"));
+    assert!(matches!(Error::from_reply("BUSYKEY Target key name already exists."), Error::Reply { prefix, .. } if prefix == "BUSYKEY"));
+    assert!(matches!(Error::from_reply("something went wrong"), Error::Err(msg) if msg == "something went wrong"));
+
+    for line in ["ERR syntax error", "WRONGTYPE Operation against a key holding the wrong kind of value", "MOVED 3999 127.0.0.1:6381", "BUSYKEY Target key name already exists."] {
+        let err = Error::from_reply(line);
+        assert_eq!(err.to_string(), line);
+        assert!(matches!(err.to_frame(), Frame::Error(msg) if msg == line));
+    }
+
+    assert!(matches!(Error::Protocol("invalid frame format".into()).to_frame(), Frame::Error(msg) if msg == "ERR Protocol error: invalid frame format"));
+    assert!(matches!(Error::WrongArity { cmd: "get".into() }.to_frame(), Frame::Error(msg) if msg == "ERR wrong number of arguments for 'get' command"));
+
+    // Only replies are classified; messages built in-process keep their wording.
+    assert!(matches!(Error::from("NOPERM not a reply"), Error::Other(_)));
+}
+
+#[tokio::test]
+async fn syntax_errors_keep_the_connection() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_eq!(error(&raw(&mut client, &["get"]).await), "ERR wrong number of arguments for 'get' command");
+    assert_eq!(error(&raw(&mut client, &["get", "a", "b"]).await), "ERR wrong number of arguments for 'get' command");
+    assert!(error(&raw(&mut client, &["set", "key", "value", "NX"]).await).starts_with("ERR "));
+    assert!(error(&raw(&mut client, &["scan", "nope"]).await).starts_with("ERR "));
+    assert!(error(&raw(&mut client, &["scan", "0", "COUNT", "0"]).await).starts_with("ERR "));
+
+    let replies = client.pipeline(&[command(&["set", "key", "1"]), command(&["ttl"]), command(&["get", "key"])]).await.unwrap();
+    assert!(replies[0] == "OK");
+    assert!(matches!(&replies[1], Frame::Error(msg) if msg.starts_with("ERR wrong number of arguments")));
+    assert!(replies[2] == "1");
+
+    assert_eq!(client.ping(None).await.unwrap(), "PONG");
+}
+
+#[tokio::test]
+async fn client_results_are_typed() {
+    let config = Config {
+        requirepass: Some("secret".to_string()),
+        ..Config::default()
+    };
+
+    let server = TestServer::with_config(config).await;
+    let mut client = server.client().await;
+
+    assert!(matches!(client.get("key").await, Err(Error::NoAuth(_))));
+    assert!(matches!(client.auth(None, "wrong").await, Err(Error::WrongPass(_))));
+    client.auth(None, "secret").await.unwrap();
+
+    let rules = ["on", ">pass", "~cache:*", "+@read"].map(String::from);
+    client.acl_setuser("reader", &rules).await.unwrap();
+
+    let mut reader = server.client().await;
+    reader.auth(Some("reader"), "pass").await.unwrap();
+    assert!(matches!(reader.get("secret:a").await, Err(Error::NoPerm(msg)) if msg == "No permissions to access a key"));
+    assert!(matches!(reader.set("cache:a", "1".into()).await, Err(Error::NoPerm(_))));
+
+    assert!(matches!(client.execute(command(&["get"])).await, Err(Error::Err(msg)) if msg == "wrong number of arguments for 'get' command"));
+    assert!(matches!(client.execute(command(&["bogus"])).await, Err(Error::Err(_))));
+}
+
+#[tokio::test]
+async fn protocol_errors_close_the_connection() {
+    let server = TestServer::start().await;
+    let mut socket = TcpStream::connect(server.addr()).await.unwrap();
+
+    socket.write_all(b"!oops\r\n").await.unwrap();
+
+    let mut reply = String::new();
+    socket.read_to_string(&mut reply).await.unwrap();
+    assert!(reply.starts_with("-ERR Protocol error: invalid frame type byte"), "{:?}", reply);
+}
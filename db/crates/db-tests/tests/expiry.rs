@@ -0,0 +1,86 @@
+use db_proto::prelude::Db;
+use db_tests::TestServer;
+use std::time::Duration;
+use tokio::time;
+
+#[tokio::test(start_paused = true)]
+async fn keys_expire_on_access() {
+    let db = Db::new();
+
+    db.set("short".to_string(), "v".into(), Some(Duration::from_secs(10)));
+    db.set("forever".to_string(), "v".into(), None);
+    assert!(db.get("short").is_some());
+    assert_eq!(db.ttl("short"), Some(Some(Duration::from_secs(10))));
+
+    time::advance(Duration::from_secs(9)).await;
+    assert_eq!(db.ttl("short"), Some(Some(Duration::from_secs(1))));
+    assert!(db.get("short").is_some());
+
+    time::advance(Duration::from_secs(1)).await;
+    assert_eq!(db.get("short"), None);
+    assert_eq!(db.ttl("short"), None);
+    assert!(db.get("forever").is_some());
+}
+
+#[tokio::test(start_paused = true)]
+async fn background_task_purges_expired_keys() {
+    let db = Db::new();
+
+    for i in 0..100 {
+        db.set(format!("key:{}", i), "v".into(), Some(Duration::from_secs(1 + i % 10)));
+    }
+    db.set("forever".to_string(), "v".into(), None);
+    assert_eq!(db.expiring(), 100);
+
+    time::sleep(Duration::from_millis(5500)).await;
+    assert_eq!(db.expiring(), 50);
+    assert_eq!(db.len(), 51);
+
+    time::sleep(Duration::from_secs(5)).await;
+    assert_eq!(db.expiring(), 0);
+    assert_eq!(db.len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn overwriting_clears_or_replaces_expiry() {
+    let db = Db::new();
+
+    db.set("key".to_string(), "v1".into(), Some(Duration::from_secs(1)));
+    db.set("key".to_string(), "v2".into(), None);
+    time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(db.get("key").unwrap(), "v2");
+
+    db.set("key".to_string(), "v3".into(), Some(Duration::from_secs(10)));
+    db.set("key".to_string(), "v4".into(), Some(Duration::from_secs(1)));
+    time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(db.get("key"), None);
+    assert_eq!(db.expiring(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn move_keeps_expiry() {
+    let db = Db::new();
+
+    db.set("key".to_string(), "v".into(), Some(Duration::from_secs(5)));
+    assert!(db.move_key("key", 1));
+
+    let other = db.select(1);
+    assert_eq!(other.ttl("key"), Some(Some(Duration::from_secs(5))));
+
+    time::sleep(Duration::from_secs(6)).await;
+    assert_eq!(other.get("key"), None);
+    assert_eq!(other.expiring(), 0);
+}
+
+#[tokio::test]
+async fn expiry_over_the_wire() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set_expires("key", "v".into(), Duration::from_millis(100)).await.unwrap();
+    assert!(client.get("key").await.unwrap().is_some());
+
+    time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(client.get("key").await.unwrap(), None);
+    assert_eq!(client.dbsize().await.unwrap(), 0);
+}
@@ -0,0 +1,149 @@
+use bytes::{Bytes, BytesMut};
+use db_proto::pkg::frame::{Error, Limits};
+use db_proto::pkg::Frame;
+
+const ITERATIONS: usize = 10_000;
+
+/// xorshift64, so failures reproduce from the printed seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 { self.next() % n }
+
+    fn bytes(&mut self, max: u64) -> Vec<u8> { (0..self.below(max + 1)).map(|_| self.next() as u8).collect() }
+
+    fn line(&mut self, max: u64) -> String { (0..self.below(max + 1)).map(|_| (b' ' + self.below(95) as u8) as char).collect() }
+}
+
+fn frame(rng: &mut Rng, depth: u32) -> Frame {
+    match rng.below(if depth < 4 { 6 } else { 5 }) {
+        0 => Frame::Simple(rng.line(20)),
+        1 => Frame::Error(rng.line(20)),
+        2 => Frame::Integer(rng.next() as i64),
+        3 => Frame::Bulk(Bytes::from(rng.bytes(64))),
+        4 => Frame::Null,
+        _ => Frame::Array((0..rng.below(6)).map(|_| frame(rng, depth + 1)).collect()),
+    }
+}
+
+fn encode(frame: &Frame) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    frame.encode(&mut buf);
+    buf.to_vec()
+}
+
+/// Decodes from the front of `src`, returning the frame and the number of
+/// bytes it consumed.
+fn decode(src: &[u8]) -> Result<Option<(Frame, usize)>, Error> {
+    let mut buf = BytesMut::from(src);
+    let frame = Frame::decode(&mut buf, &Limits::default())?;
+
+    Ok(frame.map(|frame| (frame, src.len() - buf.len())))
+}
+
+#[test]
+fn encoded_frames_round_trip() {
+    let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+    for _ in 0..ITERATIONS {
+        let original = frame(&mut rng, 0);
+        let encoded = encode(&original);
+
+        let (decoded, len) = decode(&encoded).unwrap().unwrap();
+        assert_eq!(len, encoded.len());
+        assert_eq!(encode(&decoded), encoded, "{:?}", original);
+    }
+}
+
+#[test]
+fn every_prefix_is_incomplete() {
+    let mut rng = Rng(0x0dd_ba11_cafe_f00d);
+
+    for _ in 0..ITERATIONS / 10 {
+        let encoded = encode(&frame(&mut rng, 0));
+
+        for end in 0..encoded.len() {
+            assert!(matches!(decode(&encoded[..end]), Ok(None)), "prefix {:?}", &encoded[..end]);
+        }
+    }
+}
+
+#[test]
+fn random_bytes_never_panic() {
+    let mut rng = Rng(0xdead_beef_0bad_f00d);
+
+    for _ in 0..ITERATIONS {
+        let mut input = rng.bytes(64);
+
+        if let Some(first) = input.first_mut() {
+            *first = b"+-:$*?"[rng.below(6) as usize];
+        }
+
+        let _ = decode(&input);
+    }
+}
+
+#[test]
+fn mutated_frames_never_panic() {
+    let mut rng = Rng(0xfeed_face_1337_4242);
+
+    for _ in 0..ITERATIONS {
+        let mut input = encode(&frame(&mut rng, 0));
+
+        for _ in 0..=rng.below(4) {
+            let i = rng.below(input.len() as u64) as usize;
+
+            match rng.below(3) {
+                0 => input[i] = rng.next() as u8,
+                1 => input[i] = b"\r\n-0123456789*$"[rng.below(15) as usize],
+                _ => input.truncate(i),
+            }
+
+            if input.is_empty() {
+                break;
+            }
+        }
+
+        let _ = decode(&input);
+    }
+}
+
+#[test]
+fn limits_reject_oversized_headers() {
+    let limits = Limits { max_bulk_len: 16, max_array_len: 4 };
+    let check = |src: &[u8], limits: &Limits| Frame::decode(&mut BytesMut::from(src), limits);
+
+    assert!(matches!(check(b"$17\r\n", &limits), Err(Error::Other(_))));
+    assert!(matches!(check(b"*5\r\n", &limits), Err(Error::Other(_))));
+    assert!(matches!(check(b"$16\r\n", &limits), Ok(None)));
+
+    let nested = "*1\r\n".repeat(64);
+    assert!(matches!(check(nested.as_bytes(), &Limits::default()), Err(Error::Other(_))));
+}
+
+#[test]
+fn bulk_payloads_share_the_read_buffer() {
+    let mut buf = BytesMut::from(&b"*2\r\n$3\r\nset\r\n$5\r\nhello\r\n+OK\r\n"[..]);
+    let start = buf.as_ptr() as usize;
+
+    let frame = Frame::decode(&mut buf, &Limits::default()).unwrap().unwrap();
+    let Frame::Array(parts) = frame else { panic!("expected an array") };
+    let Frame::Bulk(value) = &parts[1] else { panic!("expected a bulk string") };
+
+    assert_eq!(value, "hello");
+    assert_eq!(value.as_ptr() as usize, start + 17);
+    assert_eq!(&buf[..], b"+OK\r\n");
+}
+
+#[test]
+fn bulk_without_terminator_is_rejected() {
+    assert!(matches!(decode(b"$3\r\nabcde"), Err(Error::Other(_))));
+    assert!(matches!(decode(b"$3\r\nab"), Ok(None)));
+}
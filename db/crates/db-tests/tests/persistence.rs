@@ -0,0 +1,104 @@
+use db_proto::pkg::config;
+use db_server::Config;
+use db_tests::TestServer;
+use std::path::Path;
+use std::time::Duration;
+
+fn persistent(dir: &Path) -> Config {
+    Config {
+        dir: dir.to_path_buf(),
+        dbfilename: Some("dump.db".to_string()),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn snapshot_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let server = TestServer::with_config(persistent(dir.path())).await;
+    let mut client = server.client().await;
+
+    client.set("plain", "zero".into()).await.unwrap();
+    client.set_expires("volatile", "soon".into(), Duration::from_secs(100)).await.unwrap();
+    client.set_expires("expired", "gone".into(), Duration::from_millis(10)).await.unwrap();
+    client.select(2).await.unwrap();
+    client.set("plain", "two".into()).await.unwrap();
+    client.set("binary", vec![0u8, 255, 13, 10].into()).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(client);
+    server.stop().await;
+    assert!(dir.path().join("dump.db").exists());
+
+    let server = TestServer::with_config(persistent(dir.path())).await;
+    let mut client = server.client().await;
+
+    assert_eq!(client.get("plain").await.unwrap().unwrap(), "zero");
+    assert_eq!(client.get("expired").await.unwrap(), None);
+    let ttl = client.pttl("volatile").await.unwrap().unwrap();
+    assert!(ttl > Duration::from_secs(90) && ttl <= Duration::from_secs(100));
+    assert_eq!(client.dbsize().await.unwrap(), 2);
+
+    client.select(2).await.unwrap();
+    assert_eq!(client.get("plain").await.unwrap().unwrap(), "two");
+    assert_eq!(client.get("binary").await.unwrap().unwrap(), &[0u8, 255, 13, 10][..]);
+
+    client.select(1).await.unwrap();
+    assert_eq!(client.dbsize().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn snapshot_keeps_swapped_databases() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let server = TestServer::with_config(persistent(dir.path())).await;
+    let mut client = server.client().await;
+    client.set("key", "zero".into()).await.unwrap();
+    client.swapdb(0, 5).await.unwrap();
+    drop(client);
+    server.stop().await;
+
+    let server = TestServer::with_config(persistent(dir.path())).await;
+    let mut client = server.client().await;
+    assert_eq!(client.get("key").await.unwrap(), None);
+    client.select(5).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "zero");
+}
+
+#[tokio::test]
+async fn without_dbfilename_nothing_is_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+
+    let server = TestServer::with_config(config).await;
+    server.client().await.set("key", "value".into()).await.unwrap();
+    server.stop().await;
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn save_rules_trigger_background_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        save: config::parse_save("1 1").unwrap(),
+        ..persistent(dir.path())
+    };
+
+    let server = TestServer::with_config(config).await;
+    let mut client = server.client().await;
+    let before = client.lastsave().await.unwrap();
+    client.set("key", "value".into()).await.unwrap();
+
+    let saved = async {
+        while !dir.path().join("dump.db").exists() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), saved).await.expect("no background save");
+    assert!(client.lastsave().await.unwrap() >= before);
+}
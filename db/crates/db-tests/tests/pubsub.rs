@@ -0,0 +1,78 @@
+use db_tests::TestServer;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn publish_reaches_every_subscriber() {
+    let server = TestServer::start().await;
+    let mut publisher = server.client().await;
+
+    let mut first = server.client().await.subscribe(vec!["news".to_string()]).await.unwrap();
+    let mut second = server.client().await.subscribe(vec!["news".to_string(), "sport".to_string()]).await.unwrap();
+    assert_eq!(second.get_subscribed(), ["news", "sport"]);
+
+    assert_eq!(publisher.publish("news", "hello".into()).await.unwrap(), 2);
+    assert_eq!(publisher.publish("sport", "goal".into()).await.unwrap(), 1);
+    assert_eq!(publisher.publish("weather", "rain".into()).await.unwrap(), 0);
+
+    let message = first.next_message().await.unwrap().unwrap();
+    assert_eq!((message.channel.as_str(), &message.content[..]), ("news", &b"hello"[..]));
+
+    let message = second.next_message().await.unwrap().unwrap();
+    assert_eq!((message.channel.as_str(), &message.content[..]), ("news", &b"hello"[..]));
+    let message = second.next_message().await.unwrap().unwrap();
+    assert_eq!((message.channel.as_str(), &message.content[..]), ("sport", &b"goal"[..]));
+}
+
+#[tokio::test]
+async fn messages_arrive_in_order() {
+    let server = TestServer::start().await;
+    let mut publisher = server.client().await;
+    let mut subscriber = server.client().await.subscribe(vec!["seq".to_string()]).await.unwrap();
+
+    for i in 0..100 {
+        publisher.publish("seq", i.to_string().into()).await.unwrap();
+    }
+
+    for i in 0..100 {
+        let message = timeout(Duration::from_secs(5), subscriber.next_message()).await.unwrap().unwrap().unwrap();
+        assert_eq!(message.content, i.to_string());
+    }
+}
+
+#[tokio::test]
+async fn subscribe_and_unsubscribe_while_subscribed() {
+    let server = TestServer::start().await;
+    let mut publisher = server.client().await;
+    let mut subscriber = server.client().await.subscribe(vec!["a".to_string()]).await.unwrap();
+
+    subscriber.subscribe(&["b".to_string()]).await.unwrap();
+    assert_eq!(subscriber.get_subscribed(), ["a", "b"]);
+    assert_eq!(publisher.publish("b", "1".into()).await.unwrap(), 1);
+    assert_eq!(subscriber.next_message().await.unwrap().unwrap().channel, "b");
+
+    subscriber.unsubscribe(&["a".to_string()]).await.unwrap();
+    assert_eq!(subscriber.get_subscribed(), ["b"]);
+    assert_eq!(publisher.publish("a", "2".into()).await.unwrap(), 0);
+
+    subscriber.unsubscribe(&[]).await.unwrap();
+    assert!(subscriber.get_subscribed().is_empty());
+    assert_eq!(publisher.publish("b", "3".into()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn dropped_subscriber_stops_counting() {
+    let server = TestServer::start().await;
+    let mut publisher = server.client().await;
+    let subscriber = server.client().await.subscribe(vec!["gone".to_string()]).await.unwrap();
+
+    assert_eq!(publisher.publish("gone", "1".into()).await.unwrap(), 1);
+    drop(subscriber);
+
+    let released = async {
+        while publisher.publish("gone", "2".into()).await.unwrap() != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), released).await.expect("subscription outlived its connection");
+}
@@ -0,0 +1,123 @@
+use db_proto::clients::Client;
+use db_proto::pkg::Frame;
+use db_server::Config;
+use db_tests::{raw, TestServer};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixStream;
+
+async fn repl_offset(client: &mut Client) -> u64 {
+    let info = client.info(Some("replication")).await.unwrap();
+    let line = info.lines().find(|line| line.starts_with("master_repl_offset:")).unwrap();
+    line["master_repl_offset:".len()..].parse().unwrap()
+}
+
+/// Waits until the replica has consumed everything the master propagated.
+async fn caught_up(master: &mut Client, replica: &mut Client) {
+    let synced = async {
+        loop {
+            let (role, target) = (replica.role().await.unwrap(), repl_offset(master).await);
+
+            if matches!(&role, Frame::Array(parts) if parts[3] == "connected") && repl_offset(replica).await == target {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+
+    tokio::time::timeout(Duration::from_secs(10), synced).await.expect("replica never caught up");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn writes_racing_a_full_resync_apply_once() {
+    let master = TestServer::start().await;
+    let replica = TestServer::start().await;
+    let mut writer = master.client().await;
+    let mut reader = replica.client().await;
+
+    writer.set("key", "zero".into()).await.unwrap();
+
+    let mut swappers = Vec::new();
+
+    for _ in 0..4 {
+        let mut swapper = master.client().await;
+        swappers.push(tokio::spawn(async move {
+            for _ in 0..500 {
+                swapper.swapdb(0, 1).await.unwrap();
+            }
+        }));
+    }
+
+    reader.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+
+    for swapper in swappers {
+        swapper.await.unwrap();
+    }
+
+    caught_up(&mut writer, &mut reader).await;
+
+    assert_eq!(reader.get("key").await.unwrap(), writer.get("key").await.unwrap());
+    reader.select(1).await.unwrap();
+    writer.select(1).await.unwrap();
+    assert_eq!(reader.get("key").await.unwrap(), writer.get("key").await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_client_that_stops_reading_cannot_stall_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.sock");
+    let config = Config {
+        unixsocket: Some(path.clone()),
+        ..Config::default()
+    };
+
+    let master = TestServer::with_config(config).await;
+    let replica = TestServer::start().await;
+    let mut writer = master.client().await;
+    let mut reader = replica.client().await;
+
+    // Pipeline writes without ever reading a reply, until the server can no
+    // longer send them. Unix sockets have small fixed buffers, so this is quick.
+    let mut stalled = UnixStream::connect(&path).await.unwrap();
+    let batch = "*3\r\n$3\r\nset\r\n$7\r\nstalled\r\n$1\r\n1\r\n".repeat(1000);
+    while tokio::time::timeout(Duration::from_secs(1), stalled.write_all(batch.as_bytes())).await.is_ok() {}
+
+    reader.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::timeout(Duration::from_secs(5), writer.set("key", "value".into())).await.expect("writes stalled behind the resync").unwrap();
+    caught_up(&mut writer, &mut reader).await;
+    assert_eq!(reader.get("key").await.unwrap().unwrap(), "value");
+}
+
+#[tokio::test]
+async fn load_resyncs_replicas_and_is_rejected_on_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+
+    let master = TestServer::with_config(config).await;
+    let replica = TestServer::start().await;
+    let mut writer = master.client().await;
+    let mut reader = replica.client().await;
+
+    writer.set("key", "saved".into()).await.unwrap();
+    writer.dump(Some("snapshot.db".as_ref())).await.unwrap();
+
+    reader.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+    writer.set("key", "changed".into()).await.unwrap();
+    caught_up(&mut writer, &mut reader).await;
+    assert_eq!(reader.get("key").await.unwrap().unwrap(), "changed");
+
+    writer.load(Some("snapshot.db".as_ref())).await.unwrap();
+    let resynced = async {
+        while reader.get("key").await.unwrap().unwrap() != "saved" {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), resynced).await.expect("LOAD never reached the replica");
+
+    assert!(matches!(raw(&mut reader, &["load"]).await, Frame::Error(msg) if msg.starts_with("READONLY")));
+}
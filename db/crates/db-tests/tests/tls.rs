@@ -0,0 +1,185 @@
+use db_proto::clients::Client;
+use db_proto::pkg::tls::TlsConfig;
+use db_server::{Config, MasterTlsFiles, TlsFiles, HANDSHAKE_TIMEOUT};
+use db_tests::TestServer;
+use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+use std::path::PathBuf;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// A self-signed CA with a `localhost` server certificate and a client
+/// certificate issued by it, written as PEM files into a temporary directory.
+struct Pki {
+    dir: TempDir,
+    ca: String,
+    client_cert: String,
+    client_key: String,
+}
+
+impl Pki {
+    fn generate() -> Pki {
+        let dir = tempfile::tempdir().unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.to_string()]).unwrap().signed_by(&key, &ca, &ca_key).unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+
+        let (server_cert, server_key) = issue("localhost");
+        let (client_cert, client_key) = issue("client");
+
+        std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.path().join("server.pem"), server_cert).unwrap();
+        std::fs::write(dir.path().join("server.key"), server_key).unwrap();
+        std::fs::write(dir.path().join("client.pem"), &client_cert).unwrap();
+        std::fs::write(dir.path().join("client.key"), &client_key).unwrap();
+
+        Pki {
+            dir,
+            ca: ca.pem(),
+            client_cert,
+            client_key,
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf { self.dir.path().join(name) }
+
+    fn config(&self, verify_clients: bool) -> Config {
+        Config {
+            tls: Some(TlsFiles {
+                cert_file: self.path("server.pem"),
+                key_file: self.path("server.key"),
+                ca_cert_file: verify_clients.then(|| self.path("ca.pem")),
+            }),
+            ..Config::default()
+        }
+    }
+
+    /// A plaintext replica that reaches its master over TLS with the client
+    /// certificate.
+    fn replica_config(&self) -> Config {
+        Config {
+            master_tls: Some(MasterTlsFiles {
+                ca_cert_file: self.path("ca.pem"),
+                cert_file: Some(self.path("client.pem")),
+                key_file: Some(self.path("client.key")),
+                server_name: Some("localhost".to_string()),
+            }),
+            ..Config::default()
+        }
+    }
+
+    fn trusting(&self) -> TlsConfig { TlsConfig::new().add_ca_pem(self.ca.as_bytes()).unwrap() }
+}
+
+async fn connect(server: &TestServer, tls: &TlsConfig) -> db_proto::Result<Client> {
+    let mut client = Client::connect_tls(server.addr(), "localhost", tls).await?;
+    // With TLS 1.3 the server checks the client certificate after the client
+    // considers the handshake done, so only the first reply reveals a rejection.
+    client.ping(None).await?;
+    Ok(client)
+}
+
+#[tokio::test]
+async fn handshake_with_a_trusted_certificate() {
+    let pki = Pki::generate();
+    let server = TestServer::with_config(pki.config(false)).await;
+
+    let mut client = connect(&server, &pki.trusting()).await.unwrap();
+    client.set("key", "value".into()).await.unwrap();
+    assert_eq!(client.get("key").await.unwrap().unwrap(), "value");
+
+    let tls = TlsConfig::new().add_ca_file(&pki.path("ca.pem")).unwrap();
+    assert!(Client::connect_tls(server.addr(), "db.example.com", &tls).await.is_err());
+}
+
+#[tokio::test]
+async fn untrusted_certificates_are_rejected() {
+    let pki = Pki::generate();
+    let server = TestServer::with_config(pki.config(false)).await;
+
+    let other = Pki::generate();
+    assert!(connect(&server, &other.trusting()).await.is_err());
+    assert!(connect(&server, &TlsConfig::new()).await.is_err());
+
+    // The server still serves trusted clients after failed handshakes.
+    connect(&server, &pki.trusting()).await.unwrap();
+}
+
+#[tokio::test]
+async fn client_certificates_are_verified() {
+    let pki = Pki::generate();
+    let server = TestServer::with_config(pki.config(true)).await;
+
+    assert!(connect(&server, &pki.trusting()).await.is_err());
+
+    let other = Pki::generate();
+    let foreign = pki.trusting().with_client_cert(other.client_cert.as_bytes(), other.client_key.as_bytes()).unwrap();
+    assert!(connect(&server, &foreign).await.is_err());
+
+    let tls = pki.trusting().with_client_cert(pki.client_cert.as_bytes(), pki.client_key.as_bytes()).unwrap();
+    let mut client = connect(&server, &tls).await.unwrap();
+    client.set("key", "value".into()).await.unwrap();
+}
+
+#[tokio::test]
+async fn plaintext_clients_are_not_served() {
+    let pki = Pki::generate();
+    let server = TestServer::with_config(pki.config(false)).await;
+
+    let mut client = server.client().await;
+    assert!(client.ping(None).await.is_err());
+}
+
+#[tokio::test]
+async fn stalled_handshakes_release_their_connection_slot() {
+    let pki = Pki::generate();
+    let config = Config {
+        maxclients: 1,
+        ..pki.config(false)
+    };
+    let server = TestServer::with_config(config).await;
+
+    // Takes the only slot without ever sending a ClientHello.
+    let _stalled = tokio::net::TcpStream::connect(server.addr()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let deadline = HANDSHAKE_TIMEOUT + Duration::from_secs(5);
+    tokio::time::timeout(deadline, connect(&server, &pki.trusting())).await.expect("slot never released").unwrap();
+}
+
+#[tokio::test]
+async fn replicas_follow_a_tls_master() {
+    let pki = Pki::generate();
+    let master = TestServer::with_config(pki.config(true)).await;
+    let replica = TestServer::with_config(pki.replica_config()).await;
+
+    let tls = pki.trusting().with_client_cert(pki.client_cert.as_bytes(), pki.client_key.as_bytes()).unwrap();
+    let mut writer = connect(&master, &tls).await.unwrap();
+    let mut reader = replica.client().await;
+
+    writer.set("before", "1".into()).await.unwrap();
+    reader.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+    writer.set("after", "2".into()).await.unwrap();
+
+    let synced = async {
+        while reader.get("after").await.unwrap().is_none() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), synced).await.expect("replica never synced over TLS");
+    assert_eq!(reader.get("before").await.unwrap().unwrap(), "1");
+
+    // Without the replication TLS settings the link never comes up.
+    let plaintext = TestServer::start().await;
+    let mut other = plaintext.client().await;
+    other.replicaof(Some(("127.0.0.1", master.port()))).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(other.get("before").await.unwrap().is_none());
+}
@@ -0,0 +1,168 @@
+use db_proto::clients::{CachingClient, Client};
+use db_proto::pkg::tracking::Tracking;
+use db_proto::pkg::Frame;
+use db_proto::Error;
+use db_tests::{command, TestServer};
+use std::time::Duration;
+use tokio::time::{self, timeout};
+
+/// Subscribes a fresh connection to the invalidation channel and turns on
+/// tracking for `tracker`, redirected to it.
+async fn listener(server: &TestServer, tracker: &mut Client) -> Client {
+    let mut listener = server.client().await;
+    let id = listener.client_id().await.unwrap();
+
+    listener.execute(command(&["subscribe", "__redis__:invalidate"])).await.unwrap();
+    tracker.client_tracking(true, Some(id)).await.unwrap();
+
+    listener
+}
+
+/// The keys of the next invalidation message, or `None` for a flush.
+async fn invalidated(listener: &mut Client) -> Option<Vec<String>> {
+    let frame = timeout(Duration::from_secs(5), listener.read_frame()).await.expect("no invalidation").unwrap().unwrap();
+
+    match frame {
+        Frame::Array(parts) => match &parts[..] {
+            [kind, channel, Frame::Array(keys)] if *kind == "message" && *channel == "__redis__:invalidate" => Some(keys.iter().map(ToString::to_string).collect()),
+            [kind, _, Frame::Null] if *kind == "message" => None,
+            _ => panic!("unexpected message {:?}", parts),
+        },
+        frame => panic!("unexpected frame {:?}", frame),
+    }
+}
+
+async fn nothing_pending(listener: &mut Client) { assert!(timeout(Duration::from_millis(100), listener.read_frame()).await.is_err()); }
+
+#[tokio::test]
+async fn writes_invalidate_keys_that_were_read() {
+    let server = TestServer::start().await;
+    let mut tracker = server.client().await;
+    let mut writer = server.client().await;
+    let mut listener = listener(&server, &mut tracker).await;
+
+    tracker.get("read").await.unwrap();
+    writer.set("unread", "1".into()).await.unwrap();
+    writer.set("read", "1".into()).await.unwrap();
+    assert_eq!(invalidated(&mut listener).await, Some(vec!["read".to_string()]));
+
+    // Tracking is one-shot: the key must be read again to be invalidated again.
+    writer.set("read", "2".into()).await.unwrap();
+    nothing_pending(&mut listener).await;
+
+    tracker.get("read").await.unwrap();
+    tracker.set("read", "3".into()).await.unwrap();
+    assert_eq!(invalidated(&mut listener).await, Some(vec!["read".to_string()]));
+
+    tracker.get("read").await.unwrap();
+    tracker.client_tracking(false, None).await.unwrap();
+    writer.set("read", "4".into()).await.unwrap();
+    nothing_pending(&mut listener).await;
+}
+
+#[tokio::test]
+async fn expiry_and_flush_invalidate() {
+    let server = TestServer::start().await;
+    let mut tracker = server.client().await;
+    let mut listener = listener(&server, &mut tracker).await;
+
+    tracker.set_expires("volatile", "v".into(), Duration::from_millis(50)).await.unwrap();
+    tracker.get("volatile").await.unwrap();
+    assert_eq!(invalidated(&mut listener).await, Some(vec!["volatile".to_string()]));
+
+    tracker.set("key", "v".into()).await.unwrap();
+    tracker.get("key").await.unwrap();
+    tracker.flushdb(false).await.unwrap();
+    assert_eq!(invalidated(&mut listener).await, None);
+}
+
+#[tokio::test]
+async fn tracking_requires_a_redirect_target() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert!(matches!(client.client_tracking(true, None).await, Err(Error::Err(_))));
+    assert!(matches!(client.client_tracking(true, Some(9999)).await, Err(Error::Err(msg)) if msg.contains("does not exist")));
+    assert!(matches!(client.execute(command(&["client", "tracking", "maybe"])).await, Err(Error::Err(msg)) if msg == "syntax error"));
+}
+
+#[test]
+fn stopped_clients_release_their_keys() {
+    let tracking = Tracking::new();
+    tracking.enable(1, 10);
+    tracking.enable(2, 10);
+
+    tracking.track(1, &["shared", "mine"]);
+    tracking.track(2, &["shared", "theirs"]);
+    assert_eq!(tracking.tracked_keys(), 3);
+
+    tracking.disable(1);
+    assert_eq!(tracking.tracked_keys(), 2);
+
+    tracking.invalidate("theirs");
+    tracking.forget(2);
+    assert_eq!(tracking.tracked_keys(), 0);
+}
+
+#[tokio::test]
+async fn caching_client_serves_reads_until_invalidated() {
+    let server = TestServer::start().await;
+    let mut writer = server.client().await;
+    writer.set("hot", "1".into()).await.unwrap();
+
+    let mut cache = CachingClient::connect(server.addr()).await.unwrap();
+    assert_eq!(cache.get("hot").await.unwrap().unwrap(), "1");
+    assert_eq!(cache.get("missing").await.unwrap(), None);
+    assert_eq!(cache.cached(), 2);
+
+    let hits = keyspace_hits(&mut writer).await;
+
+    for _ in 0..100 {
+        assert_eq!(cache.get("hot").await.unwrap().unwrap(), "1");
+    }
+
+    assert_eq!(keyspace_hits(&mut writer).await, hits);
+
+    writer.set("hot", "2".into()).await.unwrap();
+    wait_until(|| cache.cached() == 1).await;
+    assert_eq!(cache.get("hot").await.unwrap().unwrap(), "2");
+
+    cache.set("hot", "3".into()).await.unwrap();
+    assert_eq!(cache.get("hot").await.unwrap().unwrap(), "3");
+    assert_eq!(writer.get("hot").await.unwrap().unwrap(), "3");
+
+    writer.flushall(false).await.unwrap();
+    wait_until(|| cache.cached() == 0).await;
+    assert_eq!(cache.get("hot").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn caching_client_respects_capacity() {
+    let server = TestServer::start().await;
+    let mut cache = CachingClient::connect(server.addr()).await.unwrap();
+    cache.set_capacity(10);
+
+    for i in 0..50 {
+        cache.get(&format!("key:{}", i)).await.unwrap();
+    }
+
+    assert_eq!(cache.cached(), 10);
+}
+
+async fn keyspace_hits(client: &mut Client) -> u64 {
+    let info = client.info(Some("stats")).await.unwrap();
+    let line = info.lines().find(|line| line.starts_with("keyspace_hits:")).unwrap();
+    line["keyspace_hits:".len()..].parse().unwrap()
+}
+
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    for _ in 0..500 {
+        if condition() {
+            return;
+        }
+
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("condition not reached");
+}
@@ -0,0 +1,72 @@
+use db_proto::clients::{Bincode, Codec, Json, MessagePack};
+use db_proto::pkg::Frame;
+use db_tests::{raw, TestServer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Session {
+    user: String,
+    visits: u32,
+    roles: Vec<String>,
+    attributes: BTreeMap<String, i64>,
+    last_seen: Option<u64>,
+}
+
+fn session() -> Session {
+    Session {
+        user: "ada".to_string(),
+        visits: 42,
+        roles: vec!["admin".to_string(), "ops".to_string()],
+        attributes: BTreeMap::from([("quota".to_string(), -1), ("tier".to_string(), 3)]),
+        last_seen: Some(1_700_000_000),
+    }
+}
+
+async fn round_trip(codec: impl Codec + Copy) {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set_as("session", &session(), codec).await.unwrap();
+    assert_eq!(client.get_as::<Session>("session", codec).await.unwrap(), Some(session()));
+    assert_eq!(client.get_as::<Session>("missing", codec).await.unwrap(), None);
+
+    client.set_as("numbers", &[1u8, 2, 3][..], codec).await.unwrap();
+    assert_eq!(client.get_as::<Vec<u8>>("numbers", codec).await.unwrap(), Some(vec![1, 2, 3]));
+
+    client.set_expires_as("volatile", &session(), Duration::from_secs(100), codec).await.unwrap();
+    assert!(client.pttl("volatile").await.unwrap().is_some());
+    assert_eq!(client.get_as::<Session>("volatile", codec).await.unwrap(), Some(session()));
+}
+
+#[tokio::test]
+async fn json_round_trip() {
+    round_trip(Json).await;
+
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set_as("session", &session(), Json).await.unwrap();
+    let stored = raw(&mut client, &["get", "session"]).await;
+    assert!(matches!(stored, Frame::Bulk(json) if json.starts_with(b"{\"user\":\"ada\"")));
+}
+
+#[tokio::test]
+async fn bincode_round_trip() { round_trip(Bincode).await; }
+
+#[tokio::test]
+async fn message_pack_round_trip() { round_trip(MessagePack).await; }
+
+#[tokio::test]
+async fn decoding_with_the_wrong_type_fails() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    client.set("plain", "not json".into()).await.unwrap();
+    assert!(client.get_as::<Session>("plain", Json).await.is_err());
+
+    client.set_as("count", &7u32, MessagePack).await.unwrap();
+    assert!(client.get_as::<Session>("count", MessagePack).await.is_err());
+    assert_eq!(client.get("count").await.unwrap().unwrap(), &[7u8][..]);
+}
@@ -0,0 +1,71 @@
+use db_proto::clients::Client;
+use db_server::Config;
+use db_tests::TestServer;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::time::Duration;
+
+fn unix(path: &Path, perm: Option<u32>) -> Config {
+    Config {
+        unixsocket: Some(path.to_path_buf()),
+        unixsocketperm: perm,
+        ..Config::default()
+    }
+}
+
+/// The unix listener is bound during startup, after the TCP listener.
+async fn connect(path: &Path) -> Client {
+    for _ in 0..100 {
+        if let Ok(client) = Client::connect_unix(path).await {
+            return client;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("unix socket {:?} never became connectable", path);
+}
+
+#[tokio::test]
+async fn serves_commands_with_configured_permissions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.sock");
+
+    let server = TestServer::with_config(unix(&path, Some(0o700))).await;
+    let mut client = connect(&path).await;
+
+    client.set("key", "value".into()).await.unwrap();
+    assert_eq!(server.client().await.get("key").await.unwrap().unwrap(), "value");
+
+    let meta = std::fs::symlink_metadata(&path).unwrap();
+    assert!(meta.file_type().is_socket());
+    assert_eq!(meta.permissions().mode() & 0o777, 0o700);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    drop(client);
+    server.stop().await;
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn replaces_a_stale_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.sock");
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+    let _server = TestServer::with_config(unix(&path, None)).await;
+    assert_eq!(connect(&path).await.ping(None).await.unwrap(), "PONG");
+}
+
+#[tokio::test]
+async fn leaves_other_files_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.sock");
+    std::fs::write(&path, "not a socket").unwrap();
+
+    let _server = TestServer::with_config(unix(&path, None)).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+    assert!(Client::connect_unix(&path).await.is_err());
+}